@@ -2,6 +2,29 @@ pub use sea_orm_migration::prelude::*;
 
 mod m20250408_000001_create_sites_table;
 mod m20250408_000002_create_site_urls_table;
+mod m20250415_000003_create_groups_table;
+mod m20250415_000004_create_group_sites_table;
+mod m20250415_000005_create_runs_table;
+mod m20250415_000006_create_reports_table;
+mod m20250422_000007_add_deleted_at_to_sites;
+mod m20250422_000008_create_site_audit_log_table;
+mod m20250429_000009_add_metrics_to_reports;
+mod m20250506_000010_add_samples_to_runs;
+mod m20250506_000011_create_regressions_table;
+mod m20250513_000012_add_total_byte_weight_to_reports;
+mod m20250513_000013_create_budgets_table;
+mod m20250513_000014_create_budget_results_table;
+mod m20250520_000015_create_webhooks_table;
+mod m20250520_000016_create_webhook_deliveries_table;
+mod m20250527_000017_create_digest_recipients_table;
+mod m20250527_000018_create_digests_table;
+mod m20250603_000019_create_api_keys_table;
+mod m20250610_000020_create_users_table;
+mod m20250610_000021_create_teams_table;
+mod m20250610_000022_create_team_members_table;
+mod m20250610_000023_create_sessions_table;
+mod m20250610_000024_add_team_id_to_sites;
+mod m20250617_000025_create_audit_jobs_table;
 
 pub struct Migrator;
 
@@ -11,6 +34,29 @@ impl MigratorTrait for Migrator {
         vec![
             Box::new(m20250408_000001_create_sites_table::Migration),
             Box::new(m20250408_000002_create_site_urls_table::Migration),
+            Box::new(m20250415_000003_create_groups_table::Migration),
+            Box::new(m20250415_000004_create_group_sites_table::Migration),
+            Box::new(m20250415_000005_create_runs_table::Migration),
+            Box::new(m20250415_000006_create_reports_table::Migration),
+            Box::new(m20250422_000007_add_deleted_at_to_sites::Migration),
+            Box::new(m20250422_000008_create_site_audit_log_table::Migration),
+            Box::new(m20250429_000009_add_metrics_to_reports::Migration),
+            Box::new(m20250506_000010_add_samples_to_runs::Migration),
+            Box::new(m20250506_000011_create_regressions_table::Migration),
+            Box::new(m20250513_000012_add_total_byte_weight_to_reports::Migration),
+            Box::new(m20250513_000013_create_budgets_table::Migration),
+            Box::new(m20250513_000014_create_budget_results_table::Migration),
+            Box::new(m20250520_000015_create_webhooks_table::Migration),
+            Box::new(m20250520_000016_create_webhook_deliveries_table::Migration),
+            Box::new(m20250527_000017_create_digest_recipients_table::Migration),
+            Box::new(m20250527_000018_create_digests_table::Migration),
+            Box::new(m20250603_000019_create_api_keys_table::Migration),
+            Box::new(m20250610_000020_create_users_table::Migration),
+            Box::new(m20250610_000021_create_teams_table::Migration),
+            Box::new(m20250610_000022_create_team_members_table::Migration),
+            Box::new(m20250610_000023_create_sessions_table::Migration),
+            Box::new(m20250610_000024_add_team_id_to_sites::Migration),
+            Box::new(m20250617_000025_create_audit_jobs_table::Migration),
         ]
     }
 }
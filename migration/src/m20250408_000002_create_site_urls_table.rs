@@ -42,7 +42,7 @@ impl MigrationTrait for Migration {
 }
 
 #[derive(DeriveIden)]
-enum SiteUrls {
+pub enum SiteUrls {
     Table,
     Id,
     SiteId,
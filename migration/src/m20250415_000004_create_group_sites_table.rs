@@ -0,0 +1,55 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20250408_000001_create_sites_table::Sites;
+use super::m20250415_000003_create_groups_table::Groups;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(GroupSites::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(GroupSites::GroupId).integer().not_null())
+                    .col(ColumnDef::new(GroupSites::SiteId).integer().not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(GroupSites::GroupId)
+                            .col(GroupSites::SiteId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-group-site-group_id")
+                            .from(GroupSites::Table, GroupSites::GroupId)
+                            .to(Groups::Table, Groups::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-group-site-site_id")
+                            .from(GroupSites::Table, GroupSites::SiteId)
+                            .to(Sites::Table, Sites::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(GroupSites::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum GroupSites {
+    Table,
+    GroupId,
+    SiteId,
+}
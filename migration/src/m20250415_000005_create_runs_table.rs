@@ -0,0 +1,55 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use super::m20250415_000003_create_groups_table::Groups;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Runs::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Runs::Id))
+                    .col(ColumnDef::new(Runs::GroupId).integer().null())
+                    .col(string_len(Runs::Status, 16).not_null())
+                    .col(string_len(Runs::Strategy, 16).not_null())
+                    .col(
+                        ColumnDef::new(Runs::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(ColumnDef::new(Runs::FinishedAt).timestamp().null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-run-group_id")
+                            .from(Runs::Table, Runs::GroupId)
+                            .to(Groups::Table, Groups::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Runs::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Runs {
+    Table,
+    Id,
+    GroupId,
+    Status,
+    Strategy,
+    CreatedAt,
+    FinishedAt,
+}
@@ -0,0 +1,83 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use super::m20250408_000001_create_sites_table::Sites;
+use super::m20250408_000002_create_site_urls_table::SiteUrls;
+use super::m20250415_000005_create_runs_table::Runs;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Reports::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Reports::Id))
+                    .col(ColumnDef::new(Reports::RunId).integer().not_null())
+                    .col(ColumnDef::new(Reports::SiteId).integer().not_null())
+                    .col(ColumnDef::new(Reports::SiteUrlId).integer().not_null())
+                    .col(string(Reports::Url).not_null())
+                    .col(string_len(Reports::Strategy, 16).not_null())
+                    .col(ColumnDef::new(Reports::Performance).double().null())
+                    .col(ColumnDef::new(Reports::Accessibility).double().null())
+                    .col(ColumnDef::new(Reports::BestPractices).double().null())
+                    .col(ColumnDef::new(Reports::Seo).double().null())
+                    .col(ColumnDef::new(Reports::Data).json_binary().not_null())
+                    .col(
+                        ColumnDef::new(Reports::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-report-run_id")
+                            .from(Reports::Table, Reports::RunId)
+                            .to(Runs::Table, Runs::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-report-site_id")
+                            .from(Reports::Table, Reports::SiteId)
+                            .to(Sites::Table, Sites::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-report-site_url_id")
+                            .from(Reports::Table, Reports::SiteUrlId)
+                            .to(SiteUrls::Table, SiteUrls::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Reports::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Reports {
+    Table,
+    Id,
+    RunId,
+    SiteId,
+    SiteUrlId,
+    Url,
+    Strategy,
+    Performance,
+    Accessibility,
+    BestPractices,
+    Seo,
+    Data,
+    CreatedAt,
+}
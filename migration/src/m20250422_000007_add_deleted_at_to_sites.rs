@@ -0,0 +1,36 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20250408_000001_create_sites_table::Sites;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Sites::Table)
+                    .add_column(ColumnDef::new(SoftDelete::DeletedAt).timestamp().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Sites::Table)
+                    .drop_column(SoftDelete::DeletedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SoftDelete {
+    DeletedAt,
+}
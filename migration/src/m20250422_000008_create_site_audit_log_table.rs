@@ -0,0 +1,61 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // No foreign key on site_id so the history outlives a purged site
+        manager
+            .create_table(
+                Table::create()
+                    .table(SiteAuditLog::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SiteAuditLog::Id))
+                    .col(ColumnDef::new(SiteAuditLog::SiteId).integer().not_null())
+                    .col(string_len(SiteAuditLog::Action, 32).not_null())
+                    .col(string(SiteAuditLog::Actor).not_null())
+                    .col(
+                        ColumnDef::new(SiteAuditLog::Changes)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(SiteAuditLog::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-site_audit_log-site_id")
+                    .table(SiteAuditLog::Table)
+                    .col(SiteAuditLog::SiteId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SiteAuditLog::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SiteAuditLog {
+    Table,
+    Id,
+    SiteId,
+    Action,
+    Actor,
+    Changes,
+    CreatedAt,
+}
@@ -0,0 +1,79 @@
+use sea_orm_migration::prelude::*;
+
+use super::m20250415_000006_create_reports_table::Reports;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One statement per column, SQLite cannot add several in a single ALTER
+        for metric in Metric::ALL {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Reports::Table)
+                        .add_column(ColumnDef::new(metric).double().null())
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-reports-site_url_id-created_at")
+                    .table(Reports::Table)
+                    .col(Reports::SiteUrlId)
+                    .col(Reports::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-reports-site_url_id-created_at")
+                    .table(Reports::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        for metric in Metric::ALL {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Reports::Table)
+                        .drop_column(metric)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden, Clone, Copy)]
+enum Metric {
+    Lcp,
+    Cls,
+    Inp,
+    Tbt,
+    Fcp,
+    Ttfb,
+}
+
+impl Metric {
+    const ALL: [Metric; 6] = [
+        Metric::Lcp,
+        Metric::Cls,
+        Metric::Inp,
+        Metric::Tbt,
+        Metric::Fcp,
+        Metric::Ttfb,
+    ];
+}
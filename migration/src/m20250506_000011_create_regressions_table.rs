@@ -0,0 +1,102 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use super::m20250408_000001_create_sites_table::Sites;
+use super::m20250408_000002_create_site_urls_table::SiteUrls;
+use super::m20250415_000005_create_runs_table::Runs;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Regressions::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Regressions::Id))
+                    .col(ColumnDef::new(Regressions::RunId).integer().not_null())
+                    .col(
+                        ColumnDef::new(Regressions::PreviousRunId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Regressions::SiteId).integer().not_null())
+                    .col(ColumnDef::new(Regressions::SiteUrlId).integer().not_null())
+                    .col(string(Regressions::Url).not_null())
+                    .col(string_len(Regressions::Strategy, 16).not_null())
+                    .col(string_len(Regressions::Metric, 32).not_null())
+                    .col(
+                        ColumnDef::new(Regressions::PreviousValue)
+                            .double()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Regressions::CurrentValue)
+                            .double()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Regressions::Tolerance).double().not_null())
+                    .col(
+                        ColumnDef::new(Regressions::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-regression-run_id")
+                            .from(Regressions::Table, Regressions::RunId)
+                            .to(Runs::Table, Runs::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-regression-previous_run_id")
+                            .from(Regressions::Table, Regressions::PreviousRunId)
+                            .to(Runs::Table, Runs::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-regression-site_id")
+                            .from(Regressions::Table, Regressions::SiteId)
+                            .to(Sites::Table, Sites::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-regression-site_url_id")
+                            .from(Regressions::Table, Regressions::SiteUrlId)
+                            .to(SiteUrls::Table, SiteUrls::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Regressions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Regressions {
+    Table,
+    Id,
+    RunId,
+    PreviousRunId,
+    SiteId,
+    SiteUrlId,
+    Url,
+    Strategy,
+    Metric,
+    PreviousValue,
+    CurrentValue,
+    Tolerance,
+    CreatedAt,
+}
@@ -0,0 +1,57 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use super::m20250408_000001_create_sites_table::Sites;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Budgets::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Budgets::Id))
+                    .col(ColumnDef::new(Budgets::SiteId).integer().not_null())
+                    .col(ColumnDef::new(Budgets::Pattern).string().null())
+                    .col(string_len(Budgets::Metric, 32).not_null())
+                    .col(ColumnDef::new(Budgets::Min).double().null())
+                    .col(ColumnDef::new(Budgets::Max).double().null())
+                    .col(
+                        ColumnDef::new(Budgets::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-budget-site_id")
+                            .from(Budgets::Table, Budgets::SiteId)
+                            .to(Sites::Table, Sites::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Budgets::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Budgets {
+    Table,
+    Id,
+    SiteId,
+    Pattern,
+    Metric,
+    Min,
+    Max,
+    CreatedAt,
+}
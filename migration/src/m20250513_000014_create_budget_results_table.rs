@@ -0,0 +1,79 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use super::m20250408_000001_create_sites_table::Sites;
+use super::m20250415_000005_create_runs_table::Runs;
+use super::m20250415_000006_create_reports_table::Reports;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BudgetResults::Table)
+                    .if_not_exists()
+                    .col(pk_auto(BudgetResults::Id))
+                    .col(ColumnDef::new(BudgetResults::RunId).integer().not_null())
+                    .col(ColumnDef::new(BudgetResults::ReportId).integer().not_null())
+                    .col(ColumnDef::new(BudgetResults::SiteId).integer().not_null())
+                    .col(string(BudgetResults::Url).not_null())
+                    .col(boolean(BudgetResults::Passed).not_null())
+                    .col(
+                        ColumnDef::new(BudgetResults::Failures)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(BudgetResults::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-budget_result-run_id")
+                            .from(BudgetResults::Table, BudgetResults::RunId)
+                            .to(Runs::Table, Runs::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-budget_result-report_id")
+                            .from(BudgetResults::Table, BudgetResults::ReportId)
+                            .to(Reports::Table, Reports::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-budget_result-site_id")
+                            .from(BudgetResults::Table, BudgetResults::SiteId)
+                            .to(Sites::Table, Sites::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BudgetResults::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum BudgetResults {
+    Table,
+    Id,
+    RunId,
+    ReportId,
+    SiteId,
+    Url,
+    Passed,
+    Failures,
+    CreatedAt,
+}
@@ -0,0 +1,48 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Webhooks::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Webhooks::Id))
+                    .col(string(Webhooks::Url).not_null())
+                    .col(string_len(Webhooks::Format, 16).not_null())
+                    .col(ColumnDef::new(Webhooks::Secret).string().null())
+                    .col(ColumnDef::new(Webhooks::Events).json_binary().not_null())
+                    .col(boolean(Webhooks::Active).not_null().default(true))
+                    .col(
+                        ColumnDef::new(Webhooks::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Webhooks::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+pub enum Webhooks {
+    Table,
+    Id,
+    Url,
+    Format,
+    Secret,
+    Events,
+    Active,
+    CreatedAt,
+}
@@ -0,0 +1,73 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use super::m20250520_000015_create_webhooks_table::Webhooks;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(WebhookDeliveries::Table)
+                    .if_not_exists()
+                    .col(pk_auto(WebhookDeliveries::Id))
+                    .col(
+                        ColumnDef::new(WebhookDeliveries::WebhookId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(string_len(WebhookDeliveries::Event, 32).not_null())
+                    .col(
+                        ColumnDef::new(WebhookDeliveries::Payload)
+                            .json_binary()
+                            .not_null(),
+                    )
+                    .col(integer(WebhookDeliveries::Attempts).not_null())
+                    .col(
+                        ColumnDef::new(WebhookDeliveries::StatusCode)
+                            .integer()
+                            .null(),
+                    )
+                    .col(boolean(WebhookDeliveries::Success).not_null())
+                    .col(ColumnDef::new(WebhookDeliveries::Error).text().null())
+                    .col(
+                        ColumnDef::new(WebhookDeliveries::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-webhook_delivery-webhook_id")
+                            .from(WebhookDeliveries::Table, WebhookDeliveries::WebhookId)
+                            .to(Webhooks::Table, Webhooks::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(WebhookDeliveries::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum WebhookDeliveries {
+    Table,
+    Id,
+    WebhookId,
+    Event,
+    Payload,
+    Attempts,
+    StatusCode,
+    Success,
+    Error,
+    CreatedAt,
+}
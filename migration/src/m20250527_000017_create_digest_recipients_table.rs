@@ -0,0 +1,58 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use super::m20250415_000003_create_groups_table::Groups;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DigestRecipients::Table)
+                    .if_not_exists()
+                    .col(pk_auto(DigestRecipients::Id))
+                    .col(integer(DigestRecipients::GroupId).not_null())
+                    .col(string(DigestRecipients::Email).not_null())
+                    .col(
+                        ColumnDef::new(DigestRecipients::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-digest_recipient-group_id")
+                            .from(DigestRecipients::Table, DigestRecipients::GroupId)
+                            .to(Groups::Table, Groups::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .index(
+                        Index::create()
+                            .name("idx-digest_recipients-group_id-email")
+                            .col(DigestRecipients::GroupId)
+                            .col(DigestRecipients::Email)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DigestRecipients::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum DigestRecipients {
+    Table,
+    Id,
+    GroupId,
+    Email,
+    CreatedAt,
+}
@@ -0,0 +1,62 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use super::m20250415_000003_create_groups_table::Groups;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Digests::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Digests::Id))
+                    .col(integer(Digests::GroupId).not_null())
+                    .col(date(Digests::PeriodStart).not_null())
+                    .col(date(Digests::PeriodEnd).not_null())
+                    .col(integer(Digests::Recipients).not_null())
+                    .col(
+                        ColumnDef::new(Digests::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-digest-group_id")
+                            .from(Digests::Table, Digests::GroupId)
+                            .to(Groups::Table, Groups::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .index(
+                        Index::create()
+                            .name("idx-digests-group_id-period_start")
+                            .col(Digests::GroupId)
+                            .col(Digests::PeriodStart)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Digests::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Digests {
+    Table,
+    Id,
+    GroupId,
+    PeriodStart,
+    PeriodEnd,
+    Recipients,
+    CreatedAt,
+}
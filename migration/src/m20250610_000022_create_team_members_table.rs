@@ -0,0 +1,57 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use super::m20250610_000020_create_users_table::Users;
+use super::m20250610_000021_create_teams_table::Teams;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(TeamMembers::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(TeamMembers::TeamId).integer().not_null())
+                    .col(ColumnDef::new(TeamMembers::UserId).integer().not_null())
+                    .col(string_len(TeamMembers::Role, 16).not_null())
+                    .primary_key(
+                        Index::create()
+                            .col(TeamMembers::TeamId)
+                            .col(TeamMembers::UserId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-team-member-team_id")
+                            .from(TeamMembers::Table, TeamMembers::TeamId)
+                            .to(Teams::Table, Teams::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-team-member-user_id")
+                            .from(TeamMembers::Table, TeamMembers::UserId)
+                            .to(Users::Table, Users::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(TeamMembers::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum TeamMembers {
+    Table,
+    TeamId,
+    UserId,
+    Role,
+}
@@ -0,0 +1,66 @@
+use sea_orm_migration::{prelude::*, sea_orm::DatabaseBackend};
+
+use super::m20250408_000001_create_sites_table::Sites;
+use super::m20250610_000021_create_teams_table::Teams;
+
+const FOREIGN_KEY: &str = "fk-site-team_id";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Sites::Table)
+                    .add_column(ColumnDef::new(SiteTeam::TeamId).integer().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // SQLite can't add constraints to an existing table
+        if manager.get_database_backend() == DatabaseBackend::Sqlite {
+            return Ok(());
+        }
+
+        manager
+            .create_foreign_key(
+                ForeignKey::create()
+                    .name(FOREIGN_KEY)
+                    .from(Sites::Table, SiteTeam::TeamId)
+                    .to(Teams::Table, Teams::Id)
+                    .on_delete(ForeignKeyAction::SetNull)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != DatabaseBackend::Sqlite {
+            manager
+                .drop_foreign_key(
+                    ForeignKey::drop()
+                        .name(FOREIGN_KEY)
+                        .table(Sites::Table)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Sites::Table)
+                    .drop_column(SiteTeam::TeamId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SiteTeam {
+    TeamId,
+}
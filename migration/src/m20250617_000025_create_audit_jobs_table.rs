@@ -0,0 +1,106 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use super::m20250408_000001_create_sites_table::Sites;
+use super::m20250415_000005_create_runs_table::Runs;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuditJobs::Table)
+                    .if_not_exists()
+                    .col(pk_auto(AuditJobs::Id))
+                    .col(integer(AuditJobs::RunId).not_null())
+                    .col(integer(AuditJobs::SiteId).not_null())
+                    .col(string_len(AuditJobs::Kind, 16).not_null())
+                    .col(ColumnDef::new(AuditJobs::Url).string().null())
+                    .col(string_len(AuditJobs::Strategy, 16).not_null())
+                    .col(ColumnDef::new(AuditJobs::Sample).integer().null())
+                    .col(string_len(AuditJobs::Status, 16).not_null())
+                    .col(integer(AuditJobs::Attempts).not_null().default(0))
+                    .col(
+                        ColumnDef::new(AuditJobs::AvailableAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(AuditJobs::LockedUntil).timestamp().null())
+                    .col(ColumnDef::new(AuditJobs::LockedBy).string_len(64).null())
+                    .col(ColumnDef::new(AuditJobs::LastError).text().null())
+                    .col(
+                        ColumnDef::new(AuditJobs::CreatedAt)
+                            .timestamp()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(ColumnDef::new(AuditJobs::FinishedAt).timestamp().null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-audit_job-run_id")
+                            .from(AuditJobs::Table, AuditJobs::RunId)
+                            .to(Runs::Table, Runs::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk-audit_job-site_id")
+                            .from(AuditJobs::Table, AuditJobs::SiteId)
+                            .to(Sites::Table, Sites::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    // Crawls have no url or sample, so only audits are deduplicated
+                    .index(
+                        Index::create()
+                            .name("idx-audit_jobs-run_id-url-strategy-sample")
+                            .col(AuditJobs::RunId)
+                            .col(AuditJobs::Url)
+                            .col(AuditJobs::Strategy)
+                            .col(AuditJobs::Sample)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-audit_jobs-status-available_at")
+                    .table(AuditJobs::Table)
+                    .col(AuditJobs::Status)
+                    .col(AuditJobs::AvailableAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuditJobs::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AuditJobs {
+    Table,
+    Id,
+    RunId,
+    SiteId,
+    Kind,
+    Url,
+    Strategy,
+    Sample,
+    Status,
+    Attempts,
+    AvailableAt,
+    LockedUntil,
+    LockedBy,
+    LastError,
+    CreatedAt,
+    FinishedAt,
+}
@@ -0,0 +1,20 @@
+#![cfg(feature = "sqlite")]
+
+use migration::{Migrator, MigratorTrait};
+use sea_orm::Database;
+use sea_orm_migration::sea_orm;
+
+// Every migration has to apply and roll back on SQLite as well as Postgres
+#[async_std::test]
+async fn migrates_up_and_down() {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+
+    Migrator::up(&db, None).await.unwrap();
+    assert!(Migrator::get_pending_migrations(&db)
+        .await
+        .unwrap()
+        .is_empty());
+
+    Migrator::down(&db, None).await.unwrap();
+    Migrator::up(&db, None).await.unwrap();
+}
@@ -0,0 +1,200 @@
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter,
+};
+use sha2::Sha256;
+use std::sync::LazyLock;
+
+use crate::{
+    auth::{generate_key, hash_key},
+    entities::{
+        sessions::{self, Entity as Sessions},
+        team_members::{self, Entity as TeamMembers},
+        users::{self, Entity as Users},
+    },
+};
+
+const SCHEME: &str = "pbkdf2-sha256";
+// OWASP's recommendation for PBKDF2-HMAC-SHA256
+const ITERATIONS: u32 = 600_000;
+const SALT_LEN: usize = 16;
+
+/// Salted PBKDF2 hash as `pbkdf2-sha256$<iterations>$<salt>$<hash>`.
+pub fn hash_password(password: &str) -> String {
+    hash_password_with(password, ITERATIONS)
+}
+
+/// `hash_password` with a custom work factor, for tests and fixtures.
+pub fn hash_password_with(password: &str, iterations: u32) -> String {
+    let mut salt = [0u8; SALT_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+
+    let hash = pbkdf2(password.as_bytes(), &salt, iterations);
+    format!(
+        "{SCHEME}${iterations}${}${}",
+        hex::encode(salt),
+        hex::encode(hash)
+    )
+}
+
+// Checked when a user has no password hash, so unknown emails take as long to
+// reject as wrong passwords
+static DUMMY_HASH: LazyLock<String> = LazyLock::new(|| hash_password(""));
+
+/// `verify_password` for a login, `false` without a stored hash after the
+/// same amount of work.
+pub fn verify_login(password: &str, stored: Option<&str>) -> bool {
+    match stored {
+        Some(stored) => verify_password(password, stored),
+        None => {
+            verify_password(password, &DUMMY_HASH);
+            false
+        }
+    }
+}
+
+pub fn verify_password(password: &str, stored: &str) -> bool {
+    let parts: Vec<&str> = stored.split('$').collect();
+    let [SCHEME, iterations, salt, hash] = parts[..] else {
+        return false;
+    };
+    let (Ok(iterations), Ok(salt), Ok(hash)) =
+        (iterations.parse(), hex::decode(salt), hex::decode(hash))
+    else {
+        return false;
+    };
+
+    let derived = pbkdf2(password.as_bytes(), &salt, iterations);
+    // Constant time so the comparison doesn't leak how much of the hash matched
+    hash.len() == derived.len()
+        && hash
+            .iter()
+            .zip(derived)
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+// A single 32 byte block of PBKDF2-HMAC-SHA256 (RFC 8018)
+fn pbkdf2(password: &[u8], salt: &[u8], iterations: u32) -> [u8; 32] {
+    let keyed = Hmac::<Sha256>::new_from_slice(password).expect("HMAC accepts keys of any size");
+
+    let mut mac = keyed.clone();
+    mac.update(salt);
+    mac.update(&1u32.to_be_bytes());
+    let mut block: [u8; 32] = mac.finalize().into_bytes().into();
+    let mut output = block;
+
+    for _ in 1..iterations {
+        let mut mac = keyed.clone();
+        mac.update(&block);
+        block = mac.finalize().into_bytes().into();
+        output.iter_mut().zip(block).for_each(|(out, b)| *out ^= b);
+    }
+
+    output
+}
+
+/// Starts a session for the user, returning the token for the cookie.
+pub async fn create_session(
+    db: &DatabaseConnection,
+    user_id: i32,
+    hours: i64,
+) -> Result<String, DbErr> {
+    let token = generate_key();
+
+    sessions::ActiveModel {
+        user_id: Set(user_id),
+        token_hash: Set(hash_key(&token)),
+        expires_at: Set((Utc::now() + Duration::hours(hours)).naive_utc()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    Ok(token)
+}
+
+/// The user of an unexpired session.
+pub async fn session_user(
+    db: &DatabaseConnection,
+    token: &str,
+) -> Result<Option<users::Model>, DbErr> {
+    let Some(session) = Sessions::find()
+        .filter(sessions::Column::TokenHash.eq(hash_key(token)))
+        .filter(sessions::Column::ExpiresAt.gt(Utc::now().naive_utc()))
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    Users::find_by_id(session.user_id).one(db).await
+}
+
+pub async fn end_session(db: &DatabaseConnection, token: &str) -> Result<(), DbErr> {
+    Sessions::delete_many()
+        .filter(sessions::Column::TokenHash.eq(hash_key(token)))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn memberships(
+    db: &DatabaseConnection,
+    user_id: i32,
+) -> Result<Vec<team_members::Model>, DbErr> {
+    TeamMembers::find()
+        .filter(team_members::Column::UserId.eq(user_id))
+        .all(db)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    mod pbkdf2 {
+        use super::super::*;
+
+        #[test]
+        fn matches_rfc_7914_vector() {
+            // PBKDF2-HMAC-SHA256 test vector from RFC 7914 section 11
+            let hash = pbkdf2(b"passwd", b"salt", 1);
+            assert_eq!(
+                hex::encode(hash),
+                "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc"
+            );
+        }
+    }
+
+    mod verify_password {
+        use super::super::*;
+
+        #[test]
+        fn accepts_only_the_password() {
+            let stored = hash_password_with("correct horse", 10);
+
+            assert!(stored.starts_with("pbkdf2-sha256$10$"));
+            assert!(verify_password("correct horse", &stored));
+            assert!(!verify_password("correct horse ", &stored));
+            assert_ne!(stored, hash_password_with("correct horse", 10));
+        }
+
+        #[test]
+        fn logins_need_a_stored_hash() {
+            let stored = hash_password_with("correct horse", 10);
+
+            assert!(verify_login("correct horse", Some(&stored)));
+            assert!(!verify_login("correct horse", None));
+        }
+
+        #[test]
+        fn rejects_malformed_hashes() {
+            assert!(!verify_password("secret", ""));
+            assert!(!verify_password("secret", "md5$1$00$00"));
+            assert!(!verify_password("secret", "pbkdf2-sha256$many$00$00"));
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+use url::Url;
+use utoipa::ToSchema;
+
+use crate::{
+    client::sitemaps::match_dynamic_url_pattern,
+    entities::{budgets, reports, sea_orm_active_enums::Metric},
+};
+
+/// A bound on one metric, optionally limited to urls matching a sitemap pattern.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[schema(as = NewBudget)]
+pub struct Budget {
+    pub pattern: Option<String>,
+    pub metric: Metric,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl Budget {
+    pub fn applies_to(&self, url: &Url) -> bool {
+        match &self.pattern {
+            Some(pattern) => match_dynamic_url_pattern(url, pattern).is_some(),
+            None => true,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        match (self.min, self.max) {
+            (None, None) => false,
+            (Some(min), Some(max)) => min <= max,
+            _ => true,
+        }
+    }
+}
+
+impl From<&budgets::Model> for Budget {
+    fn from(budget: &budgets::Model) -> Self {
+        Budget {
+            pattern: budget.pattern.clone(),
+            metric: budget.metric,
+            min: budget.min,
+            max: budget.max,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BudgetFailure {
+    pub metric: Metric,
+    pub pattern: Option<String>,
+    pub value: f64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl BudgetFailure {
+    /// The metric, its value and the bound it broke, e.g. `Lcp 3100 > 2500`.
+    pub fn describe(&self) -> String {
+        let bound = match (self.min, self.max) {
+            (Some(min), _) if self.value < min => format!("< {min}"),
+            (_, Some(max)) => format!("> {max}"),
+            _ => String::new(),
+        };
+        format!("{:?} {} {bound}", self.metric, self.value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BudgetOutcome {
+    pub passed: bool,
+    pub failures: Vec<BudgetFailure>,
+}
+
+/// `None` when no budget applies to the url. Metrics missing from the report
+/// cannot be judged so are left out rather than failed.
+pub fn evaluate(budgets: &[Budget], url: &Url, report: &reports::Model) -> Option<BudgetOutcome> {
+    let applicable: Vec<&Budget> = budgets
+        .iter()
+        .filter(|budget| budget.applies_to(url))
+        .collect();
+
+    if applicable.is_empty() {
+        return None;
+    }
+
+    let failures: Vec<BudgetFailure> = applicable
+        .into_iter()
+        .filter_map(|budget| {
+            let value = budget.metric.value(report)?;
+            let below = budget.min.is_some_and(|min| value < min);
+            let above = budget.max.is_some_and(|max| value > max);
+
+            (below || above).then(|| BudgetFailure {
+                metric: budget.metric,
+                pattern: budget.pattern.clone(),
+                value,
+                min: budget.min,
+                max: budget.max,
+            })
+        })
+        .collect();
+
+    Some(BudgetOutcome {
+        passed: failures.is_empty(),
+        failures,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    mod evaluate {
+        use super::super::*;
+        use crate::analysis::test_support::report as base;
+
+        fn report() -> reports::Model {
+            reports::Model {
+                url: "https://example.com/blog/post".to_string(),
+                performance: Some(0.72),
+                lcp: Some(2100.0),
+                total_byte_weight: Some(2_500_000.0),
+                ..base()
+            }
+        }
+
+        fn budget(
+            pattern: Option<&str>,
+            metric: Metric,
+            min: Option<f64>,
+            max: Option<f64>,
+        ) -> Budget {
+            Budget {
+                pattern: pattern.map(str::to_string),
+                metric,
+                min,
+                max,
+            }
+        }
+
+        #[test]
+        fn lists_failing_metrics() {
+            let url = Url::parse("https://example.com/blog/post").unwrap();
+            let budgets = vec![
+                budget(None, Metric::Lcp, None, Some(2500.0)),
+                budget(None, Metric::Performance, Some(0.8), None),
+                budget(None, Metric::TotalByteWeight, None, Some(2_000_000.0)),
+            ];
+
+            let outcome = evaluate(&budgets, &url, &report()).unwrap();
+            assert!(!outcome.passed);
+            let metrics: Vec<Metric> = outcome.failures.iter().map(|f| f.metric).collect();
+            assert_eq!(metrics, vec![Metric::Performance, Metric::TotalByteWeight]);
+        }
+
+        #[test]
+        fn only_applies_matching_patterns() {
+            let url = Url::parse("https://example.com/blog/post").unwrap();
+            let budgets = vec![
+                budget(Some("/blog/:slug"), Metric::Lcp, None, Some(2500.0)),
+                budget(Some("/products/:id"), Metric::Performance, Some(0.9), None),
+            ];
+
+            let outcome = evaluate(&budgets, &url, &report()).unwrap();
+            assert!(outcome.passed);
+
+            let other = Url::parse("https://example.com/about").unwrap();
+            assert!(evaluate(&budgets, &other, &report()).is_none());
+        }
+
+        #[test]
+        fn skips_metrics_missing_from_report() {
+            let url = Url::parse("https://example.com/blog/post").unwrap();
+            let budgets = vec![budget(None, Metric::Cls, None, Some(0.1))];
+
+            let outcome = evaluate(&budgets, &url, &report()).unwrap();
+            assert!(outcome.passed);
+        }
+
+        #[test]
+        fn budget_needs_a_bound() {
+            assert!(!budget(None, Metric::Lcp, None, None).is_valid());
+            assert!(!budget(None, Metric::Lcp, Some(3.0), Some(2.0)).is_valid());
+            assert!(budget(None, Metric::Lcp, None, Some(2500.0)).is_valid());
+        }
+    }
+}
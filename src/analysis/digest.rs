@@ -0,0 +1,253 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use sea_orm::DerivePartialModel;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+use crate::{
+    analysis::budgets::BudgetFailure,
+    client::psi::CategoryScores,
+    entities::{budget_results, reports, sites},
+};
+
+const WORST_PAGES: usize = 5;
+const BUDGET_FAILURES: usize = 10;
+
+/// The scores of a report a digest reads, selected without its PSI `data`
+/// which can run to megabytes a row.
+#[derive(Debug, Clone, PartialEq, DerivePartialModel)]
+#[sea_orm(entity = "reports::Entity", from_query_result)]
+pub struct DigestReport {
+    pub site_id: i32,
+    pub url: String,
+    pub created_at: NaiveDateTime,
+    pub performance: Option<f64>,
+    pub accessibility: Option<f64>,
+    pub best_practices: Option<f64>,
+    pub seo: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SiteChange {
+    pub site_id: i32,
+    pub domain: String,
+    pub report_count: usize,
+    pub previous: CategoryScores,
+    pub current: CategoryScores,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct PageScore {
+    pub url: String,
+    pub report_count: usize,
+    pub performance: f64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BudgetFailureSummary {
+    pub url: String,
+    pub failed_runs: usize,
+    pub latest: Vec<BudgetFailure>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Digest {
+    pub group: String,
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    pub report_count: usize,
+    pub sites: Vec<SiteChange>,
+    pub worst_pages: Vec<PageScore>,
+    pub budget_failures: Vec<BudgetFailureSummary>,
+}
+
+/// Summarises one period of a group's history, `previous` holds the reports of
+/// the period before it so score changes can be shown per site.
+pub fn build(
+    group: &str,
+    (period_start, period_end): (NaiveDate, NaiveDate),
+    sites: &[sites::Model],
+    previous: &[DigestReport],
+    current: &[DigestReport],
+    budget_results: &[budget_results::Model],
+) -> Digest {
+    let site_scores = |reports: &[DigestReport], site_id: i32| {
+        let scores: Vec<CategoryScores> = reports
+            .iter()
+            .filter(|report| report.site_id == site_id)
+            .map(|report| CategoryScores {
+                performance: report.performance,
+                accessibility: report.accessibility,
+                best_practices: report.best_practices,
+                seo: report.seo,
+            })
+            .collect();
+        (scores.len(), CategoryScores::average(&scores))
+    };
+
+    let site_changes = sites
+        .iter()
+        .map(|site| {
+            let (report_count, current) = site_scores(current, site.id);
+            SiteChange {
+                site_id: site.id,
+                domain: site.domain.clone(),
+                report_count,
+                previous: site_scores(previous, site.id).1,
+                current,
+            }
+        })
+        .collect();
+
+    let mut pages: BTreeMap<&str, Vec<f64>> = BTreeMap::new();
+    for report in current {
+        if let Some(performance) = report.performance {
+            pages.entry(&report.url).or_default().push(performance);
+        }
+    }
+
+    let mut worst_pages: Vec<PageScore> = pages
+        .into_iter()
+        .map(|(url, values)| PageScore {
+            url: url.to_string(),
+            report_count: values.len(),
+            performance: values.iter().sum::<f64>() / values.len() as f64,
+        })
+        .collect();
+    worst_pages.sort_by(|a, b| a.performance.total_cmp(&b.performance));
+    worst_pages.truncate(WORST_PAGES);
+
+    let mut failed: BTreeMap<&str, Vec<&budget_results::Model>> = BTreeMap::new();
+    for result in budget_results.iter().filter(|result| !result.passed) {
+        failed.entry(&result.url).or_default().push(result);
+    }
+
+    let mut budget_failures: Vec<BudgetFailureSummary> = failed
+        .into_iter()
+        .map(|(url, results)| {
+            let latest = results
+                .iter()
+                .max_by_key(|result| (result.created_at, result.id))
+                .and_then(|result| serde_json::from_value(result.failures.clone()).ok())
+                .unwrap_or_default();
+
+            BudgetFailureSummary {
+                url: url.to_string(),
+                failed_runs: results.len(),
+                latest,
+            }
+        })
+        .collect();
+    budget_failures.sort_by_key(|failure| std::cmp::Reverse(failure.failed_runs));
+    budget_failures.truncate(BUDGET_FAILURES);
+
+    Digest {
+        group: group.to_string(),
+        period_start,
+        period_end,
+        report_count: current.len(),
+        sites: site_changes,
+        worst_pages,
+        budget_failures,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod build {
+        use super::super::*;
+        use serde_json::json;
+
+        fn site(id: i32, domain: &str) -> sites::Model {
+            sites::Model {
+                id,
+                domain: domain.to_string(),
+                created_at: Default::default(),
+                deleted_at: None,
+                team_id: None,
+            }
+        }
+
+        fn report(site_id: i32, url: &str, performance: f64) -> DigestReport {
+            DigestReport {
+                site_id,
+                url: url.to_string(),
+                created_at: Default::default(),
+                performance: Some(performance),
+                accessibility: None,
+                best_practices: None,
+                seo: None,
+            }
+        }
+
+        fn failed_budget(id: i32, url: &str, value: f64) -> budget_results::Model {
+            budget_results::Model {
+                id,
+                run_id: id,
+                report_id: id,
+                site_id: 1,
+                url: url.to_string(),
+                passed: false,
+                failures: json!([{ "metric": "lcp", "pattern": null, "value": value, "min": null, "max": 2500.0 }]),
+                created_at: Default::default(),
+            }
+        }
+
+        fn period() -> (NaiveDate, NaiveDate) {
+            (
+                NaiveDate::from_ymd_opt(2025, 5, 19).unwrap(),
+                NaiveDate::from_ymd_opt(2025, 5, 26).unwrap(),
+            )
+        }
+
+        #[test]
+        fn compares_site_scores_with_previous_period() {
+            let sites = vec![site(1, "https://example.com/")];
+            let previous = vec![report(1, "https://example.com/", 0.9)];
+            let current = vec![
+                report(1, "https://example.com/", 0.7),
+                report(1, "https://example.com/blog", 0.5),
+            ];
+
+            let digest = build("Marketing", period(), &sites, &previous, &current, &[]);
+            assert_eq!(digest.report_count, 2);
+            assert_eq!(digest.sites[0].previous.performance, Some(0.9));
+            assert_eq!(digest.sites[0].current.performance, Some(0.6));
+        }
+
+        #[test]
+        fn orders_worst_pages_by_average_performance() {
+            let current = vec![
+                report(1, "https://example.com/", 0.9),
+                report(1, "https://example.com/blog", 0.4),
+                report(1, "https://example.com/blog", 0.6),
+            ];
+
+            let digest = build("Marketing", period(), &[], &[], &current, &[]);
+            let urls: Vec<&str> = digest
+                .worst_pages
+                .iter()
+                .map(|page| page.url.as_str())
+                .collect();
+            assert_eq!(
+                urls,
+                vec!["https://example.com/blog", "https://example.com/"]
+            );
+            assert_eq!(digest.worst_pages[0].report_count, 2);
+        }
+
+        #[test]
+        fn counts_budget_failures_per_url() {
+            let results = vec![
+                failed_budget(1, "https://example.com/", 3000.0),
+                failed_budget(2, "https://example.com/", 3200.0),
+                failed_budget(3, "https://example.com/blog", 2800.0),
+            ];
+
+            let digest = build("Marketing", period(), &[], &[], &[], &results);
+            assert_eq!(digest.budget_failures[0].url, "https://example.com/");
+            assert_eq!(digest.budget_failures[0].failed_runs, 2);
+            assert_eq!(digest.budget_failures[0].latest[0].value, 3200.0);
+        }
+    }
+}
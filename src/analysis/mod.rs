@@ -0,0 +1,7 @@
+pub mod budgets;
+pub mod digest;
+pub mod regressions;
+pub mod trends;
+
+#[cfg(test)]
+pub mod test_support;
@@ -0,0 +1,134 @@
+use serde::Serialize;
+
+use crate::{
+    analysis::trends::median,
+    config::RegressionThresholds,
+    entities::{reports, sea_orm_active_enums::Metric},
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub metric: Metric,
+    pub previous_value: f64,
+    pub current_value: f64,
+    pub tolerance: f64,
+}
+
+/// Compares the samples of one url between two runs, medians are compared and
+/// the widest min-max spread of either run widens the tolerance.
+pub fn detect(
+    previous: &[reports::Model],
+    current: &[reports::Model],
+    thresholds: &RegressionThresholds,
+) -> Vec<Finding> {
+    Metric::ALL
+        .iter()
+        .filter_map(|metric| {
+            let previous_values: Vec<f64> =
+                previous.iter().filter_map(|r| metric.value(r)).collect();
+            let current_values: Vec<f64> = current.iter().filter_map(|r| metric.value(r)).collect();
+
+            let noise =
+                spread(&previous_values).max(spread(&current_values)) * thresholds.noise_multiplier;
+            let previous_value = median(previous_values)?;
+            let current_value = median(current_values)?;
+
+            let (tolerance, worsened_by) = if metric.is_score() {
+                (
+                    thresholds.score_drop + noise,
+                    previous_value - current_value,
+                )
+            } else {
+                (
+                    previous_value * thresholds.metric_increase + noise,
+                    current_value - previous_value,
+                )
+            };
+
+            (worsened_by > tolerance).then_some(Finding {
+                metric: *metric,
+                previous_value,
+                current_value,
+                tolerance,
+            })
+        })
+        .collect()
+}
+
+fn spread(values: &[f64]) -> f64 {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+    if values.is_empty() {
+        0.0
+    } else {
+        max - min
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod detect {
+        use super::super::*;
+        use crate::analysis::test_support::report;
+
+        fn sample(performance: f64, lcp: f64) -> reports::Model {
+            reports::Model {
+                performance: Some(performance),
+                lcp: Some(lcp),
+                ..report()
+            }
+        }
+
+        #[test]
+        fn flags_score_drop_and_slower_metric() {
+            let previous = vec![sample(0.9, 2000.0)];
+            let current = vec![sample(0.7, 2500.0)];
+
+            let findings = detect(&previous, &current, &RegressionThresholds::default());
+            let metrics: Vec<Metric> = findings.iter().map(|f| f.metric).collect();
+            assert_eq!(metrics, vec![Metric::Performance, Metric::Lcp]);
+            assert_eq!(findings[1].tolerance, 200.0);
+        }
+
+        #[test]
+        fn ignores_changes_within_threshold() {
+            let previous = vec![sample(0.9, 2000.0)];
+            let current = vec![sample(0.87, 2150.0)];
+
+            let findings = detect(&previous, &current, &RegressionThresholds::default());
+            assert!(findings.is_empty());
+        }
+
+        #[test]
+        fn noisy_samples_widen_tolerance() {
+            let previous = vec![sample(0.9, 2000.0), sample(0.9, 2000.0)];
+            let current = vec![
+                sample(0.6, 2000.0),
+                sample(0.9, 2000.0),
+                sample(0.8, 2000.0),
+            ];
+
+            let findings = detect(&previous, &current, &RegressionThresholds::default());
+            assert!(findings.is_empty());
+
+            let strict = RegressionThresholds {
+                noise_multiplier: 0.0,
+                ..Default::default()
+            };
+            let findings = detect(&previous, &current, &strict);
+            assert_eq!(findings.len(), 1);
+            assert_eq!(findings[0].metric, Metric::Performance);
+        }
+
+        #[test]
+        fn skips_metrics_missing_from_either_run() {
+            let previous = vec![sample(0.9, 2000.0)];
+            let mut current = sample(0.9, 2000.0);
+            current.lcp = None;
+
+            let findings = detect(&previous, &[current], &RegressionThresholds::default());
+            assert!(findings.is_empty());
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use chrono::{NaiveDate, NaiveDateTime};
+
+use crate::entities::{reports, sea_orm_active_enums::Strategy};
+
+/// A mobile report of `https://example.com/` without scores or metrics,
+/// tests fill in what they need with struct update syntax.
+pub fn report() -> reports::Model {
+    reports::Model {
+        id: 1,
+        run_id: 1,
+        site_id: 1,
+        site_url_id: 1,
+        url: "https://example.com/".to_string(),
+        strategy: Strategy::Mobile,
+        performance: None,
+        accessibility: None,
+        best_practices: None,
+        seo: None,
+        lcp: None,
+        cls: None,
+        inp: None,
+        tbt: None,
+        fcp: None,
+        ttfb: None,
+        total_byte_weight: None,
+        data: serde_json::json!({}),
+        created_at: Default::default(),
+    }
+}
+
+/// Midday on a `YYYY-MM-DD` date, clear of any day boundary.
+pub fn noon(date: &str) -> NaiveDateTime {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .unwrap()
+        .and_hms_opt(12, 0, 0)
+        .unwrap()
+}
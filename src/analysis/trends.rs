@@ -0,0 +1,203 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
+use sea_orm::DerivePartialModel;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+use crate::{
+    client::psi::{CategoryScores, CoreWebVitals},
+    entities::reports,
+};
+
+/// The scores and metrics of a report, selected without its PSI `data` which
+/// can run to megabytes a row.
+#[derive(Debug, Clone, PartialEq, DerivePartialModel)]
+#[sea_orm(entity = "reports::Entity", from_query_result)]
+pub struct ReportMetrics {
+    pub created_at: NaiveDateTime,
+    pub performance: Option<f64>,
+    pub accessibility: Option<f64>,
+    pub best_practices: Option<f64>,
+    pub seo: Option<f64>,
+    pub lcp: Option<f64>,
+    pub cls: Option<f64>,
+    pub inp: Option<f64>,
+    pub tbt: Option<f64>,
+    pub fcp: Option<f64>,
+    pub ttfb: Option<f64>,
+}
+
+impl From<&reports::Model> for ReportMetrics {
+    fn from(report: &reports::Model) -> Self {
+        ReportMetrics {
+            created_at: report.created_at,
+            performance: report.performance,
+            accessibility: report.accessibility,
+            best_practices: report.best_practices,
+            seo: report.seo,
+            lcp: report.lcp,
+            cls: report.cls,
+            inp: report.inp,
+            tbt: report.tbt,
+            fcp: report.fcp,
+            ttfb: report.ttfb,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Bucket {
+    #[default]
+    Day,
+    Week,
+}
+
+impl Bucket {
+    // Weeks start on Monday, matching ISO 8601
+    pub fn start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Bucket::Day => date,
+            Bucket::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TrendPoint {
+    pub bucket: NaiveDate,
+    pub samples: usize,
+    pub scores: CategoryScores,
+    pub vitals: CoreWebVitals,
+}
+
+/// Median of every score and metric per bucket, oldest bucket first.
+pub fn build_trend(reports: &[ReportMetrics], bucket: Bucket) -> Vec<TrendPoint> {
+    let mut buckets: BTreeMap<NaiveDate, Vec<ReportMetrics>> = BTreeMap::new();
+
+    for report in reports {
+        buckets
+            .entry(bucket.start(report.created_at.date()))
+            .or_default()
+            .push(report.clone());
+    }
+
+    buckets
+        .into_iter()
+        .map(|(start, reports)| {
+            let (scores, vitals) = median_summary(&reports);
+
+            TrendPoint {
+                bucket: start,
+                samples: reports.len(),
+                scores,
+                vitals,
+            }
+        })
+        .collect()
+}
+
+/// Median of every score and metric across the given samples.
+pub fn median_summary(reports: &[ReportMetrics]) -> (CategoryScores, CoreWebVitals) {
+    let median_of = |value: fn(&ReportMetrics) -> Option<f64>| {
+        median(reports.iter().filter_map(value).collect())
+    };
+
+    (
+        CategoryScores {
+            performance: median_of(|r| r.performance),
+            accessibility: median_of(|r| r.accessibility),
+            best_practices: median_of(|r| r.best_practices),
+            seo: median_of(|r| r.seo),
+        },
+        CoreWebVitals {
+            lcp: median_of(|r| r.lcp),
+            cls: median_of(|r| r.cls),
+            inp: median_of(|r| r.inp),
+            tbt: median_of(|r| r.tbt),
+            fcp: median_of(|r| r.fcp),
+            ttfb: median_of(|r| r.ttfb),
+        },
+    )
+}
+
+pub fn median(mut values: Vec<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+
+    values.sort_by(f64::total_cmp);
+    let middle = values.len() / 2;
+
+    if values.len().is_multiple_of(2) {
+        Some((values[middle - 1] + values[middle]) / 2.0)
+    } else {
+        Some(values[middle])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod median {
+        use super::super::*;
+
+        #[test]
+        fn empty_values() {
+            assert_eq!(median(vec![]), None);
+        }
+
+        #[test]
+        fn odd_and_even_lengths() {
+            assert_eq!(median(vec![3.0, 1.0, 2.0]), Some(2.0));
+            assert_eq!(median(vec![4.0, 1.0, 3.0, 2.0]), Some(2.5));
+        }
+    }
+
+    mod build_trend {
+        use super::super::*;
+        use crate::analysis::test_support::{noon, report as base};
+
+        fn report(date: &str, performance: f64, lcp: Option<f64>) -> ReportMetrics {
+            ReportMetrics::from(&reports::Model {
+                performance: Some(performance),
+                lcp,
+                created_at: noon(date),
+                ..base()
+            })
+        }
+
+        #[test]
+        fn buckets_by_day() {
+            let reports = vec![
+                report("2025-04-02", 0.9, Some(2000.0)),
+                report("2025-04-01", 0.5, Some(3000.0)),
+                report("2025-04-01", 0.7, None),
+                report("2025-04-01", 0.6, Some(1000.0)),
+            ];
+
+            let trend = build_trend(&reports, Bucket::Day);
+            assert_eq!(trend.len(), 2);
+            assert_eq!(trend[0].bucket.to_string(), "2025-04-01");
+            assert_eq!(trend[0].samples, 3);
+            assert_eq!(trend[0].scores.performance, Some(0.6));
+            assert_eq!(trend[0].vitals.lcp, Some(2000.0));
+            assert_eq!(trend[1].scores.performance, Some(0.9));
+        }
+
+        #[test]
+        fn buckets_by_week_starting_monday() {
+            let reports = vec![
+                report("2025-04-06", 0.4, None),
+                report("2025-04-07", 0.8, None),
+                report("2025-04-13", 0.6, None),
+            ];
+
+            let trend = build_trend(&reports, Bucket::Week);
+            assert_eq!(trend.len(), 2);
+            assert_eq!(trend[0].bucket.to_string(), "2025-03-31");
+            assert_eq!(trend[1].bucket.to_string(), "2025-04-07");
+            assert_eq!(trend[1].samples, 2);
+            assert_eq!(trend[1].scores.performance, Some(0.7));
+        }
+    }
+}
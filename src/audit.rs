@@ -0,0 +1,125 @@
+use axum::{extract::FromRequestParts, http::request::Parts};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    TransactionTrait,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::convert::Infallible;
+
+use crate::{
+    auth::Principal,
+    entities::{
+        sea_orm_active_enums::AuditAction,
+        site_audit_log,
+        sites::{self, Entity as Sites},
+    },
+};
+
+const ACTOR_HEADER: &str = "x-actor";
+
+/// Who made a change. Logged in users are recorded by email and API keys by
+/// the key name. The `X-Actor` request header is only a claim made by the
+/// client, so it's appended to a key name as `key:<name> (on behalf of <actor>)`
+/// and never replaces it.
+#[derive(Debug, Clone)]
+pub struct Actor(pub String);
+
+impl Default for Actor {
+    fn default() -> Self {
+        Actor("anonymous".to_string())
+    }
+}
+
+impl<S> FromRequestParts<S> for Actor
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Some(principal) = parts.extensions.get::<Principal>() else {
+            return Ok(Actor::default());
+        };
+
+        let on_behalf_of = parts
+            .headers
+            .get(ACTOR_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty());
+
+        Ok(match (principal, on_behalf_of) {
+            (Principal::Key(_), Some(on_behalf_of)) => Actor(format!(
+                "{} (on behalf of {on_behalf_of})",
+                principal.actor()
+            )),
+            _ => Actor(principal.actor()),
+        })
+    }
+}
+
+pub async fn record<C: ConnectionTrait>(
+    db: &C,
+    site_id: i32,
+    action: AuditAction,
+    actor: &Actor,
+    changes: Value,
+) -> Result<site_audit_log::Model, DbErr> {
+    site_audit_log::ActiveModel {
+        site_id: Set(site_id),
+        action: Set(action),
+        actor: Set(actor.0.clone()),
+        changes: Set(changes),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+}
+
+pub fn field_change(field: &str, from: impl Serialize, to: impl Serialize) -> Value {
+    json!({ field: { "from": from, "to": to } })
+}
+
+/// Soft deletes or restores the site, recording who did it. `None` when the
+/// site doesn't exist or is already deleted or restored.
+pub async fn set_deleted_at(
+    db: &DatabaseConnection,
+    site_id: i32,
+    actor: &Actor,
+    deleted: bool,
+) -> Result<Option<sites::Model>, DbErr> {
+    let txn = db.begin().await?;
+
+    let Some(site) = Sites::find_by_id(site_id).one(&txn).await? else {
+        return Ok(None);
+    };
+    if site.deleted_at.is_some() == deleted {
+        return Ok(None);
+    }
+
+    let previous = site.deleted_at;
+    let deleted_at = deleted.then(|| chrono::Utc::now().naive_utc());
+
+    let mut site: sites::ActiveModel = site.into();
+    site.deleted_at = Set(deleted_at);
+    let site = site.update(&txn).await?;
+
+    let action = if deleted {
+        AuditAction::Deleted
+    } else {
+        AuditAction::Restored
+    };
+    record(
+        &txn,
+        site.id,
+        action,
+        actor,
+        field_change("deleted_at", previous, deleted_at),
+    )
+    .await?;
+
+    txn.commit().await?;
+
+    Ok(Some(site))
+}
@@ -0,0 +1,451 @@
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{
+        header::{AUTHORIZATION, COOKIE},
+        request::Parts,
+        HeaderMap, StatusCode,
+    },
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+use rand::RngCore;
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr,
+    EntityTrait, QueryFilter,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+use crate::{
+    accounts,
+    entities::{
+        api_keys::{self, Entity as ApiKeys},
+        sea_orm_active_enums::Role,
+        sites, team_members, users,
+    },
+    AppState,
+};
+
+pub const SESSION_COOKIE: &str = "tarin_session";
+
+const KEY_PREFIX: &str = "tarin_";
+// Enough of the key to tell keys apart in listings without revealing it
+const PREFIX_LEN: usize = KEY_PREFIX.len() + 8;
+
+/// What an API key may do, each route requires one of `read`, `write` or
+/// `run`, and instance wide routes `admin` as well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Every `GET` route.
+    Read,
+    /// Creating, updating and deleting sites, groups, budgets and webhooks.
+    Write,
+    /// Starting audits, which spend audit backend quota.
+    Run,
+    /// Webhooks, the config and runs of the configured sites.
+    Admin,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Write => "write",
+            Scope::Run => "run",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+impl api_keys::Model {
+    pub fn scopes(&self) -> Vec<Scope> {
+        serde_json::from_value(self.scopes.clone()).unwrap_or_default()
+    }
+}
+
+/// A new random key, shown once and only stored hashed.
+pub fn generate_key() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("{KEY_PREFIX}{}", hex::encode(bytes))
+}
+
+pub fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Stores a new key and returns it with the plain text key.
+pub async fn create_key(
+    db: &DatabaseConnection,
+    name: &str,
+    scopes: &[Scope],
+) -> Result<(api_keys::Model, String), DbErr> {
+    let key = generate_key();
+
+    let model = api_keys::ActiveModel {
+        name: Set(name.to_string()),
+        prefix: Set(key[..PREFIX_LEN].to_string()),
+        key_hash: Set(hash_key(&key)),
+        scopes: Set(serde_json::json!(scopes)),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    Ok((model, key))
+}
+
+/// The unrevoked key matching `key`, marked as used.
+pub async fn authenticate(
+    db: &DatabaseConnection,
+    key: &str,
+) -> Result<Option<api_keys::Model>, DbErr> {
+    let Some(model) = ApiKeys::find()
+        .filter(api_keys::Column::KeyHash.eq(hash_key(key)))
+        .filter(api_keys::Column::RevokedAt.is_null())
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    ApiKeys::update_many()
+        .col_expr(
+            api_keys::Column::LastUsedAt,
+            Expr::value(Utc::now().naive_utc()),
+        )
+        .filter(api_keys::Column::Id.eq(model.id))
+        .exec(db)
+        .await?;
+
+    Ok(Some(model))
+}
+
+/// Who is making a request, an API key or a logged in user.
+#[derive(Debug, Clone)]
+pub enum Principal {
+    Key(api_keys::Model),
+    User {
+        user: users::Model,
+        teams: Vec<team_members::Model>,
+    },
+}
+
+impl Principal {
+    /// Keys and admin users see every team, keys aren't tied to one.
+    pub fn sees_every_team(&self) -> bool {
+        match self {
+            Principal::Key(_) => true,
+            Principal::User { user, .. } => user.is_admin,
+        }
+    }
+
+    /// Admin users and keys with the `admin` scope.
+    pub fn is_admin(&self) -> bool {
+        match self {
+            Principal::Key(key) => key.scopes().contains(&Scope::Admin),
+            Principal::User { user, .. } => user.is_admin,
+        }
+    }
+
+    /// Users get `read` from any team role, `write` and `run` from editor and
+    /// `admin` only as admin users.
+    pub fn allows(&self, scope: Scope) -> bool {
+        match self {
+            Principal::Key(key) => key.scopes().contains(&scope),
+            Principal::User { .. } if self.sees_every_team() => true,
+            Principal::User { teams, .. } => {
+                let needed = match scope {
+                    Scope::Read => Role::Viewer,
+                    Scope::Write | Scope::Run => Role::Editor,
+                    Scope::Admin => return false,
+                };
+                teams.iter().any(|member| member.role >= needed)
+            }
+        }
+    }
+
+    pub fn team_role(&self, team_id: i32) -> Option<Role> {
+        match self {
+            _ if self.sees_every_team() => Some(Role::Admin),
+            Principal::Key(_) => None,
+            Principal::User { teams, .. } => teams
+                .iter()
+                .find(|member| member.team_id == team_id)
+                .map(|member| member.role),
+        }
+    }
+
+    /// `None` when the site isn't visible, sites without a team are admin only.
+    pub fn site_role(&self, site: &sites::Model) -> Option<Role> {
+        match site.team_id {
+            Some(team_id) => self.team_role(team_id),
+            None => self.sees_every_team().then_some(Role::Admin),
+        }
+    }
+
+    /// The lowest role over the sites of a group or run, which belong to
+    /// whichever teams own the sites. `None` when any of them isn't visible,
+    /// and for anything without sites unless the principal is an admin.
+    pub fn sites_role<'a>(
+        &self,
+        sites: impl IntoIterator<Item = &'a sites::Model>,
+    ) -> Option<Role> {
+        if self.sees_every_team() {
+            return Some(Role::Admin);
+        }
+
+        // `None` orders first, so one hidden site hides the lot
+        sites
+            .into_iter()
+            .map(|site| self.site_role(site))
+            .min()
+            .flatten()
+    }
+
+    /// Teams whose sites are visible, `None` for every site.
+    pub fn visible_teams(&self) -> Option<Vec<i32>> {
+        match self {
+            _ if self.sees_every_team() => None,
+            Principal::Key(_) => None,
+            Principal::User { teams, .. } => {
+                Some(teams.iter().map(|member| member.team_id).collect())
+            }
+        }
+    }
+
+    /// Name recorded in the site audit log.
+    pub fn actor(&self) -> String {
+        match self {
+            Principal::Key(key) => format!("key:{}", key.name),
+            Principal::User { user, .. } => user.email.clone(),
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for Principal
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Principal>()
+            .cloned()
+            .ok_or(StatusCode::UNAUTHORIZED)
+    }
+}
+
+/// An admin user or a key with the `admin` scope, for instance wide routes such
+/// as webhooks and the config that aren't scoped to a team. Others get a 403.
+#[derive(Debug, Clone)]
+pub struct Admin(pub Principal);
+
+impl<S> FromRequestParts<S> for Admin
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let principal = Principal::from_request_parts(parts, state).await?;
+        if !principal.is_admin() {
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        Ok(Admin(principal))
+    }
+}
+
+/// The key or session user behind the request headers.
+pub async fn principal(
+    db: &DatabaseConnection,
+    headers: &HeaderMap,
+) -> Result<Option<Principal>, DbErr> {
+    if let Some(token) = bearer_token(headers) {
+        let key = authenticate(db, token).await?;
+        return Ok(key.map(Principal::Key));
+    }
+
+    let Some(token) = session_token(headers) else {
+        return Ok(None);
+    };
+    let Some(user) = accounts::session_user(db, token).await? else {
+        return Ok(None);
+    };
+    let teams = accounts::memberships(db, user.id).await?;
+
+    Ok(Some(Principal::User { user, teams }))
+}
+
+pub fn session_token(headers: &HeaderMap) -> Option<&str> {
+    cookie(headers, SESSION_COOKIE)
+}
+
+pub fn cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .find_map(|cookie| {
+            let (cookie_name, value) = cookie.trim().split_once('=')?;
+            (cookie_name == name).then_some(value)
+        })
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+/// Route layer rejecting requests without a key or session (401) or without
+/// the scope (403). The `Principal` is added to the request extensions.
+pub async fn require_scope(
+    State((app_state, scope)): State<(Arc<AppState>, Scope)>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let principal = principal(app_state.db.as_ref(), request.headers())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if !principal.allows(scope) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    request.extensions_mut().insert(principal);
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    mod generate_key {
+        use super::super::*;
+
+        #[test]
+        fn keys_are_unique_and_prefixed() {
+            let first = generate_key();
+            let second = generate_key();
+
+            assert!(first.starts_with(KEY_PREFIX));
+            assert_eq!(first.len(), KEY_PREFIX.len() + 64);
+            assert_ne!(first, second);
+            assert_ne!(hash_key(&first), hash_key(&second));
+            assert_eq!(hash_key(&first), hash_key(&first));
+        }
+    }
+
+    mod bearer_token {
+        use super::super::*;
+
+        #[test]
+        fn reads_authorization_header() {
+            let mut headers = HeaderMap::new();
+            headers.insert(AUTHORIZATION, "Bearer tarin_abc".parse().unwrap());
+            assert_eq!(bearer_token(&headers), Some("tarin_abc"));
+
+            headers.insert(AUTHORIZATION, "Basic dXNlcjpwYXNz".parse().unwrap());
+            assert_eq!(bearer_token(&headers), None);
+        }
+    }
+
+    mod session_token {
+        use super::super::*;
+
+        #[test]
+        fn reads_session_cookie() {
+            let mut headers = HeaderMap::new();
+            headers.insert(COOKIE, "theme=dark; tarin_session=abc".parse().unwrap());
+            assert_eq!(session_token(&headers), Some("abc"));
+
+            headers.insert(COOKIE, "tarin_session_old=abc".parse().unwrap());
+            assert_eq!(session_token(&headers), None);
+        }
+    }
+
+    mod principal {
+        use super::super::*;
+        use chrono::NaiveDateTime;
+
+        fn user(is_admin: bool, teams: &[(i32, Role)]) -> Principal {
+            Principal::User {
+                user: users::Model {
+                    id: 1,
+                    email: "ana@example.com".to_string(),
+                    name: None,
+                    password_hash: None,
+                    oidc_subject: None,
+                    is_admin,
+                    created_at: NaiveDateTime::default(),
+                },
+                teams: teams
+                    .iter()
+                    .map(|(team_id, role)| team_members::Model {
+                        team_id: *team_id,
+                        user_id: 1,
+                        role: *role,
+                    })
+                    .collect(),
+            }
+        }
+
+        fn site(team_id: Option<i32>) -> sites::Model {
+            sites::Model {
+                id: 1,
+                domain: "https://example.com".to_string(),
+                created_at: NaiveDateTime::default(),
+                deleted_at: None,
+                team_id,
+            }
+        }
+
+        #[test]
+        fn team_roles_limit_sites_and_scopes() {
+            let viewer = user(false, &[(1, Role::Viewer), (2, Role::Editor)]);
+
+            assert_eq!(viewer.site_role(&site(Some(1))), Some(Role::Viewer));
+            assert_eq!(viewer.site_role(&site(Some(2))), Some(Role::Editor));
+            assert_eq!(viewer.site_role(&site(Some(3))), None);
+            assert_eq!(viewer.site_role(&site(None)), None);
+            assert_eq!(viewer.visible_teams(), Some(vec![1, 2]));
+            assert!(viewer.allows(Scope::Write));
+
+            let outsider = user(false, &[(1, Role::Viewer)]);
+            assert!(outsider.allows(Scope::Read));
+            assert!(!outsider.allows(Scope::Run));
+        }
+
+        #[test]
+        fn admins_see_every_site() {
+            let admin = user(true, &[]);
+
+            assert_eq!(admin.site_role(&site(None)), Some(Role::Admin));
+            assert_eq!(admin.site_role(&site(Some(3))), Some(Role::Admin));
+            assert_eq!(admin.visible_teams(), None);
+            assert!(admin.allows(Scope::Run));
+            assert_eq!(admin.sites_role([]), Some(Role::Admin));
+        }
+
+        #[test]
+        fn lowest_role_over_several_sites() {
+            let member = user(false, &[(1, Role::Viewer), (2, Role::Editor)]);
+
+            let (first, second) = (site(Some(1)), site(Some(2)));
+            assert_eq!(member.sites_role([&second]), Some(Role::Editor));
+            assert_eq!(member.sites_role([&first, &second]), Some(Role::Viewer));
+            assert_eq!(member.sites_role([&second, &site(Some(3))]), None);
+            assert_eq!(member.sites_role([]), None);
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use sea_orm::ActiveEnum;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+use crate::{
+    analysis::budgets::{BudgetFailure, BudgetOutcome},
+    utils::escape,
+};
+
+/// One audited sample, `outcome` is `None` when no budget covers the url.
+#[derive(Debug, Clone)]
+pub struct CheckCase {
+    pub site: String,
+    pub url: String,
+    pub sample: i32,
+    pub outcome: Option<BudgetOutcome>,
+}
+
+impl CheckCase {
+    fn name(&self) -> String {
+        format!("{} (sample {})", self.url, self.sample)
+    }
+
+    fn failures(&self) -> &[BudgetFailure] {
+        self.outcome
+            .as_ref()
+            .map(|outcome| outcome.failures.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CheckReport {
+    pub cases: Vec<CheckCase>,
+}
+
+impl CheckReport {
+    pub fn failed(&self) -> usize {
+        self.cases
+            .iter()
+            .filter(|case| !case.failures().is_empty())
+            .count()
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.cases
+            .iter()
+            .filter(|case| case.outcome.is_none())
+            .count()
+    }
+
+    pub fn passed(&self) -> bool {
+        self.failed() == 0
+    }
+
+    // Cases grouped by site, sites in name order
+    fn by_site(&self) -> BTreeMap<&str, Vec<&CheckCase>> {
+        let mut sites: BTreeMap<&str, Vec<&CheckCase>> = BTreeMap::new();
+        for case in &self.cases {
+            sites.entry(case.site.as_str()).or_default().push(case);
+        }
+        sites
+    }
+}
+
+/// Human readable result, one line per failing sample and a closing count.
+pub fn summary(report: &CheckReport) -> String {
+    let mut lines = Vec::new();
+
+    for case in &report.cases {
+        if !case.failures().is_empty() {
+            let details: Vec<String> = case
+                .failures()
+                .iter()
+                .map(BudgetFailure::describe)
+                .collect();
+            lines.push(format!("FAIL {}: {}", case.name(), details.join(", ")));
+        }
+    }
+
+    lines.push(format!(
+        "{} checked, {} failed, {} without a budget",
+        report.cases.len(),
+        report.failed(),
+        report.skipped()
+    ));
+
+    lines.join("\n")
+}
+
+/// JUnit XML with a test suite per site and a test case per sample.
+pub fn junit_xml(report: &CheckReport) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"tarin\" tests=\"{}\" failures=\"{}\" skipped=\"{}\">\n",
+        report.cases.len(),
+        report.failed(),
+        report.skipped()
+    ));
+
+    for (site, cases) in report.by_site() {
+        let failures = cases
+            .iter()
+            .filter(|case| !case.failures().is_empty())
+            .count();
+        let skipped = cases.iter().filter(|case| case.outcome.is_none()).count();
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{failures}\" skipped=\"{skipped}\">\n",
+            escape(site),
+            cases.len()
+        ));
+
+        for case in cases {
+            xml.push_str(&format!(
+                "    <testcase classname=\"{}\" name=\"{}\"",
+                escape(site),
+                escape(&case.name())
+            ));
+
+            match &case.outcome {
+                None => xml.push_str(
+                    ">\n      <skipped message=\"no budget applies\"/>\n    </testcase>\n",
+                ),
+                Some(outcome) if outcome.passed => xml.push_str("/>\n"),
+                Some(outcome) => {
+                    let details: Vec<String> = outcome
+                        .failures
+                        .iter()
+                        .map(BudgetFailure::describe)
+                        .collect();
+                    xml.push_str(&format!(
+                        ">\n      <failure message=\"{}\" type=\"budget\">{}</failure>\n    </testcase>\n",
+                        escape(&format!("{} budget(s) exceeded", outcome.failures.len())),
+                        escape(&details.join("\n"))
+                    ));
+                }
+            }
+        }
+
+        xml.push_str("  </testsuite>\n");
+    }
+
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+/// SARIF 2.1.0 log with a rule per metric and a result per budget failure.
+pub fn sarif(report: &CheckReport) -> Value {
+    let mut rules: BTreeMap<String, Value> = BTreeMap::new();
+    let mut results = Vec::new();
+
+    for case in &report.cases {
+        for failure in case.failures() {
+            let rule_id = failure.metric.to_value();
+            rules.entry(rule_id.clone()).or_insert_with(|| {
+                json!({
+                    "id": rule_id,
+                    "shortDescription": { "text": format!("{:?} budget", failure.metric) },
+                })
+            });
+
+            results.push(json!({
+                "ruleId": rule_id,
+                "level": "error",
+                "message": { "text": format!("{}: {}", case.name(), failure.describe()) },
+                "locations": [{
+                    "physicalLocation": { "artifactLocation": { "uri": case.url } }
+                }],
+                "properties": {
+                    "site": case.site,
+                    "sample": case.sample,
+                    "pattern": failure.pattern,
+                    "value": failure.value,
+                    "min": failure.min,
+                    "max": failure.max,
+                },
+            }));
+        }
+    }
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "tarin",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules.into_values().collect::<Vec<Value>>(),
+                }
+            },
+            "results": results,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::sea_orm_active_enums::Metric;
+
+    fn report() -> CheckReport {
+        let failure = BudgetFailure {
+            metric: Metric::Lcp,
+            pattern: Some("/blog/:slug".to_string()),
+            value: 3100.0,
+            min: None,
+            max: Some(2500.0),
+        };
+
+        CheckReport {
+            cases: vec![
+                CheckCase {
+                    site: "https://example.com/".to_string(),
+                    url: "https://example.com/".to_string(),
+                    sample: 1,
+                    outcome: Some(BudgetOutcome {
+                        passed: true,
+                        failures: vec![],
+                    }),
+                },
+                CheckCase {
+                    site: "https://example.com/".to_string(),
+                    url: "https://example.com/blog/a&b".to_string(),
+                    sample: 1,
+                    outcome: Some(BudgetOutcome {
+                        passed: false,
+                        failures: vec![failure],
+                    }),
+                },
+                CheckCase {
+                    site: "https://example.com/".to_string(),
+                    url: "https://example.com/about".to_string(),
+                    sample: 1,
+                    outcome: None,
+                },
+            ],
+        }
+    }
+
+    mod summary {
+        use super::super::*;
+
+        #[test]
+        fn lists_failures_and_counts() {
+            let report = super::report();
+            assert!(!report.passed());
+
+            let summary = summary(&report);
+            assert!(
+                summary.contains("FAIL https://example.com/blog/a&b (sample 1): Lcp 3100 > 2500")
+            );
+            assert!(summary.ends_with("3 checked, 1 failed, 1 without a budget"));
+        }
+    }
+
+    mod junit_xml {
+        use super::super::*;
+
+        #[test]
+        fn writes_cases_per_site() {
+            let xml = junit_xml(&super::report());
+            assert!(xml
+                .contains("<testsuites name=\"tarin\" tests=\"3\" failures=\"1\" skipped=\"1\">"));
+            assert!(xml.contains("name=\"https://example.com/blog/a&amp;b (sample 1)\""));
+            assert!(xml.contains("<failure message=\"1 budget(s) exceeded\" type=\"budget\">Lcp 3100 &gt; 2500</failure>"));
+            assert!(xml.contains("<skipped message=\"no budget applies\"/>"));
+            assert_eq!(xml.matches("<testcase").count(), 3);
+        }
+    }
+
+    mod sarif {
+        use super::super::*;
+
+        #[test]
+        fn reports_each_failure() {
+            let log = sarif(&super::report());
+            let run = &log["runs"][0];
+            assert_eq!(log["version"], "2.1.0");
+            assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "lcp");
+            assert_eq!(run["results"].as_array().unwrap().len(), 1);
+            assert_eq!(
+                run["results"][0]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+                "https://example.com/blog/a&b"
+            );
+        }
+    }
+}
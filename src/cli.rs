@@ -0,0 +1,877 @@
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Args, Parser, Subcommand};
+use sea_orm::{
+    sea_query::OnConflict, ActiveEnum, ActiveModelTrait, ActiveValue::Set, ColumnTrait, Database,
+    DatabaseConnection, EntityTrait, ModelTrait, QueryFilter, QueryOrder, TransactionTrait,
+};
+use serde::de::DeserializeOwned;
+use std::{collections::HashMap, path::PathBuf, process::ExitCode, sync::Arc};
+use tokio::sync::broadcast;
+use tracing::warn;
+use url::Url;
+
+use crate::{
+    accounts,
+    analysis::budgets::{self as budget_rules, Budget},
+    audit::{self, Actor},
+    auth::{self, Scope},
+    check::{self, CheckCase, CheckReport},
+    client::{
+        processor::audit_site, provider::provider_from_settings, sitemaps::extract_sitemap_url_list,
+    },
+    config::{BudgetFile, Settings},
+    entities::{
+        api_keys::{self, Entity as ApiKeys},
+        groups::Entity as Groups,
+        reports::{self, Entity as Reports},
+        runs::{self, Entity as Runs},
+        sea_orm_active_enums::{AuditAction, JobStatus, Role, RunStatus, Strategy},
+        sites::{self, Entity as Sites},
+        team_members::{self, Entity as TeamMembers},
+        teams::{self, Entity as Teams},
+        users::{self, Entity as Users},
+    },
+    html_report::{self, latest_site_run, load_run_report},
+    queue,
+    reload::LiveSettings,
+    routes::groups::MAX_SAMPLES,
+    run_export::{export_rows, ExportFormat, ExportRow},
+    shutdown::Shutdown,
+    site_import::validate_domain,
+    utils::site_base_url,
+    worker::Worker,
+};
+
+#[derive(Parser)]
+#[command(
+    name = "tarin",
+    version,
+    about = "PageSpeed Insights reporting across many sites"
+)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Settings file, defaults to `config.toml` when present
+    #[arg(long, global = true, env = "TARIN_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Options for `serve`, which runs when no subcommand is given
+    #[command(flatten)]
+    pub serve: ServeArgs,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Start the API server, and the queue workers when no subcommand is given
+    Serve(ServeArgs),
+    /// Execute queued crawls and audits without serving the API
+    Worker(WorkerArgs),
+    /// Render a self-contained HTML report for a run or a site
+    Report(ReportArgs),
+    /// Print the urls that would be audited for a site
+    Crawl {
+        /// Site url or bare domain
+        url: String,
+    },
+    /// Audit sites and write the results, without a database unless `--group` is given
+    Run(RunArgs),
+    /// Manage the stored sites
+    #[command(subcommand)]
+    Sites(SitesCommand),
+    /// Export the results of a stored run
+    Export(ExportArgs),
+    /// Audit sites against a budgets file, exiting with 2 when a budget fails
+    Check(CheckArgs),
+    /// Manage API keys
+    #[command(subcommand)]
+    Keys(KeysCommand),
+    /// Manage user accounts
+    #[command(subcommand)]
+    Users(UsersCommand),
+    /// Manage teams and their members
+    #[command(subcommand)]
+    Teams(TeamsCommand),
+    /// Inspect and retry queued audit jobs
+    #[command(subcommand)]
+    Jobs(JobsCommand),
+}
+
+#[derive(Args)]
+pub struct RunArgs {
+    /// Site to audit, repeatable, defaults to the sites in `config.toml`
+    #[arg(long = "site")]
+    pub sites: Vec<String>,
+    /// Run a stored group instead, saving the results to the database
+    #[arg(long, conflicts_with = "sites")]
+    pub group: Option<i32>,
+    /// `mobile` or `desktop`
+    #[arg(long, default_value = "mobile", value_parser = parse_serde::<Strategy>)]
+    pub strategy: Strategy,
+    /// PSI calls per url, between 1 and 5
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(i32).range(1..=MAX_SAMPLES as i64))]
+    pub samples: i32,
+    /// `csv`, `xlsx` or `json`
+    #[arg(long, default_value = "json", value_parser = parse_serde::<ExportFormat>)]
+    pub format: ExportFormat,
+    /// File to write, defaults to stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+pub enum SitesCommand {
+    /// Add a site
+    Add {
+        domain: String,
+        /// Owning team
+        #[arg(long)]
+        team: Option<i32>,
+    },
+    /// List the sites
+    List {
+        #[arg(long)]
+        include_deleted: bool,
+    },
+    /// Soft delete a site
+    Remove { id: i32 },
+}
+
+#[derive(Subcommand)]
+pub enum KeysCommand {
+    /// Create a key, printing it once
+    Create {
+        name: String,
+        /// `read`, `write`, `run` or `admin`, repeatable. Keys see every
+        /// team's sites, `admin` also allows webhooks, the config and
+        /// `POST /reports`
+        #[arg(long = "scope", required = true, value_parser = parse_serde::<Scope>)]
+        scopes: Vec<Scope>,
+    },
+    /// List the keys
+    List,
+    /// Revoke a key
+    Revoke { id: i32 },
+}
+
+#[derive(Subcommand)]
+pub enum UsersCommand {
+    /// Create a user, who can log in with a password or through OIDC
+    Create {
+        email: String,
+        #[arg(long)]
+        name: Option<String>,
+        /// Read a password from the first line of stdin
+        #[arg(long)]
+        password_stdin: bool,
+        /// Sees and manages every team's sites
+        #[arg(long)]
+        admin: bool,
+    },
+    /// List the users
+    List,
+    /// Delete a user and their sessions
+    Remove { email: String },
+}
+
+#[derive(Subcommand)]
+pub enum TeamsCommand {
+    /// Create a team
+    Create { name: String },
+    /// List the teams with their members
+    List,
+    /// Add a user to a team, or change their role
+    AddMember {
+        team: i32,
+        email: String,
+        /// `viewer`, `editor` or `admin`
+        #[arg(long, default_value = "viewer", value_parser = parse_serde::<Role>)]
+        role: Role,
+    },
+    /// Remove a user from a team
+    RemoveMember { team: i32, email: String },
+}
+
+#[derive(Subcommand)]
+pub enum JobsCommand {
+    /// List the jobs, oldest first
+    List {
+        /// `queued`, `running`, `done` or `dead`
+        #[arg(long, value_parser = parse_serde::<JobStatus>)]
+        status: Option<JobStatus>,
+    },
+    /// Queue a dead job again with fresh attempts
+    Retry { id: i32 },
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// Run to export
+    #[arg(long)]
+    pub run: i32,
+    /// `csv`, `xlsx` or `json`
+    #[arg(long, default_value = "csv", value_parser = parse_serde::<ExportFormat>)]
+    pub format: ExportFormat,
+    /// File to write, defaults to stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct CheckArgs {
+    /// Site to audit, repeatable
+    #[arg(long = "site", required = true)]
+    pub sites: Vec<String>,
+    /// TOML file of `[[budgets]]`, in the same shape as `config.toml`
+    #[arg(long)]
+    pub budget: PathBuf,
+    /// `mobile` or `desktop`
+    #[arg(long, default_value = "mobile", value_parser = parse_serde::<Strategy>)]
+    pub strategy: Strategy,
+    /// PSI calls per url, between 1 and 5
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(i32).range(1..=MAX_SAMPLES as i64))]
+    pub samples: i32,
+    /// Write a JUnit XML report
+    #[arg(long)]
+    pub junit: Option<PathBuf>,
+    /// Write a SARIF report
+    #[arg(long)]
+    pub sarif: Option<PathBuf>,
+}
+
+// Parses an argument with the same lowercase names the API accepts
+fn parse_serde<T: DeserializeOwned>(value: &str) -> Result<T, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_string()))
+        .map_err(|_| format!("unknown value `{value}`"))
+}
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// Apply pending database migrations before serving, as `server.migrate`
+    #[arg(long)]
+    pub migrate: bool,
+    /// Also execute queued jobs in this process
+    #[arg(long)]
+    pub worker: bool,
+}
+
+#[derive(Args)]
+pub struct WorkerArgs {
+    /// Apply pending database migrations before working, as `server.migrate`
+    #[arg(long)]
+    pub migrate: bool,
+    /// Serve `/healthz`, `/readyz` and `/metrics` on this address
+    #[arg(long)]
+    pub listen: Option<String>,
+}
+
+#[derive(Args)]
+pub struct ReportArgs {
+    /// Run to report on
+    #[arg(long, required_unless_present = "site", conflicts_with = "site")]
+    pub run: Option<i32>,
+    /// Site to report on, using its most recent run
+    #[arg(long)]
+    pub site: Option<i32>,
+    /// File to write, defaults to stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+pub async fn connect(settings: &Settings) -> Result<DatabaseConnection> {
+    let database_url = settings.database_url()?;
+
+    if database_url.starts_with("sqlite:") && !cfg!(feature = "sqlite") {
+        bail!("`database.url` is a SQLite database, rebuild with `--features sqlite`");
+    }
+
+    Ok(Database::connect(database_url).await?)
+}
+
+pub async fn report(settings: &Settings, args: ReportArgs) -> Result<()> {
+    let db = connect(settings).await?;
+
+    let run = match (args.run, args.site) {
+        (Some(run_id), _) => Runs::find_by_id(run_id)
+            .one(&db)
+            .await?
+            .ok_or_else(|| anyhow!("Run {run_id} not found"))?,
+        (None, Some(site_id)) => latest_site_run(&db, site_id)
+            .await?
+            .ok_or_else(|| anyhow!("No reports found for site {site_id}"))?,
+        (None, None) => bail!("Either --run or --site is required"),
+    };
+
+    let report = load_run_report(&db, &run, args.site).await?;
+    let html = html_report::render(&report);
+
+    write_output(args.output, html.into_bytes()).await
+}
+
+pub async fn crawl(settings: &Settings, url: &str) -> Result<()> {
+    let base_url = site_base_url(url)?;
+
+    for url in extract_sitemap_url_list(&base_url, settings).await? {
+        println!("{url}");
+    }
+
+    Ok(())
+}
+
+pub async fn run(settings: Arc<Settings>, args: RunArgs) -> Result<()> {
+    let rows = match args.group {
+        Some(group_id) => run_group(settings, group_id, args.strategy, args.samples).await?,
+        None => run_offline(&settings, args.sites, args.strategy, args.samples).await?,
+    };
+
+    let format = args.format;
+    let output = tokio::task::spawn_blocking(move || export_rows(format, rows)).await??;
+    write_output(args.output, output).await
+}
+
+// Audits the sites directly against PSI, nothing is stored
+async fn run_offline(
+    settings: &Settings,
+    sites: Vec<String>,
+    strategy: Strategy,
+    samples: i32,
+) -> Result<Vec<ExportRow>> {
+    let sites = match sites.is_empty() {
+        false => sites,
+        true => settings.sites.clone(),
+    };
+
+    if sites.is_empty() {
+        bail!("No sites given, pass --site or set `sites` in the settings");
+    }
+
+    let provider = provider_from_settings(&settings.audit)?;
+    let mut rows = Vec::new();
+
+    for site in sites {
+        let base_url = site_base_url(&site)?;
+        let reports = audit_site(provider.as_ref(), settings, &base_url, strategy, samples).await?;
+        rows.extend(reports.iter().map(|report| ExportRow::new(&site, report)));
+    }
+
+    Ok(rows)
+}
+
+// Runs the group the same way the API does and waits for it to finish
+async fn run_group(
+    settings: Arc<Settings>,
+    group_id: i32,
+    strategy: Strategy,
+    samples: i32,
+) -> Result<Vec<ExportRow>> {
+    let db = Arc::new(connect(&settings).await?);
+
+    let group = Groups::find_by_id(group_id)
+        .one(db.as_ref())
+        .await?
+        .ok_or_else(|| anyhow!("Group {group_id} not found"))?;
+
+    let sites = group
+        .find_related(Sites)
+        .filter(sites::Column::DeletedAt.is_null())
+        .all(db.as_ref())
+        .await?;
+
+    if sites.is_empty() {
+        bail!("Group {group_id} has no sites");
+    }
+
+    let run = runs::ActiveModel {
+        group_id: Set(Some(group.id)),
+        status: Set(RunStatus::Pending),
+        strategy: Set(strategy),
+        samples: Set(samples),
+        ..Default::default()
+    }
+    .insert(db.as_ref())
+    .await?;
+
+    queue::enqueue_run(db.as_ref(), &run, &sites).await?;
+
+    let (events, _) = broadcast::channel(256);
+    let settings = LiveSettings::new(settings.as_ref().clone(), None, Vec::new());
+    Worker::new(db.clone(), events, settings, Shutdown::new())
+        .run_until_done(run.id)
+        .await;
+
+    let run = Runs::find_by_id(run.id)
+        .one(db.as_ref())
+        .await?
+        .ok_or_else(|| anyhow!("Run {} not found", run.id))?;
+    if run.status == RunStatus::Failed {
+        warn!("Run {} failed, exporting partial results", run.id);
+    }
+
+    run_rows(&db, run.id).await
+}
+
+pub async fn sites(settings: &Settings, command: SitesCommand) -> Result<()> {
+    let db = connect(settings).await?;
+    let actor = Actor("cli".to_string());
+
+    match command {
+        SitesCommand::Add { domain, team } => {
+            let domain = validate_domain(domain.trim()).map_err(|e| anyhow!("{domain}: {e}"))?;
+            let txn = db.begin().await?;
+
+            let site = sites::ActiveModel {
+                domain: Set(domain),
+                team_id: Set(team),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+
+            audit::record(
+                &txn,
+                site.id,
+                AuditAction::Created,
+                &actor,
+                audit::field_change("domain", None::<String>, &site.domain),
+            )
+            .await?;
+            txn.commit().await?;
+
+            println!("{}\t{}", site.id, site.domain);
+        }
+        SitesCommand::List { include_deleted } => {
+            let mut query = Sites::find().order_by_asc(sites::Column::Id);
+            if !include_deleted {
+                query = query.filter(sites::Column::DeletedAt.is_null());
+            }
+
+            for site in query.all(&db).await? {
+                match site.deleted_at {
+                    Some(deleted_at) => {
+                        println!("{}\t{}\tdeleted {deleted_at}", site.id, site.domain)
+                    }
+                    None => println!("{}\t{}", site.id, site.domain),
+                }
+            }
+        }
+        SitesCommand::Remove { id } => {
+            audit::set_deleted_at(&db, id, &actor, true)
+                .await?
+                .ok_or_else(|| anyhow!("Site {id} not found or already removed"))?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn keys(settings: &Settings, command: KeysCommand) -> Result<()> {
+    let db = connect(settings).await?;
+
+    match command {
+        KeysCommand::Create { name, mut scopes } => {
+            scopes.sort();
+            scopes.dedup();
+            let (key, secret) = auth::create_key(&db, &name, &scopes).await?;
+            eprintln!(
+                "Created key {} ({}), it is not shown again",
+                key.id, key.name
+            );
+            println!("{secret}");
+        }
+        KeysCommand::List => {
+            let keys = ApiKeys::find()
+                .order_by_asc(api_keys::Column::Id)
+                .all(&db)
+                .await?;
+
+            for key in keys {
+                let scopes: Vec<&str> = key.scopes().iter().map(Scope::as_str).collect();
+                let state = match (key.revoked_at, key.last_used_at) {
+                    (Some(revoked_at), _) => format!("revoked {revoked_at}"),
+                    (None, Some(last_used_at)) => format!("last used {last_used_at}"),
+                    (None, None) => "never used".to_string(),
+                };
+                println!(
+                    "{}\t{}\t{}\t{}\t{state}",
+                    key.id,
+                    key.prefix,
+                    key.name,
+                    scopes.join(",")
+                );
+            }
+        }
+        KeysCommand::Revoke { id } => {
+            let key = ApiKeys::find_by_id(id)
+                .one(&db)
+                .await?
+                .with_context(|| format!("Key {id} not found"))?;
+            let mut key: api_keys::ActiveModel = key.into();
+            key.revoked_at = Set(Some(chrono::Utc::now().naive_utc()));
+            key.update(&db).await?;
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn users(settings: &Settings, command: UsersCommand) -> Result<()> {
+    let db = connect(settings).await?;
+
+    match command {
+        UsersCommand::Create {
+            email,
+            name,
+            password_stdin,
+            admin,
+        } => {
+            let password_hash = if password_stdin {
+                let mut password = String::new();
+                std::io::stdin().read_line(&mut password)?;
+                let password = password.trim_end_matches(['\r', '\n']);
+                if password.is_empty() {
+                    bail!("Empty password on stdin");
+                }
+                Some(accounts::hash_password(password))
+            } else {
+                None
+            };
+
+            let user = users::ActiveModel {
+                email: Set(email.trim().to_lowercase()),
+                name: Set(name),
+                password_hash: Set(password_hash),
+                is_admin: Set(admin),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .with_context(|| format!("Unable to create user {email}"))?;
+
+            println!("{}\t{}", user.id, user.email);
+        }
+        UsersCommand::List => {
+            let users = Users::find()
+                .order_by_asc(users::Column::Id)
+                .all(&db)
+                .await?;
+
+            for user in users {
+                let admin = if user.is_admin { "\tadmin" } else { "" };
+                println!("{}\t{}{admin}", user.id, user.email);
+            }
+        }
+        UsersCommand::Remove { email } => {
+            let user = find_user(&db, &email).await?;
+            user.delete(&db).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn find_user(db: &DatabaseConnection, email: &str) -> Result<users::Model> {
+    Users::find()
+        .filter(users::Column::Email.eq(email.trim().to_lowercase()))
+        .one(db)
+        .await?
+        .with_context(|| format!("User {email} not found"))
+}
+
+pub async fn teams(settings: &Settings, command: TeamsCommand) -> Result<()> {
+    let db = connect(settings).await?;
+
+    match command {
+        TeamsCommand::Create { name } => {
+            let team = teams::ActiveModel {
+                name: Set(name.clone()),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .with_context(|| format!("Unable to create team {name}"))?;
+
+            println!("{}\t{}", team.id, team.name);
+        }
+        TeamsCommand::List => {
+            let teams = Teams::find()
+                .order_by_asc(teams::Column::Id)
+                .find_with_related(Users)
+                .all(&db)
+                .await?;
+            let members = TeamMembers::find().all(&db).await?;
+
+            for (team, users) in teams {
+                println!("{}\t{}", team.id, team.name);
+                for user in users {
+                    let role = members
+                        .iter()
+                        .find(|member| member.team_id == team.id && member.user_id == user.id)
+                        .map(|member| member.role);
+                    if let Some(role) = role {
+                        println!("\t{}\t{}", user.email, role.as_str());
+                    }
+                }
+            }
+        }
+        TeamsCommand::AddMember { team, email, role } => {
+            let user = find_user(&db, &email).await?;
+            Teams::find_by_id(team)
+                .one(&db)
+                .await?
+                .with_context(|| format!("Team {team} not found"))?;
+
+            TeamMembers::insert(team_members::ActiveModel {
+                team_id: Set(team),
+                user_id: Set(user.id),
+                role: Set(role),
+            })
+            .on_conflict(
+                OnConflict::columns([team_members::Column::TeamId, team_members::Column::UserId])
+                    .update_column(team_members::Column::Role)
+                    .to_owned(),
+            )
+            .exec(&db)
+            .await?;
+        }
+        TeamsCommand::RemoveMember { team, email } => {
+            let user = find_user(&db, &email).await?;
+            let result = TeamMembers::delete_by_id((team, user.id)).exec(&db).await?;
+            if result.rows_affected == 0 {
+                bail!("{email} is not a member of team {team}");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn export(settings: &Settings, args: ExportArgs) -> Result<()> {
+    let db = connect(settings).await?;
+
+    Runs::find_by_id(args.run)
+        .one(&db)
+        .await?
+        .ok_or_else(|| anyhow!("Run {} not found", args.run))?;
+
+    let rows = run_rows(&db, args.run).await?;
+    let format = args.format;
+    let output = tokio::task::spawn_blocking(move || export_rows(format, rows)).await??;
+    write_output(args.output, output).await
+}
+
+/// Exit status of `tarin check` when a budget failed, errors exit with 1 so CI
+/// can tell a failed budget from a broken run.
+pub const BUDGET_FAILED: u8 = 2;
+
+/// `BUDGET_FAILED` unless every budget passed. An audit error, or a report
+/// missing a metric one of its budgets bounds, fails the check with an error
+/// instead since the budget could not be judged.
+pub async fn check(settings: &Settings, args: CheckArgs) -> Result<ExitCode> {
+    let content = tokio::fs::read_to_string(&args.budget)
+        .await
+        .with_context(|| format!("Unable to read {}", args.budget.display()))?;
+    let budget_file: BudgetFile = toml::from_str(&content)
+        .with_context(|| format!("Invalid budgets file {}", args.budget.display()))?;
+
+    if let Some(invalid) = budget_file
+        .budgets
+        .iter()
+        .find(|config| !config.budget.is_valid())
+    {
+        bail!(
+            "Budget for {:?} needs a min or max, with min <= max",
+            invalid.budget.metric
+        );
+    }
+
+    let provider = provider_from_settings(&settings.audit)?;
+    let mut report = CheckReport::default();
+
+    for site in args.sites {
+        let base_url = site_base_url(&site)?;
+        let budgets: Vec<Budget> = budget_file
+            .budgets
+            .iter()
+            .filter(|config| config.applies_to_site(&site))
+            .map(|config| config.budget.clone())
+            .collect();
+
+        let reports = audit_site(
+            provider.as_ref(),
+            settings,
+            &base_url,
+            args.strategy,
+            args.samples,
+        )
+        .await?;
+        let mut samples: HashMap<String, i32> = HashMap::new();
+
+        for audit in reports {
+            let url = Url::parse(&audit.url)?;
+            let sample = samples.entry(audit.url.clone()).or_default();
+            *sample += 1;
+
+            if let Some(budget) = budgets
+                .iter()
+                .find(|budget| budget.applies_to(&url) && budget.metric.value(&audit).is_none())
+            {
+                bail!(
+                    "Report for {} has no {} value to check against its budget",
+                    audit.url,
+                    budget.metric.to_value()
+                );
+            }
+
+            report.cases.push(CheckCase {
+                site: site.clone(),
+                url: audit.url.clone(),
+                sample: *sample,
+                outcome: budget_rules::evaluate(&budgets, &url, &audit),
+            });
+        }
+    }
+
+    println!("{}", check::summary(&report));
+
+    if let Some(path) = args.junit {
+        write_output(Some(path), check::junit_xml(&report).into_bytes()).await?;
+    }
+    if let Some(path) = args.sarif {
+        let sarif = serde_json::to_vec_pretty(&check::sarif(&report))?;
+        write_output(Some(path), sarif).await?;
+    }
+
+    Ok(match report.passed() {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::from(BUDGET_FAILED),
+    })
+}
+
+async fn run_rows(db: &DatabaseConnection, run_id: i32) -> Result<Vec<ExportRow>> {
+    let reports: Vec<reports::Model> = Reports::find()
+        .filter(reports::Column::RunId.eq(run_id))
+        .order_by_asc(reports::Column::Id)
+        .all(db)
+        .await?;
+
+    let domains: HashMap<i32, String> = Sites::find()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|site| (site.id, site.domain))
+        .collect();
+
+    Ok(reports
+        .iter()
+        .map(|report| {
+            let domain = domains.get(&report.site_id).map(String::as_str);
+            ExportRow::new(domain.unwrap_or_default(), report)
+        })
+        .collect())
+}
+
+async fn write_output(path: Option<PathBuf>, output: Vec<u8>) -> Result<()> {
+    match path {
+        Some(path) => tokio::fs::write(&path, output)
+            .await
+            .with_context(|| format!("Unable to write {}", path.display())),
+        None => {
+            use std::io::Write;
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(&output)?;
+            Ok(stdout.flush()?)
+        }
+    }
+}
+
+pub async fn jobs(settings: &Settings, command: JobsCommand) -> Result<()> {
+    let db = connect(settings).await?;
+
+    match command {
+        JobsCommand::List { status } => {
+            for job in queue::list(&db, status).await? {
+                let target = job.url.as_deref().unwrap_or("crawl");
+                println!(
+                    "{}\t{}\t{}\t{}\t{}\t{}",
+                    job.id,
+                    job.run_id,
+                    job.status.to_value(),
+                    job.attempts,
+                    target,
+                    job.last_error.as_deref().unwrap_or("")
+                );
+            }
+        }
+        JobsCommand::Retry { id } => {
+            if !queue::retry(&db, id).await? {
+                bail!("Job {id} not found or not dead");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    mod cli {
+        use super::super::*;
+        use clap::CommandFactory;
+
+        #[test]
+        fn verifies_arguments() {
+            Cli::command().debug_assert();
+        }
+
+        #[test]
+        fn serves_by_default() {
+            let cli = Cli::try_parse_from(["tarin", "--migrate"]).unwrap();
+            assert!(cli.command.is_none());
+            assert!(cli.serve.migrate);
+
+            let cli = Cli::try_parse_from(["tarin", "serve", "--migrate"]).unwrap();
+            assert!(matches!(cli.command, Some(Command::Serve(args)) if args.migrate));
+        }
+
+        #[test]
+        fn parses_run_options() {
+            let cli = Cli::try_parse_from([
+                "tarin",
+                "run",
+                "--site",
+                "example.com",
+                "--strategy",
+                "desktop",
+                "--format",
+                "csv",
+            ])
+            .unwrap();
+            let Some(Command::Run(args)) = cli.command else {
+                panic!("expected run");
+            };
+            assert_eq!(args.sites, vec!["example.com"]);
+            assert_eq!(args.strategy, Strategy::Desktop);
+            assert_eq!(args.format, ExportFormat::Csv);
+            assert_eq!(args.samples, 1);
+
+            assert!(Cli::try_parse_from(["tarin", "run", "--samples", "6"]).is_err());
+            assert!(Cli::try_parse_from(["tarin", "run", "--format", "pdf"]).is_err());
+        }
+
+        #[test]
+        fn requires_check_site_and_budget() {
+            assert!(Cli::try_parse_from(["tarin", "check", "--budget", "budgets.toml"]).is_err());
+            assert!(Cli::try_parse_from(["tarin", "check", "--site", "example.com"]).is_err());
+            assert!(Cli::try_parse_from([
+                "tarin",
+                "check",
+                "--site",
+                "example.com",
+                "--budget",
+                "budgets.toml",
+                "--junit",
+                "junit.xml",
+            ])
+            .is_ok());
+        }
+    }
+}
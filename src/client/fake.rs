@@ -0,0 +1,61 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::time::Duration;
+
+use super::provider::AuditProvider;
+use crate::entities::sea_orm_active_enums::Strategy;
+
+const FIXTURE: &str = include_str!("fixtures/psi_report.json");
+
+/// Answers every audit with the same recorded PSI response, for tests and
+/// local development without an API key.
+#[derive(Clone, Default)]
+pub struct FakeAudit;
+
+#[async_trait]
+impl AuditProvider for FakeAudit {
+    fn name(&self) -> &'static str {
+        "fake"
+    }
+
+    fn pause(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    async fn audit(&self, url: &str, strategy: Strategy) -> Result<Value> {
+        let mut report: Value = serde_json::from_str(FIXTURE)?;
+
+        report["id"] = url.into();
+        report["lighthouseResult"]["requestedUrl"] = url.into();
+        report["lighthouseResult"]["finalUrl"] = url.into();
+        report["lighthouseResult"]["configSettings"]["formFactor"] = strategy.as_str().into();
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod audit {
+        use super::super::*;
+        use crate::client::psi::{total_byte_weight, CategoryScores, CoreWebVitals};
+
+        #[tokio::test]
+        async fn returns_fixture_for_url() {
+            let report = FakeAudit
+                .audit("https://example.com/blog", Strategy::Desktop)
+                .await
+                .unwrap();
+
+            assert_eq!(report["id"], "https://example.com/blog");
+            assert_eq!(
+                report["lighthouseResult"]["configSettings"]["formFactor"],
+                "desktop"
+            );
+            assert_eq!(CategoryScores::from_report(&report).performance, Some(0.87));
+            assert_eq!(CoreWebVitals::from_report(&report).inp, Some(180.0));
+            assert_eq!(total_byte_weight(&report), Some(1_482_000.0));
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::process::Stdio;
+use tokio::process::Command;
+
+use super::provider::AuditProvider;
+use crate::entities::sea_orm_active_enums::Strategy;
+
+const CATEGORIES: &str = "performance,accessibility,best-practices,seo";
+
+/// Runs a locally installed Lighthouse CLI, for sites PSI cannot reach.
+#[derive(Clone)]
+pub struct LighthouseCli {
+    program: String,
+    chrome_flags: String,
+}
+
+impl LighthouseCli {
+    pub fn new(program: &str, chrome_flags: &str) -> Self {
+        LighthouseCli {
+            program: program.to_string(),
+            chrome_flags: chrome_flags.to_string(),
+        }
+    }
+
+    fn args(&self, url: &str, strategy: Strategy) -> Vec<String> {
+        let mut args = vec![
+            url.to_string(),
+            "--output=json".to_string(),
+            "--output-path=stdout".to_string(),
+            "--quiet".to_string(),
+            format!("--only-categories={CATEGORIES}"),
+            format!("--chrome-flags={}", self.chrome_flags),
+        ];
+
+        // Lighthouse emulates a mobile device unless told otherwise
+        if strategy == Strategy::Desktop {
+            args.push("--preset=desktop".to_string());
+        }
+
+        args
+    }
+}
+
+#[async_trait]
+impl AuditProvider for LighthouseCli {
+    fn name(&self) -> &'static str {
+        "lighthouse"
+    }
+
+    async fn audit(&self, url: &str, strategy: Strategy) -> Result<Value> {
+        let output = Command::new(&self.program)
+            .args(self.args(url, strategy))
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .output()
+            .await
+            .with_context(|| format!("Unable to run {}", self.program))?;
+
+        if !output.status.success() {
+            bail!(
+                "Lighthouse exited with {} for {url}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let result: Value = serde_json::from_slice(&output.stdout)
+            .context("Unable to parse Lighthouse JSON output")?;
+
+        Ok(json!({ "id": url, "lighthouseResult": result }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod audit {
+        use super::super::*;
+        use crate::client::psi::CategoryScores;
+        use std::{io::Write, os::unix::fs::PermissionsExt};
+        use tempfile::NamedTempFile;
+
+        // Stands in for the CLI, echoing its arguments into the result
+        fn fake_lighthouse(script: &str) -> tempfile::TempPath {
+            let mut file = NamedTempFile::new().unwrap();
+            writeln!(file, "#!/bin/sh\n{script}").unwrap();
+            let path = file.into_temp_path();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+            path
+        }
+
+        #[tokio::test]
+        async fn wraps_lighthouse_result() {
+            let path = fake_lighthouse(
+                r#"echo "{\"finalUrl\": \"$1\", \"configSettings\": {\"args\": \"$*\"}, \"categories\": {\"performance\": {\"score\": 0.91}}}""#,
+            );
+            let lighthouse = LighthouseCli::new(path.to_str().unwrap(), "--headless");
+
+            let report = lighthouse
+                .audit("https://example.com/", Strategy::Desktop)
+                .await
+                .unwrap();
+            assert_eq!(
+                report["lighthouseResult"]["finalUrl"],
+                "https://example.com/"
+            );
+            assert!(report["lighthouseResult"]["configSettings"]["args"]
+                .as_str()
+                .unwrap()
+                .contains("--preset=desktop"));
+            assert_eq!(CategoryScores::from_report(&report).performance, Some(0.91));
+        }
+
+        #[tokio::test]
+        async fn fails_on_non_zero_exit() {
+            let path = fake_lighthouse("echo 'Chrome not found' >&2\nexit 1");
+            let lighthouse = LighthouseCli::new(path.to_str().unwrap(), "--headless");
+
+            let error = lighthouse
+                .audit("https://example.com/", Strategy::Mobile)
+                .await
+                .unwrap_err();
+            assert!(error.to_string().contains("Chrome not found"));
+        }
+    }
+}
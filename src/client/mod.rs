@@ -1,3 +1,6 @@
+pub mod fake;
+pub mod lighthouse;
+pub mod processor;
+pub mod provider;
 pub mod psi;
 pub mod sitemaps;
-pub mod processor;
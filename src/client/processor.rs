@@ -1,84 +1,555 @@
-use anyhow::{anyhow, Result};
-use axum::response::sse::Event;
-use std::{convert::Infallible, sync::Arc, time::Duration};
-use tokio::{
-    sync::{mpsc, Semaphore},
-    task,
-    time::sleep,
+use anyhow::{bail, Context, Result};
+use sea_orm::{
+    sea_query::{Expr, Query},
+    ActiveEnum, ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, ModelTrait, PaginatorTrait, QueryFilter,
+    QueryOrder,
 };
+use std::collections::BTreeMap;
+use tokio::time::sleep;
 use tracing::{error, info};
+use url::Url;
 
 use crate::{
-    client::{psi::PsiClient, sitemaps::extract_sitemap_url_list},
-    utils::get_base_sites,
+    analysis::{
+        budgets::{self as budget_rules, Budget},
+        regressions as detection,
+    },
+    client::{
+        provider::{provider_from_settings, AuditProvider},
+        psi::{total_byte_weight, CategoryScores, CoreWebVitals},
+        sitemaps::extract_sitemap_url_list,
+    },
+    config::{BudgetConfig, RegressionThresholds, Settings},
+    entities::{
+        audit_jobs::{self, Entity as AuditJobs},
+        budget_results,
+        budgets::{self, Entity as Budgets},
+        groups::Entity as Groups,
+        regressions,
+        reports::{self, Entity as Reports},
+        runs::{self, Entity as Runs},
+        sea_orm_active_enums::{JobKind, RunStatus, Strategy},
+        site_urls::{self, Entity as SiteUrls},
+        sites::{self, Entity as Sites},
+    },
+    events::{publish, RunEvent, RunEvents},
+    queue,
+    utils::site_base_url,
 };
 
-pub async fn process_websites(
-    sender: mpsc::Sender<std::result::Result<Event, Infallible>>,
-) -> Result<()> {
-    let websites = get_base_sites().await?;
+/// Queues crawls for runs that have no jobs, i.e. runs created or interrupted
+/// before the job queue existed. Runs whose group or sites have since been
+/// removed are failed instead.
+pub async fn queue_unfinished_runs(
+    db: &DatabaseConnection,
+    events: &RunEvents,
+) -> Result<usize, DbErr> {
+    let unfinished = Runs::find()
+        .filter(runs::Column::Status.is_in([
+            RunStatus::Pending,
+            RunStatus::Running,
+            RunStatus::Interrupted,
+        ]))
+        .filter(
+            runs::Column::Id.not_in_subquery(
+                Query::select()
+                    .column(audit_jobs::Column::RunId)
+                    .from(AuditJobs)
+                    .to_owned(),
+            ),
+        )
+        .order_by_asc(runs::Column::Id)
+        .all(db)
+        .await?;
+    let mut queued = 0;
+
+    for run in unfinished {
+        let group = match run.group_id {
+            Some(group_id) => Groups::find_by_id(group_id).one(db).await?,
+            None => None,
+        };
+        let sites = match group {
+            Some(group) => {
+                group
+                    .find_related(Sites)
+                    .filter(sites::Column::DeletedAt.is_null())
+                    .all(db)
+                    .await?
+            }
+            None => Vec::new(),
+        };
+
+        if sites.is_empty() {
+            let run = set_run_status(db, run.id, RunStatus::Failed).await?;
+            publish(events, RunEvent::Completed(run));
+            continue;
+        }
 
-    let psi_key = match std::env::var("PSI_KEY") {
-        Ok(key) => key,
-        Err(e) => return Err(anyhow!("Missing Page Speed Insights API key: {}", e)),
+        info!("Queueing unfinished run {}", run.id);
+        queue::enqueue_run(db, &run, &sites).await?;
+        queued += 1;
+    }
+
+    Ok(queued)
+}
+
+/// Carries out a claimed job. A crawl queues an audit per sampled url of its
+/// site, an audit stores the report, its budget result and publishes both.
+///
+/// Jobs may run more than once after a worker dies, so samples already stored
+/// against the run are not audited again.
+pub async fn execute_job(
+    db: &DatabaseConnection,
+    events: &RunEvents,
+    settings: &Settings,
+    job: &audit_jobs::Model,
+) -> Result<()> {
+    let Some(run) = Runs::find_by_id(job.run_id).one(db).await? else {
+        return Ok(());
+    };
+    let site = Sites::find_by_id(job.site_id)
+        .filter(sites::Column::DeletedAt.is_null())
+        .one(db)
+        .await?;
+    let Some(site) = site else {
+        info!("Skipping job {}, site {} was removed", job.id, job.site_id);
+        return Ok(());
     };
 
-    let psi_url = match std::env::var("PSI_URL") {
-        Ok(url) => url,
-        Err(e) => return Err(anyhow!("Missing Page Speed Insights URL: {}", e)),
+    start_run(db, run.id).await?;
+
+    match job.kind {
+        JobKind::Crawl => {
+            let base_url = site_base_url(&site.domain)
+                .with_context(|| format!("Invalid site domain: {}", site.domain))?;
+            let site_urls = extract_sitemap_url_list(&base_url, settings)
+                .await
+                .with_context(|| format!("Failed to extract urls from sitemaps for: {base_url}"))?;
+
+            let queued = queue::enqueue_audits(db, &run, site.id, &site_urls).await?;
+            info!(
+                "Queued {queued} audit(s) for {} in run {}",
+                site.domain, run.id
+            );
+        }
+        JobKind::Audit => {
+            let url = job
+                .url
+                .as_deref()
+                .map(Url::parse)
+                .transpose()?
+                .with_context(|| format!("Audit job {} has no url", job.id))?;
+            let sample = job.sample.unwrap_or(1);
+
+            let stored = Reports::find()
+                .filter(reports::Column::RunId.eq(run.id))
+                .filter(reports::Column::SiteId.eq(site.id))
+                .filter(reports::Column::Url.eq(url.as_str()))
+                .count(db)
+                .await?;
+            if stored >= sample as u64 {
+                return Ok(());
+            }
+
+            let provider = provider_from_settings(&settings.audit)?;
+            info!(
+                "Running {} report for: {} ({sample}/{}) ...",
+                provider.name(),
+                url,
+                run.samples
+            );
+
+            let psi_res = provider
+                .audit(url.as_ref(), job.strategy)
+                .await
+                .with_context(|| format!("Error fetching report for: {url}"))?;
+            if let Some(error) = psi_res.get("error") {
+                bail!("Audit of {url} returned an error: {error}");
+            }
+
+            let report = save_report(db, &run, &site, &url, psi_res).await?;
+
+            let budgets = site_budgets(db, &site, &settings.budgets).await?;
+            match save_budget_result(db, &budgets, &url, &report).await {
+                Ok(Some(result)) => publish(events, RunEvent::Budget(result)),
+                Ok(None) => (),
+                Err(e) => error!("Error saving budget result for {}: {e}", url),
+            }
+
+            publish(events, RunEvent::Report(report));
+
+            sleep(provider.pause()).await;
+        }
+    }
+
+    Ok(())
+}
+
+/// Completes the run once none of its jobs are left to work on, flagging
+/// regressions against the previous comparable run. A run is failed instead
+/// when no site stored a report, whether its jobs were dead-lettered or its
+/// sitemaps had nothing to audit.
+///
+/// Several workers may see the last job finish, only the one that stamps
+/// `finished_at` goes on.
+pub async fn finish_run_if_done(
+    db: &DatabaseConnection,
+    events: &RunEvents,
+    settings: &Settings,
+    run_id: i32,
+) -> Result<(), DbErr> {
+    if queue::open_jobs(db, run_id).await? > 0 {
+        return Ok(());
+    }
+
+    let finished = Runs::update_many()
+        .col_expr(
+            runs::Column::FinishedAt,
+            Expr::value(chrono::Utc::now().naive_utc()),
+        )
+        .filter(runs::Column::Id.eq(run_id))
+        .filter(runs::Column::FinishedAt.is_null())
+        .exec_with_returning(db)
+        .await?;
+    let Some(run) = finished.into_iter().next() else {
+        return Ok(());
     };
 
-    let psi_client = Arc::new(PsiClient::new(&psi_url, &psi_key));
-    let semaphore = Arc::new(Semaphore::new(10));
-
-    for site in websites {
-        let sender = sender.clone();
-        let psi_client = psi_client.clone();
-        let semaphore = semaphore.clone();
-
-        task::spawn(async move {
-            let _permit = match semaphore.acquire_owned().await {
-                Ok(permit) => permit,
-                Err(e) => return error!("Failed to acquire permit from semaphore: {e}"),
-            };
-
-            let site_urls = match extract_sitemap_url_list(&site).await {
-                Ok(urls_list) => urls_list,
-                Err(_) => {
-                    return error!(
-                        "Failed to extract urls from sitemaps for: {}",
-                        site.as_ref()
-                    )
-                }
-            };
-
-            for url in site_urls {
-                info!("Running PSI report for: {} ...", url);
-
-                let psi_res = match psi_client.get_report(url.as_ref()).await {
-                    Ok(res) => res,
-                    Err(_) => {
-                        error!("Error fetching report for: {}", url);
-                        break;
-                    }
-                };
-
-                let psi_event = match Event::default().json_data(psi_res) {
-                    Ok(event) => event,
-                    Err(_) => {
-                        error!("Error creating event from PSI report data for: {}", url);
-                        break;
-                    }
-                };
-
-                if sender.send(Ok(psi_event)).await.is_err() {
-                    break;
+    let reports = Reports::find()
+        .filter(reports::Column::RunId.eq(run.id))
+        .count(db)
+        .await?;
+
+    let status = if reports == 0 {
+        RunStatus::Failed
+    } else {
+        match detect_regressions(db, &run, &settings.regressions).await {
+            Ok(regressions) => {
+                for regression in regressions {
+                    publish(events, RunEvent::Regression(regression));
                 }
+            }
+            Err(e) => error!("Failed to detect regressions for run {}: {e}", run.id),
+        }
+        RunStatus::Completed
+    };
+
+    let run = set_run_status(db, run.id, status).await?;
+    publish(events, RunEvent::Completed(run));
+
+    Ok(())
+}
+
+// Marks the run as running when its first job is picked up
+async fn start_run(db: &DatabaseConnection, run_id: i32) -> Result<(), DbErr> {
+    Runs::update_many()
+        .col_expr(
+            runs::Column::Status,
+            Expr::value(RunStatus::Running.to_value()),
+        )
+        .filter(runs::Column::Id.eq(run_id))
+        .filter(runs::Column::Status.is_in([RunStatus::Pending, RunStatus::Interrupted]))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Audits every sampled url of a site without storing anything, for use
+/// outside the server. Fails on the first audit that returned an error body,
+/// like the queued jobs do, so a quota or lookup error is never read as a
+/// report without metrics.
+pub async fn audit_site(
+    provider: &dyn AuditProvider,
+    settings: &Settings,
+    base_url: &Url,
+    strategy: Strategy,
+    samples: i32,
+) -> Result<Vec<reports::Model>> {
+    let mut reports = Vec::new();
+
+    for url in extract_sitemap_url_list(base_url, settings).await? {
+        for sample in 1..=samples {
+            info!(
+                "Running {} report for: {} ({sample}/{samples}) ...",
+                provider.name(),
+                url
+            );
 
-                sleep(Duration::from_secs(2)).await;
+            let data = provider
+                .audit(url.as_ref(), strategy)
+                .await
+                .with_context(|| format!("Error fetching report for: {url}"))?;
+            if let Some(error) = data.get("error") {
+                bail!("Audit of {url} returned an error: {error}");
             }
-        });
+            reports.push(report_from_psi(&url, strategy, data));
+
+            sleep(provider.pause()).await;
+        }
     }
 
-    Ok(())
+    Ok(reports)
+}
+
+async fn detect_regressions(
+    db: &DatabaseConnection,
+    run: &runs::Model,
+    thresholds: &RegressionThresholds,
+) -> Result<Vec<regressions::Model>, DbErr> {
+    let current = Reports::find()
+        .filter(reports::Column::RunId.eq(run.id))
+        .all(db)
+        .await?;
+
+    let mut by_url: BTreeMap<i32, Vec<reports::Model>> = BTreeMap::new();
+    for report in current {
+        by_url.entry(report.site_url_id).or_default().push(report);
+    }
+
+    let mut stored = Vec::new();
+
+    for (site_url_id, current) in by_url {
+        let previous_report = Reports::find()
+            .inner_join(Runs)
+            .filter(reports::Column::SiteUrlId.eq(site_url_id))
+            .filter(reports::Column::Strategy.eq(run.strategy))
+            .filter(reports::Column::RunId.lt(run.id))
+            .filter(runs::Column::Status.eq(RunStatus::Completed))
+            .order_by_desc(reports::Column::RunId)
+            .one(db)
+            .await?;
+
+        let Some(previous_report) = previous_report else {
+            continue;
+        };
+
+        let previous = Reports::find()
+            .filter(reports::Column::RunId.eq(previous_report.run_id))
+            .filter(reports::Column::SiteUrlId.eq(site_url_id))
+            .filter(reports::Column::Strategy.eq(run.strategy))
+            .all(db)
+            .await?;
+
+        for finding in detection::detect(&previous, &current, thresholds) {
+            let regression = regressions::ActiveModel {
+                run_id: Set(run.id),
+                previous_run_id: Set(previous_report.run_id),
+                site_id: Set(current[0].site_id),
+                site_url_id: Set(site_url_id),
+                url: Set(current[0].url.clone()),
+                strategy: Set(run.strategy),
+                metric: Set(finding.metric),
+                previous_value: Set(finding.previous_value),
+                current_value: Set(finding.current_value),
+                tolerance: Set(finding.tolerance),
+                ..Default::default()
+            }
+            .insert(db)
+            .await?;
+
+            stored.push(regression);
+        }
+    }
+
+    Ok(stored)
+}
+
+async fn set_run_status(
+    db: &DatabaseConnection,
+    run_id: i32,
+    status: RunStatus,
+) -> Result<runs::Model, DbErr> {
+    let finished = matches!(status, RunStatus::Completed | RunStatus::Failed);
+
+    let mut run = runs::ActiveModel {
+        id: Set(run_id),
+        status: Set(status),
+        ..Default::default()
+    };
+
+    if finished {
+        run.finished_at = Set(Some(chrono::Utc::now().naive_utc()));
+    }
+
+    run.update(db).await
+}
+
+/// An unsaved report with scores and metrics extracted from the PSI response,
+/// run, site and url ids are left for the caller to fill in.
+pub fn report_from_psi(url: &Url, strategy: Strategy, data: serde_json::Value) -> reports::Model {
+    let scores = CategoryScores::from_report(&data);
+    let vitals = CoreWebVitals::from_report(&data);
+
+    reports::Model {
+        id: 0,
+        run_id: 0,
+        site_id: 0,
+        site_url_id: 0,
+        url: url.to_string(),
+        strategy,
+        performance: scores.performance,
+        accessibility: scores.accessibility,
+        best_practices: scores.best_practices,
+        seo: scores.seo,
+        lcp: vitals.lcp,
+        cls: vitals.cls,
+        inp: vitals.inp,
+        tbt: vitals.tbt,
+        fcp: vitals.fcp,
+        ttfb: vitals.ttfb,
+        total_byte_weight: total_byte_weight(&data),
+        data,
+        created_at: chrono::Utc::now().naive_utc(),
+    }
+}
+
+async fn save_report(
+    db: &DatabaseConnection,
+    run: &runs::Model,
+    site: &sites::Model,
+    url: &Url,
+    data: serde_json::Value,
+) -> Result<reports::Model, DbErr> {
+    let site_url = find_or_create_site_url(db, site.id, url).await?;
+
+    let mut report: reports::ActiveModel = report_from_psi(url, run.strategy, data).into();
+    report.id = NotSet;
+    report.created_at = NotSet;
+    report.run_id = Set(run.id);
+    report.site_id = Set(site.id);
+    report.site_url_id = Set(site_url.id);
+
+    report.insert(db).await
+}
+
+// Budgets stored against the site plus any from the settings that target it
+async fn site_budgets(
+    db: &DatabaseConnection,
+    site: &sites::Model,
+    config_budgets: &[BudgetConfig],
+) -> Result<Vec<Budget>, DbErr> {
+    let mut site_budgets: Vec<Budget> = Budgets::find()
+        .filter(budgets::Column::SiteId.eq(site.id))
+        .all(db)
+        .await?
+        .iter()
+        .map(Budget::from)
+        .collect();
+
+    site_budgets.extend(
+        config_budgets
+            .iter()
+            .filter(|config| config.applies_to_site(&site.domain))
+            .map(|config| config.budget.clone()),
+    );
+
+    Ok(site_budgets)
+}
+
+async fn save_budget_result(
+    db: &DatabaseConnection,
+    budgets: &[Budget],
+    url: &Url,
+    report: &reports::Model,
+) -> Result<Option<budget_results::Model>, DbErr> {
+    let Some(outcome) = budget_rules::evaluate(budgets, url, report) else {
+        return Ok(None);
+    };
+
+    let failures =
+        serde_json::to_value(&outcome.failures).map_err(|e| DbErr::Custom(e.to_string()))?;
+
+    let result = budget_results::ActiveModel {
+        run_id: Set(report.run_id),
+        report_id: Set(report.id),
+        site_id: Set(report.site_id),
+        url: Set(report.url.clone()),
+        passed: Set(outcome.passed),
+        failures: Set(failures),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    Ok(Some(result))
+}
+
+async fn find_or_create_site_url(
+    db: &DatabaseConnection,
+    site_id: i32,
+    url: &Url,
+) -> Result<site_urls::Model, DbErr> {
+    let existing = SiteUrls::find()
+        .filter(site_urls::Column::SiteId.eq(site_id))
+        .filter(site_urls::Column::Url.eq(url.as_str()))
+        .one(db)
+        .await?;
+
+    if let Some(site_url) = existing {
+        return Ok(site_url);
+    }
+
+    site_urls::ActiveModel {
+        site_id: Set(site_id),
+        url: Set(url.to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    mod audit_site {
+        use super::super::*;
+        use async_trait::async_trait;
+        use mockito::Server;
+        use serde_json::{json, Value};
+        use std::time::Duration;
+
+        // Answers like PSI does once the API key's quota is used up
+        struct QuotaExceeded;
+
+        #[async_trait]
+        impl AuditProvider for QuotaExceeded {
+            fn name(&self) -> &'static str {
+                "quota"
+            }
+
+            fn pause(&self) -> Duration {
+                Duration::ZERO
+            }
+
+            async fn audit(&self, _url: &str, _strategy: Strategy) -> Result<Value> {
+                Ok(json!({ "error": { "code": 429, "message": "Quota exceeded" } }))
+            }
+        }
+
+        #[tokio::test]
+        async fn fails_on_error_body() {
+            let mut server = Server::new_async().await;
+            let page = format!("{}/about", server.url());
+            server
+                .mock("GET", "/sitemaps.xml")
+                .with_body(format!("<urlset><url><loc>{page}</loc></url></urlset>"))
+                .create_async()
+                .await;
+
+            let base_url = Url::parse(&server.url()).unwrap();
+            let error = audit_site(
+                &QuotaExceeded,
+                &Settings::default(),
+                &base_url,
+                Strategy::Mobile,
+                1,
+            )
+            .await
+            .unwrap_err();
+
+            assert!(error.to_string().contains("Quota exceeded"));
+        }
+    }
 }
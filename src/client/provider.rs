@@ -0,0 +1,86 @@
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use super::{fake::FakeAudit, lighthouse::LighthouseCli, psi::PsiClient};
+use crate::{
+    config::{AuditBackend, AuditSettings},
+    entities::sea_orm_active_enums::Strategy,
+    metrics::metrics,
+};
+
+/// Something that can run a Lighthouse audit of a url.
+///
+/// Results are returned in the PSI response shape, with the Lighthouse result
+/// under `lighthouseResult`, so the report parsing works for every backend.
+#[async_trait]
+pub trait AuditProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Wait between audits, PSI is rate limited per API key.
+    fn pause(&self) -> Duration {
+        Duration::from_secs(2)
+    }
+
+    async fn audit(&self, url: &str, strategy: Strategy) -> Result<Value>;
+}
+
+/// Builds the backend chosen by `audit.provider`.
+pub fn provider_from_settings(settings: &AuditSettings) -> Result<Arc<dyn AuditProvider>> {
+    match settings.provider {
+        AuditBackend::Psi => {
+            let Some(psi_key) = &settings.psi_key else {
+                bail!("Missing setting `audit.psi_key`, set TARIN_AUDIT_PSI_KEY or PSI_KEY");
+            };
+            Ok(Arc::new(Measured(PsiClient::new(
+                &settings.psi_url,
+                psi_key,
+            ))))
+        }
+        AuditBackend::Lighthouse => Ok(Arc::new(Measured(LighthouseCli::new(
+            &settings.lighthouse_path,
+            &settings.lighthouse_chrome_flags,
+        )))),
+        AuditBackend::Fake => Ok(Arc::new(Measured(FakeAudit))),
+    }
+}
+
+/// Counts and times the audits of the wrapped backend.
+struct Measured<P>(P);
+
+#[async_trait]
+impl<P: AuditProvider> AuditProvider for Measured<P> {
+    fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    fn pause(&self) -> Duration {
+        self.0.pause()
+    }
+
+    async fn audit(&self, url: &str, strategy: Strategy) -> Result<Value> {
+        let start = Instant::now();
+        let result = self.0.audit(url, strategy).await;
+
+        // PSI reports quota and lookup failures in the body
+        let outcome = match &result {
+            Ok(report) if report.get("error").is_none() => "ok",
+            _ => "error",
+        };
+        let metrics = metrics();
+        metrics
+            .audit_duration
+            .with_label_values(&[self.name()])
+            .observe(start.elapsed().as_secs_f64());
+        metrics
+            .audits
+            .with_label_values(&[self.name(), outcome])
+            .inc();
+
+        result
+    }
+}
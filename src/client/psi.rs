@@ -1,6 +1,14 @@
 use anyhow::{Context, Result};
+use async_trait::async_trait;
 use reqwest::Client;
+use serde::Serialize;
 use serde_json::Value;
+use utoipa::ToSchema;
+
+use super::provider::AuditProvider;
+use crate::entities::{reports, sea_orm_active_enums::Strategy};
+
+const CATEGORIES: [&str; 4] = ["performance", "accessibility", "best-practices", "seo"];
 
 #[derive(Clone)]
 pub struct PsiClient {
@@ -18,8 +26,14 @@ impl PsiClient {
         }
     }
 
-    pub async fn get_report(&self, report_url: &str) -> Result<Value> {
-        let params = [("url", report_url), ("key", &self.api_key)];
+    pub async fn get_report(&self, report_url: &str, strategy: Strategy) -> Result<Value> {
+        let mut params = vec![
+            ("url", report_url),
+            ("key", &self.api_key),
+            ("strategy", strategy.as_str()),
+        ];
+        params.extend(CATEGORIES.iter().map(|category| ("category", *category)));
+
         let response = self
             .client
             .get(&self.base_url)
@@ -34,3 +48,369 @@ impl PsiClient {
         Ok(response)
     }
 }
+
+#[async_trait]
+impl AuditProvider for PsiClient {
+    fn name(&self) -> &'static str {
+        "psi"
+    }
+
+    async fn audit(&self, url: &str, strategy: Strategy) -> Result<Value> {
+        self.get_report(url, strategy).await
+    }
+}
+
+/// Lighthouse category scores in the 0-1 range reported by PSI.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, ToSchema)]
+pub struct CategoryScores {
+    pub performance: Option<f64>,
+    pub accessibility: Option<f64>,
+    pub best_practices: Option<f64>,
+    pub seo: Option<f64>,
+}
+
+impl CategoryScores {
+    pub fn from_report(report: &Value) -> Self {
+        let score = |category: &str| {
+            report
+                .pointer(&format!("/lighthouseResult/categories/{category}/score"))
+                .and_then(Value::as_f64)
+        };
+
+        CategoryScores {
+            performance: score("performance"),
+            accessibility: score("accessibility"),
+            best_practices: score("best-practices"),
+            seo: score("seo"),
+        }
+    }
+
+    /// Averages each category independently, ignoring missing scores.
+    pub fn average<'a>(scores: impl IntoIterator<Item = &'a CategoryScores>) -> Self {
+        let mut sums = [(0.0, 0u32); 4];
+
+        for entry in scores {
+            let values = [
+                entry.performance,
+                entry.accessibility,
+                entry.best_practices,
+                entry.seo,
+            ];
+            for (sum, value) in sums.iter_mut().zip(values) {
+                if let Some(value) = value {
+                    sum.0 += value;
+                    sum.1 += 1;
+                }
+            }
+        }
+
+        let mean = |(total, count): (f64, u32)| (count > 0).then(|| total / count as f64);
+
+        CategoryScores {
+            performance: mean(sums[0]),
+            accessibility: mean(sums[1]),
+            best_practices: mean(sums[2]),
+            seo: mean(sums[3]),
+        }
+    }
+}
+
+/// Core web vitals, timings are in milliseconds and CLS is unitless.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, ToSchema)]
+pub struct CoreWebVitals {
+    pub lcp: Option<f64>,
+    pub cls: Option<f64>,
+    pub inp: Option<f64>,
+    pub tbt: Option<f64>,
+    pub fcp: Option<f64>,
+    pub ttfb: Option<f64>,
+}
+
+impl CoreWebVitals {
+    pub fn from_report(report: &Value) -> Self {
+        let audit = |id: &str| {
+            report
+                .pointer(&format!("/lighthouseResult/audits/{id}/numericValue"))
+                .and_then(Value::as_f64)
+        };
+
+        // INP is a field metric so only comes from CrUX loading experience data
+        let inp = report
+            .pointer("/loadingExperience/metrics/INTERACTION_TO_NEXT_PAINT/percentile")
+            .and_then(Value::as_f64);
+
+        CoreWebVitals {
+            lcp: audit("largest-contentful-paint"),
+            cls: audit("cumulative-layout-shift"),
+            inp,
+            tbt: audit("total-blocking-time"),
+            fcp: audit("first-contentful-paint"),
+            ttfb: audit("server-response-time"),
+        }
+    }
+}
+
+pub fn total_byte_weight(report: &Value) -> Option<f64> {
+    report
+        .pointer("/lighthouseResult/audits/total-byte-weight/numericValue")
+        .and_then(Value::as_f64)
+}
+
+/// A Lighthouse opportunity audit with its estimated savings.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Opportunity {
+    pub id: String,
+    pub title: String,
+    pub savings_ms: f64,
+}
+
+/// Opportunities with the largest estimated savings first.
+pub fn top_opportunities(report: &Value, limit: usize) -> Vec<Opportunity> {
+    let Some(audits) = report
+        .pointer("/lighthouseResult/audits")
+        .and_then(Value::as_object)
+    else {
+        return Vec::new();
+    };
+
+    let mut opportunities: Vec<Opportunity> = audits
+        .iter()
+        .filter(|(_, audit)| audit.pointer("/details/type") == Some(&Value::from("opportunity")))
+        .filter_map(|(id, audit)| {
+            let savings_ms = audit
+                .pointer("/details/overallSavingsMs")
+                .and_then(Value::as_f64)?;
+
+            (savings_ms > 0.0).then(|| Opportunity {
+                id: id.clone(),
+                title: audit
+                    .get("title")
+                    .and_then(Value::as_str)
+                    .unwrap_or(id)
+                    .to_string(),
+                savings_ms,
+            })
+        })
+        .collect();
+
+    opportunities.sort_by(|a, b| b.savings_ms.total_cmp(&a.savings_ms));
+    opportunities.truncate(limit);
+    opportunities
+}
+
+impl From<&reports::Model> for CoreWebVitals {
+    fn from(report: &reports::Model) -> Self {
+        CoreWebVitals {
+            lcp: report.lcp,
+            cls: report.cls,
+            inp: report.inp,
+            tbt: report.tbt,
+            fcp: report.fcp,
+            ttfb: report.ttfb,
+        }
+    }
+}
+
+impl From<&reports::Model> for CategoryScores {
+    fn from(report: &reports::Model) -> Self {
+        CategoryScores {
+            performance: report.performance,
+            accessibility: report.accessibility,
+            best_practices: report.best_practices,
+            seo: report.seo,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod get_report {
+        use super::super::*;
+        use mockito::{Matcher, Server};
+
+        #[tokio::test]
+        async fn requests_every_category() {
+            let mut server = Server::new_async().await;
+            let mock = server
+                .mock("GET", "/runPagespeed")
+                .match_query(Matcher::AllOf(vec![
+                    Matcher::UrlEncoded("url".into(), "https://example.com/".into()),
+                    Matcher::UrlEncoded("key".into(), "secret".into()),
+                    Matcher::UrlEncoded("strategy".into(), "desktop".into()),
+                    Matcher::Regex("category=performance&.*category=seo".into()),
+                ]))
+                .with_body(include_str!("fixtures/psi_report.json"))
+                .create_async()
+                .await;
+
+            let client = PsiClient::new(&format!("{}/runPagespeed", server.url()), "secret");
+            let report = client
+                .get_report("https://example.com/", Strategy::Desktop)
+                .await
+                .unwrap();
+
+            mock.assert_async().await;
+            assert_eq!(CategoryScores::from_report(&report).performance, Some(0.87));
+        }
+
+        #[tokio::test]
+        async fn fails_on_upstream_error_page() {
+            let mut server = Server::new_async().await;
+            server
+                .mock("GET", "/runPagespeed")
+                .match_query(Matcher::Any)
+                .with_status(502)
+                .with_body("Bad gateway")
+                .create_async()
+                .await;
+
+            let client = PsiClient::new(&format!("{}/runPagespeed", server.url()), "secret");
+            let result = client
+                .get_report("https://example.com/", Strategy::Mobile)
+                .await;
+            assert!(result.is_err());
+        }
+
+        #[tokio::test]
+        async fn fails_on_invalid_json() {
+            let mut server = Server::new_async().await;
+            server
+                .mock("GET", "/runPagespeed")
+                .match_query(Matcher::Any)
+                .with_body(r#"{"lighthouseResult": {"categories": "#)
+                .create_async()
+                .await;
+
+            let client = PsiClient::new(&format!("{}/runPagespeed", server.url()), "secret");
+            let result = client
+                .get_report("https://example.com/", Strategy::Mobile)
+                .await;
+            assert!(result.is_err());
+        }
+    }
+
+    mod category_scores {
+        use super::super::*;
+        use serde_json::json;
+
+        #[test]
+        fn extracts_scores_from_report() {
+            let report = json!({
+                "lighthouseResult": {
+                    "categories": {
+                        "performance": { "score": 0.91 },
+                        "accessibility": { "score": 0.8 },
+                        "best-practices": { "score": 1 },
+                        "seo": { "score": null }
+                    }
+                }
+            });
+
+            let scores = CategoryScores::from_report(&report);
+            assert_eq!(scores.performance, Some(0.91));
+            assert_eq!(scores.accessibility, Some(0.8));
+            assert_eq!(scores.best_practices, Some(1.0));
+            assert_eq!(scores.seo, None);
+        }
+
+        #[test]
+        fn missing_lighthouse_result() {
+            let scores = CategoryScores::from_report(&json!({ "error": {} }));
+            assert_eq!(scores, CategoryScores::default());
+        }
+
+        #[test]
+        fn average_ignores_missing_scores() {
+            let first = CategoryScores {
+                performance: Some(0.5),
+                accessibility: Some(1.0),
+                best_practices: None,
+                seo: None,
+            };
+            let second = CategoryScores {
+                performance: Some(1.0),
+                accessibility: None,
+                best_practices: Some(0.4),
+                seo: None,
+            };
+
+            let average = CategoryScores::average([&first, &second]);
+            assert_eq!(average.performance, Some(0.75));
+            assert_eq!(average.accessibility, Some(1.0));
+            assert_eq!(average.best_practices, Some(0.4));
+            assert_eq!(average.seo, None);
+        }
+    }
+
+    mod core_web_vitals {
+        use super::super::*;
+        use serde_json::json;
+
+        #[test]
+        fn extracts_lab_and_field_metrics() {
+            let report = json!({
+                "loadingExperience": {
+                    "metrics": {
+                        "INTERACTION_TO_NEXT_PAINT": { "percentile": 180 }
+                    }
+                },
+                "lighthouseResult": {
+                    "audits": {
+                        "largest-contentful-paint": { "numericValue": 2400.5 },
+                        "cumulative-layout-shift": { "numericValue": 0.05 },
+                        "total-blocking-time": { "numericValue": 120 },
+                        "first-contentful-paint": { "numericValue": 900 },
+                        "server-response-time": { "numericValue": 210 }
+                    }
+                }
+            });
+
+            let vitals = CoreWebVitals::from_report(&report);
+            assert_eq!(vitals.lcp, Some(2400.5));
+            assert_eq!(vitals.cls, Some(0.05));
+            assert_eq!(vitals.inp, Some(180.0));
+            assert_eq!(vitals.tbt, Some(120.0));
+            assert_eq!(vitals.fcp, Some(900.0));
+            assert_eq!(vitals.ttfb, Some(210.0));
+        }
+
+        #[test]
+        fn missing_field_data() {
+            let vitals = CoreWebVitals::from_report(&json!({ "lighthouseResult": {} }));
+            assert_eq!(vitals, CoreWebVitals::default());
+        }
+    }
+
+    mod top_opportunities {
+        use super::super::*;
+        use serde_json::json;
+
+        #[test]
+        fn orders_by_savings_and_skips_other_audits() {
+            let report = json!({
+                "lighthouseResult": {
+                    "audits": {
+                        "render-blocking-resources": {
+                            "title": "Eliminate render-blocking resources",
+                            "details": { "type": "opportunity", "overallSavingsMs": 300 }
+                        },
+                        "uses-webp-images": {
+                            "title": "Serve images in next-gen formats",
+                            "details": { "type": "opportunity", "overallSavingsMs": 1200 }
+                        },
+                        "unused-css-rules": {
+                            "title": "Reduce unused CSS",
+                            "details": { "type": "opportunity", "overallSavingsMs": 0 }
+                        },
+                        "largest-contentful-paint": { "numericValue": 2400 }
+                    }
+                }
+            });
+
+            let opportunities = top_opportunities(&report, 5);
+            let ids: Vec<&str> = opportunities.iter().map(|o| o.id.as_str()).collect();
+            assert_eq!(ids, vec!["uses-webp-images", "render-blocking-resources"]);
+            assert_eq!(top_opportunities(&report, 1).len(), 1);
+        }
+    }
+}
@@ -7,9 +7,23 @@ use quick_xml::reader::Reader;
 use tracing::info;
 use url::Url;
 
-use crate::config::{load_config, Config};
+use crate::{config::Settings, metrics::metrics};
 
 async fn fetch_sitemap(url: &Url) -> Result<String> {
+    let timer = metrics().sitemap_duration.start_timer();
+    let result = request_sitemap(url).await;
+    timer.observe_duration();
+
+    let outcome = if result.is_ok() { "ok" } else { "error" };
+    metrics()
+        .sitemap_fetches
+        .with_label_values(&[outcome])
+        .inc();
+
+    result
+}
+
+async fn request_sitemap(url: &Url) -> Result<String> {
     // TODO: look into refactoring this into a client, see https://docs.rs/reqwest/latest/reqwest/?search=params#making-a-get-request
     let response = reqwest::get(url.to_string()).await?;
 
@@ -61,9 +75,7 @@ fn remove_duplicates(mut source_set: Vec<Url>) -> Vec<Url> {
     source_set
 }
 
-pub async fn extract_sitemap_url_list(base_url: &Url) -> Result<Vec<Url>> {
-    let config = load_config("config.toml").await;
-
+pub async fn extract_sitemap_url_list(base_url: &Url, settings: &Settings) -> Result<Vec<Url>> {
     let sitemap_index_url =
         build_sitemap_index_url(base_url).context("Failed to build sitemap index url")?;
 
@@ -74,10 +86,11 @@ pub async fn extract_sitemap_url_list(base_url: &Url) -> Result<Vec<Url>> {
         &sitemap_index_url,
         &mut all_urls,
         &mut visited_sitemaps,
-        &config,
+        settings,
     )
     .await?;
 
+    metrics().sitemap_urls.inc_by(all_urls.len() as u64);
     Ok(all_urls)
 }
 
@@ -86,7 +99,7 @@ async fn fetch_sitemap_recurse(
     sitemap_url: &Url,
     all_urls: &mut Vec<Url>,
     visited_sitemaps: &mut HashSet<Url>,
-    config: &Option<Config>,
+    settings: &Settings,
 ) -> Result<()> {
     if !visited_sitemaps.insert(sitemap_url.to_owned()) {
         return Ok(());
@@ -101,27 +114,25 @@ async fn fetch_sitemap_recurse(
     let mut seen_patterns = HashSet::new();
 
     for url in unique_urls {
-        if let Some(ref config) = config {
-            if config
-                .ignore_paths
-                .iter()
-                .any(|ignore_path| url.path().contains(ignore_path))
-            {
-                info!("Matched ignore list: {url}");
-                continue;
-            }
+        if settings
+            .ignore_paths
+            .iter()
+            .any(|ignore_path| url.path().contains(ignore_path))
+        {
+            info!("Matched ignore list: {url}");
+            continue;
+        }
 
-            if let Some(matched_pattern) = get_pattern(&url, &config.patterns) {
-                if seen_patterns.contains(&matched_pattern) {
-                    info!("Skipping duplicate pattern: {url}");
-                    continue;
-                }
-                seen_patterns.insert(matched_pattern.clone());
+        if let Some(matched_pattern) = get_pattern(&url, &settings.patterns) {
+            if seen_patterns.contains(&matched_pattern) {
+                info!("Skipping duplicate pattern: {url}");
+                continue;
             }
+            seen_patterns.insert(matched_pattern.clone());
         }
 
         if url.path().contains("sitemaps/") {
-            fetch_sitemap_recurse(&url, all_urls, visited_sitemaps, config).await?;
+            fetch_sitemap_recurse(&url, all_urls, visited_sitemaps, settings).await?;
         } else {
             all_urls.push(url);
         }
@@ -139,7 +150,7 @@ fn get_pattern(url: &Url, patterns: &Vec<String>) -> Option<String> {
     None
 }
 
-fn match_dynamic_url_pattern(url: &Url, pattern: &str) -> Option<String> {
+pub fn match_dynamic_url_pattern(url: &Url, pattern: &str) -> Option<String> {
     let pattern_parts: Vec<&str> = pattern.split("/").filter(|s| !s.is_empty()).collect();
     let url_parts: Vec<&str> = url.path_segments()?.collect();
 
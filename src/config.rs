@@ -1,16 +1,482 @@
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::Weekday;
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 use tokio::fs;
+use toml::{Table, Value};
+use tracing::warn;
+use url::Url;
 
-#[derive(Serialize, Deserialize)]
-pub struct Config {
+use crate::{analysis::budgets::Budget, site_import::validate_domain};
+
+const ENV_PREFIX: &str = "TARIN_";
+pub const DEFAULT_FILE: &str = "config.toml";
+const PSI_URL: &str = "https://www.googleapis.com/pagespeedonline/v5/runPagespeed";
+
+// Tables whose keys are reached as `TARIN_<TABLE>_<KEY>`
+const SECTIONS: [&str; 8] = [
+    "server",
+    "database",
+    "audit",
+    "queue",
+    "auth",
+    "smtp",
+    "digest",
+    "regressions",
+];
+
+// Variables read before the `TARIN_` prefix, kept working underneath it
+const LEGACY_ENV: [(&str, &str); 10] = [
+    ("SERVER_URL", "server.url"),
+    ("MIGRATE_ON_STARTUP", "server.migrate"),
+    ("DATABASE_URL", "database.url"),
+    ("AUDIT_PROVIDER", "audit.provider"),
+    ("PSI_URL", "audit.psi_url"),
+    ("PSI_KEY", "audit.psi_key"),
+    ("LIGHTHOUSE_PATH", "audit.lighthouse_path"),
+    ("LIGHTHOUSE_CHROME_FLAGS", "audit.lighthouse_chrome_flags"),
+    ("SMTP_URL", "smtp.url"),
+    ("DIGEST_FROM", "smtp.from"),
+];
+
+/// Every setting, layered from defaults, the config file and `TARIN_` env vars.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Sitemap paths audited once per matching url pattern.
     pub patterns: Vec<String>,
     pub ignore_paths: Vec<String>,
+    /// Sites for `GET /reports` and `tarin run` without `--site`.
     pub sites: Vec<String>,
+    pub server: ServerSettings,
+    pub database: DatabaseSettings,
+    pub audit: AuditSettings,
+    pub queue: QueueSettings,
+    pub auth: AuthSettings,
+    pub smtp: SmtpSettings,
+    pub regressions: RegressionThresholds,
+    pub budgets: Vec<BudgetConfig>,
+    pub digest: DigestSchedule,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSettings {
+    pub url: String,
+    /// Apply pending migrations at boot.
+    pub migrate: bool,
+    /// Seconds in-flight audits get to finish on shutdown.
+    pub shutdown_timeout: u64,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        ServerSettings {
+            url: "127.0.0.1:8080".to_string(),
+            migrate: false,
+            shutdown_timeout: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseSettings {
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditBackend {
+    #[default]
+    Psi,
+    Lighthouse,
+    Fake,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuditSettings {
+    pub provider: AuditBackend,
+    pub psi_url: String,
+    pub psi_key: Option<String>,
+    pub lighthouse_path: String,
+    pub lighthouse_chrome_flags: String,
+}
+
+impl Default for AuditSettings {
+    fn default() -> Self {
+        AuditSettings {
+            provider: AuditBackend::default(),
+            psi_url: PSI_URL.to_string(),
+            psi_key: None,
+            lighthouse_path: "lighthouse".to_string(),
+            lighthouse_chrome_flags: "--headless=new --no-sandbox".to_string(),
+        }
+    }
+}
+
+/// The job queue audits are worked from, see `queue.rs`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QueueSettings {
+    /// Jobs each instance works on at once.
+    pub workers: usize,
+    /// Attempts before a job is dead-lettered.
+    pub max_attempts: i32,
+    /// Seconds before the first retry, doubling with each attempt.
+    pub retry_delay: u64,
+    /// Seconds a claimed job stays hidden from other workers, after which it's
+    /// assumed lost and claimed again.
+    pub visibility_timeout: u64,
+}
+
+impl Default for QueueSettings {
+    fn default() -> Self {
+        QueueSettings {
+            workers: 10,
+            max_attempts: 3,
+            retry_delay: 30,
+            visibility_timeout: 600,
+        }
+    }
+}
+
+/// User logins, OIDC is enabled when `oidc_issuer` is set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthSettings {
+    pub session_hours: i64,
+    /// Mark cookies `Secure`, which they also are when `oidc_redirect_url` is
+    /// https.
+    pub secure_cookies: bool,
+    pub oidc_issuer: Option<String>,
+    pub oidc_client_id: Option<String>,
+    pub oidc_client_secret: Option<String>,
+    /// Where the provider sends users back, ending in `/auth/oidc/callback`.
+    pub oidc_redirect_url: Option<String>,
+}
+
+impl AuthSettings {
+    /// Whether cookies may only be sent over https.
+    pub fn secure(&self) -> bool {
+        self.secure_cookies
+            || self
+                .oidc_redirect_url
+                .as_deref()
+                .is_some_and(|url| url.starts_with("https://"))
+    }
+}
+
+impl Default for AuthSettings {
+    fn default() -> Self {
+        AuthSettings {
+            session_hours: 24 * 14,
+            secure_cookies: false,
+            oidc_issuer: None,
+            oidc_client_id: None,
+            oidc_client_secret: None,
+            oidc_redirect_url: None,
+        }
+    }
+}
+
+/// Digests are disabled unless `url` is set.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SmtpSettings {
+    pub url: Option<String>,
+    pub from: Option<String>,
+}
+
+impl Settings {
+    /// Reads `path`, or `config.toml` when present, then applies `env` over it.
+    pub async fn load(
+        path: Option<&Path>,
+        env: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self> {
+        let file = match path {
+            Some(path) => Some(
+                fs::read_to_string(path)
+                    .await
+                    .with_context(|| format!("Unable to read config file {}", path.display()))?,
+            ),
+            None => fs::read_to_string(DEFAULT_FILE).await.ok(),
+        };
+        let name = path.map_or(DEFAULT_FILE.to_string(), |path| path.display().to_string());
+
+        Settings::from_sources(file.as_deref().map(|file| (name.as_str(), file)), env)
+    }
+
+    /// Layers the named file contents and env vars over the defaults.
+    pub fn from_sources(
+        file: Option<(&str, &str)>,
+        env: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self> {
+        let defaults = Value::try_from(Settings::default())?;
+        let mut merged = defaults.clone();
+
+        if let Some((name, content)) = file {
+            let file: Value =
+                toml::from_str(content).with_context(|| format!("Invalid config file {name}"))?;
+            merge(&mut merged, file);
+        }
+
+        let env: Vec<(String, String)> = env.into_iter().collect();
+        let legacy = env.iter().filter_map(|(name, value)| {
+            let (_, path) = LEGACY_ENV.iter().find(|(legacy, _)| legacy == name)?;
+            Some((name, path.split('.').map(str::to_string).collect(), value))
+        });
+        let prefixed = env.iter().filter_map(|(name, value)| {
+            let key = name.strip_prefix(ENV_PREFIX)?.to_lowercase();
+            if key == "config" {
+                return None;
+            }
+
+            // Unlike unknown keys in the file, other tools' `TARIN_` variables
+            // such as `TARIN_LOG` must not stop every command from starting
+            let path = env_path(&key);
+            if !is_setting(&defaults, &path) {
+                warn!("Ignoring {name}, it doesn't match a setting");
+                return None;
+            }
+            Some((name, path, value))
+        });
+
+        for (name, path, value) in legacy.chain(prefixed).collect::<Vec<_>>() {
+            let value = env_value(lookup(&defaults, &path), value);
+            set(&mut merged, &path, value)
+                .with_context(|| format!("Unable to apply {name} as `{}`", path.join(".")))?;
+        }
+
+        let settings: Settings = serde_path_to_error::deserialize(merged)
+            .map_err(|e| anyhow!("Invalid setting `{}`: {}", e.path(), e.inner().message()))?;
+        settings.validate()?;
+
+        Ok(settings)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.digest.hour > 23 {
+            bail!("Invalid setting `digest.hour`: must be between 0 and 23");
+        }
+
+        for (name, value) in [
+            ("score_drop", self.regressions.score_drop),
+            ("metric_increase", self.regressions.metric_increase),
+            ("noise_multiplier", self.regressions.noise_multiplier),
+        ] {
+            if value < 0.0 {
+                bail!("Invalid setting `regressions.{name}`: must not be negative");
+            }
+        }
+
+        for (index, config) in self.budgets.iter().enumerate() {
+            if !config.budget.is_valid() {
+                bail!("Invalid setting `budgets[{index}]`: needs a min or max, with min <= max");
+            }
+        }
+
+        Url::parse(&self.audit.psi_url)
+            .map_err(|e| anyhow!("Invalid setting `audit.psi_url`: {e}"))?;
+
+        if let Some(url) = &self.database.url {
+            if !url.starts_with("postgres") && !url.starts_with("sqlite:") {
+                bail!("Invalid setting `database.url`: expected a postgres:// or sqlite: url");
+            }
+        }
+
+        if self.queue.workers < 1 {
+            bail!("Invalid setting `queue.workers`: must be at least 1");
+        }
+
+        if self.queue.max_attempts < 1 {
+            bail!("Invalid setting `queue.max_attempts`: must be at least 1");
+        }
+
+        if self.auth.session_hours < 1 {
+            bail!("Invalid setting `auth.session_hours`: must be at least 1");
+        }
+
+        if self.auth.oidc_issuer.is_some() {
+            for (name, value) in [
+                ("oidc_client_id", &self.auth.oidc_client_id),
+                ("oidc_redirect_url", &self.auth.oidc_redirect_url),
+            ] {
+                if value.is_none() {
+                    bail!("Missing setting `auth.{name}`: required when `auth.oidc_issuer` is set");
+                }
+            }
+        }
+
+        if self.smtp.url.is_some() && self.smtp.from.is_none() {
+            bail!("Missing setting `smtp.from`: required when `smtp.url` is set");
+        }
+
+        Ok(())
+    }
+
+    pub fn database_url(&self) -> Result<&str> {
+        self.database.url.as_deref().ok_or_else(|| {
+            anyhow!("Missing setting `database.url`, set TARIN_DATABASE_URL or DATABASE_URL")
+        })
+    }
+}
+
+// `TARIN_AUDIT_PSI_KEY` is `audit.psi_key`, unknown sections are top-level keys
+fn env_path(key: &str) -> Vec<String> {
+    SECTIONS
+        .iter()
+        .find_map(|section| {
+            let field = key.strip_prefix(section)?.strip_prefix('_')?;
+            Some(vec![section.to_string(), field.to_string()])
+        })
+        .unwrap_or_else(|| vec![key.to_string()])
+}
+
+// Settings missing from the defaults are unset options, which take a string
+fn is_setting(defaults: &Value, path: &[String]) -> bool {
+    if lookup(defaults, path).is_some() {
+        return true;
+    }
+
+    let mut probe = defaults.clone();
+    set(&mut probe, path, Value::String(String::new())).is_ok()
+        && probe.try_into::<Settings>().is_ok()
+}
+
+// Env values are strings, other types are parsed as TOML with lists also
+// accepted comma separated and booleans also as `1` or `0`
+fn env_value(default: Option<&Value>, value: &str) -> Value {
+    let parsed = || {
+        toml::from_str::<Table>(&format!("value = {value}"))
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+    };
+
+    match default {
+        None | Some(Value::String(_)) => Value::String(value.to_string()),
+        Some(Value::Boolean(_)) => match value.trim().to_lowercase().as_str() {
+            "1" | "true" => Value::Boolean(true),
+            "0" | "false" => Value::Boolean(false),
+            _ => Value::String(value.to_string()),
+        },
+        Some(Value::Array(_)) => parsed().unwrap_or_else(|| {
+            Value::Array(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(|item| Value::String(item.to_string()))
+                    .collect(),
+            )
+        }),
+        Some(_) => parsed().unwrap_or_else(|| Value::String(value.to_string())),
+    }
+}
+
+fn lookup<'a>(value: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |value, key| value.get(key))
+}
+
+fn set(value: &mut Value, path: &[String], new: Value) -> Result<()> {
+    let (key, parents) = path.split_last().context("empty path")?;
+    let mut table = value.as_table_mut().context("not a table")?;
+
+    for parent in parents {
+        table = table
+            .entry(parent.as_str())
+            .or_insert_with(|| Value::Table(Table::new()))
+            .as_table_mut()
+            .with_context(|| format!("`{parent}` is not a table"))?;
+    }
+
+    table.insert(key.clone(), new);
+    Ok(())
+}
+
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Table(base), Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
 }
 
-pub async fn load_config(file_path: &str) -> Option<Config> {
-    let content = fs::read_to_string(file_path).await.ok()?;
-    toml::from_str(&content).ok()
+/// A budget kept in version control, applied to every site unless `site` is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetConfig {
+    pub site: Option<String>,
+    #[serde(flatten)]
+    pub budget: Budget,
+}
+
+impl BudgetConfig {
+    /// Whether the budget covers the site, both sides are normalised so
+    /// `example.com` matches `https://example.com/`.
+    pub fn applies_to_site(&self, domain: &str) -> bool {
+        match &self.site {
+            Some(site) => {
+                let site = validate_domain(site).ok();
+                site.is_some() && site == validate_domain(domain).ok()
+            }
+            None => true,
+        }
+    }
+}
+
+/// A standalone budgets file, as used by `tarin check`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BudgetFile {
+    #[serde(default)]
+    pub budgets: Vec<BudgetConfig>,
+}
+
+/// How far a result may move from the previous run before it is flagged.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RegressionThresholds {
+    /// Absolute drop allowed in a 0-1 category score.
+    pub score_drop: f64,
+    /// Relative increase allowed in a timing or CLS, e.g. `0.1` is 10%.
+    pub metric_increase: f64,
+    /// Multiple of the sample spread added to the tolerance to absorb noise.
+    pub noise_multiplier: f64,
+}
+
+impl Default for RegressionThresholds {
+    fn default() -> Self {
+        RegressionThresholds {
+            score_drop: 0.05,
+            metric_increase: 0.1,
+            noise_multiplier: 1.0,
+        }
+    }
+}
+
+/// When the weekly email digest goes out, in UTC.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DigestSchedule {
+    pub weekday: Weekday,
+    pub hour: u32,
+}
+
+impl Default for DigestSchedule {
+    fn default() -> Self {
+        DigestSchedule {
+            weekday: Weekday::Mon,
+            hour: 8,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -30,11 +496,80 @@ mod tests {
         .expect("Failed to write to temp file");
         let file_path = temp_file.path().to_str().unwrap();
 
-        let config = load_config(file_path).await.unwrap();
+        let config = Settings::load(Some(Path::new(file_path)), no_env())
+            .await
+            .unwrap();
         assert_eq!(config.patterns.len(), 2);
         assert_eq!(config.ignore_paths.len(), 1);
         assert_eq!(config.patterns[0], "/first/:slug");
         assert_eq!(config.ignore_paths[0], "/ignore-this");
+        assert_eq!(config.regressions.score_drop, 0.05);
+    }
+
+    #[test]
+    async fn load_regression_thresholds() {
+        let mut temp_file = NamedTempFile::new().expect("Failed to create tempfile");
+        writeln!(temp_file, "[regressions]\nscore_drop = 0.1")
+            .expect("Failed to write to temp file");
+        let file_path = temp_file.path().to_str().unwrap();
+
+        let config = Settings::load(Some(Path::new(file_path)), no_env())
+            .await
+            .unwrap();
+        assert_eq!(config.regressions.score_drop, 0.1);
+        assert_eq!(config.regressions.metric_increase, 0.1);
+    }
+
+    #[test]
+    async fn load_budgets() {
+        let mut temp_file = NamedTempFile::new().expect("Failed to create tempfile");
+        writeln!(
+            temp_file,
+            "[[budgets]]\npattern = \"/blog/:slug\"\nmetric = \"lcp\"\nmax = 2500\n\n[[budgets]]\nsite = \"https://example.com\"\nmetric = \"performance\"\nmin = 0.8"
+        )
+        .expect("Failed to write to temp file");
+        let file_path = temp_file.path().to_str().unwrap();
+
+        let config = Settings::load(Some(Path::new(file_path)), no_env())
+            .await
+            .unwrap();
+        assert_eq!(config.budgets.len(), 2);
+        assert_eq!(config.budgets[0].site, None);
+        assert_eq!(
+            config.budgets[0].budget.pattern.as_deref(),
+            Some("/blog/:slug")
+        );
+        assert_eq!(config.budgets[0].budget.max, Some(2500.0));
+        assert_eq!(
+            config.budgets[1].site.as_deref(),
+            Some("https://example.com")
+        );
+    }
+
+    #[test]
+    async fn match_budget_file_sites() {
+        let file: BudgetFile = toml::from_str(
+            "[[budgets]]\nsite = \"example.com\"\nmetric = \"lcp\"\nmax = 2500\n\n[[budgets]]\nmetric = \"cls\"\nmax = 0.1",
+        )
+        .unwrap();
+
+        assert!(file.budgets[0].applies_to_site("https://example.com/"));
+        assert!(!file.budgets[0].applies_to_site("https://example.org"));
+        assert!(file.budgets[1].applies_to_site("https://example.org"));
+    }
+
+    #[test]
+    async fn load_digest_schedule() {
+        let mut temp_file = NamedTempFile::new().expect("Failed to create tempfile");
+        writeln!(temp_file, "[digest]\nweekday = \"Friday\"")
+            .expect("Failed to write to temp file");
+        let file_path = temp_file.path().to_str().unwrap();
+
+        let config = Settings::load(Some(Path::new(file_path)), no_env())
+            .await
+            .unwrap();
+        assert_eq!(config.digest.weekday, chrono::Weekday::Fri);
+        assert_eq!(config.digest.hour, 8);
     }
 
     #[test]
@@ -43,14 +578,159 @@ mod tests {
         writeln!(temp_file, "pattern [\"/first/:slug\"]").expect("Failed to write to tempfile");
         let file_path = temp_file.path().to_str().unwrap();
 
-        let config = load_config(file_path).await;
-        assert!(config.is_none());
+        let error = Settings::load(Some(Path::new(file_path)), no_env())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().starts_with("Invalid config file"));
     }
 
     #[test]
     async fn missing_config_file() {
-        let file_path = "missing.toml";
-        let config = load_config(file_path).await;
-        assert!(config.is_none());
+        let file_path = Path::new("missing.toml");
+        assert!(Settings::load(Some(file_path), no_env()).await.is_err());
+    }
+
+    #[test]
+    async fn env_overrides_file() {
+        let settings = Settings::from_sources(
+            Some((
+                "config.toml",
+                "sites = [\"https://example.com\"]\n\n[server]\nurl = \"0.0.0.0:80\"\n\n[digest]\nhour = 6",
+            )),
+            env(&[
+                ("TARIN_SERVER_URL", "127.0.0.1:3000"),
+                ("TARIN_DIGEST_HOUR", "9"),
+                ("TARIN_SITES", "https://a.example, https://b.example"),
+                ("TARIN_AUDIT_PSI_KEY", "123"),
+                ("TARIN_SERVER_MIGRATE", "true"),
+                ("HOME", "/root"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(settings.server.url, "127.0.0.1:3000");
+        assert!(settings.server.migrate);
+        assert_eq!(settings.digest.hour, 9);
+        assert_eq!(
+            settings.sites,
+            vec!["https://a.example", "https://b.example"]
+        );
+        assert_eq!(settings.audit.psi_key.as_deref(), Some("123"));
+        assert_eq!(settings.audit.psi_url, PSI_URL);
+    }
+
+    #[test]
+    async fn skips_unknown_env_vars() {
+        let settings = Settings::from_sources(
+            Some(("config.toml", "[server]\nmigrate = false")),
+            env(&[
+                ("TARIN_LOG", "debug"),
+                ("TARIN_SERVER_PORT", "80"),
+                ("TARIN_SERVER_MIGRATE", "1"),
+                ("TARIN_SMTP_URL", "smtp://localhost"),
+                ("TARIN_SMTP_FROM", "tarin@example.com"),
+            ]),
+        )
+        .unwrap();
+
+        assert!(settings.server.migrate);
+        assert_eq!(settings.smtp.url.as_deref(), Some("smtp://localhost"));
+
+        let settings = Settings::from_sources(None, env(&[("MIGRATE_ON_STARTUP", "0")])).unwrap();
+        assert!(!settings.server.migrate);
+
+        let error = Settings::from_sources(None, env(&[("TARIN_SERVER_MIGRATE", "yes")]))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.starts_with("Invalid setting `server.migrate`"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    async fn prefixed_env_overrides_legacy() {
+        let settings = Settings::from_sources(
+            None,
+            env(&[
+                ("DATABASE_URL", "postgres://legacy"),
+                ("TARIN_DATABASE_URL", "postgres://prefixed"),
+                ("PSI_KEY", "legacy-key"),
+                ("AUDIT_PROVIDER", "fake"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(settings.database_url().unwrap(), "postgres://prefixed");
+        assert_eq!(settings.audit.psi_key.as_deref(), Some("legacy-key"));
+        assert_eq!(settings.audit.provider, AuditBackend::Fake);
+    }
+
+    #[test]
+    async fn points_at_bad_key() {
+        let error = Settings::from_sources(None, env(&[("TARIN_DIGEST_HOUR", "soon")]))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.starts_with("Invalid setting `digest.hour`"),
+            "{error}"
+        );
+
+        let error = Settings::from_sources(Some(("config.toml", "[server]\nport = 80")), no_env())
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.starts_with("Invalid setting `server.port`: unknown field"),
+            "{error}"
+        );
+
+        let error = Settings::from_sources(None, env(&[("TARIN_DIGEST_HOUR", "24")]))
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "Invalid setting `digest.hour`: must be between 0 and 23"
+        );
+
+        let error = Settings::from_sources(None, env(&[("SMTP_URL", "smtp://localhost")]))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("`smtp.from`"), "{error}");
+    }
+
+    #[test]
+    async fn requires_database_url() {
+        let settings = Settings::from_sources(None, no_env()).unwrap();
+        assert!(settings
+            .database_url()
+            .unwrap_err()
+            .to_string()
+            .contains("TARIN_DATABASE_URL"));
+    }
+
+    #[test]
+    async fn secure_cookies_behind_https() {
+        let mut auth = AuthSettings::default();
+        assert!(!auth.secure());
+
+        auth.oidc_redirect_url = Some("http://localhost/auth/oidc/callback".to_string());
+        assert!(!auth.secure());
+
+        auth.oidc_redirect_url = Some("https://tarin.example.com/auth/oidc/callback".to_string());
+        assert!(auth.secure());
+
+        auth.oidc_redirect_url = None;
+        auth.secure_cookies = true;
+        assert!(auth.secure());
+    }
+
+    fn no_env() -> Vec<(String, String)> {
+        Vec::new()
+    }
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
     }
 }
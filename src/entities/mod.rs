@@ -2,5 +2,24 @@
 
 pub mod prelude;
 
+pub mod api_keys;
+pub mod audit_jobs;
+pub mod budget_results;
+pub mod budgets;
+pub mod digest_recipients;
+pub mod digests;
+pub mod group_sites;
+pub mod groups;
+pub mod regressions;
+pub mod reports;
+pub mod runs;
+pub mod sea_orm_active_enums;
+pub mod sessions;
+pub mod site_audit_log;
 pub mod site_urls;
 pub mod sites;
+pub mod team_members;
+pub mod teams;
+pub mod users;
+pub mod webhook_deliveries;
+pub mod webhooks;
@@ -1,4 +1,22 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.7
 
+pub use super::api_keys::Entity as ApiKeys;
+pub use super::audit_jobs::Entity as AuditJobs;
+pub use super::budget_results::Entity as BudgetResults;
+pub use super::budgets::Entity as Budgets;
+pub use super::digest_recipients::Entity as DigestRecipients;
+pub use super::digests::Entity as Digests;
+pub use super::group_sites::Entity as GroupSites;
+pub use super::groups::Entity as Groups;
+pub use super::regressions::Entity as Regressions;
+pub use super::reports::Entity as Reports;
+pub use super::runs::Entity as Runs;
+pub use super::sessions::Entity as Sessions;
+pub use super::site_audit_log::Entity as SiteAuditLog;
 pub use super::site_urls::Entity as SiteUrls;
 pub use super::sites::Entity as Sites;
+pub use super::team_members::Entity as TeamMembers;
+pub use super::teams::Entity as Teams;
+pub use super::users::Entity as Users;
+pub use super::webhook_deliveries::Entity as WebhookDeliveries;
+pub use super::webhooks::Entity as Webhooks;
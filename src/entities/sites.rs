@@ -2,20 +2,56 @@
 
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, ToSchema)]
 #[sea_orm(table_name = "sites")]
+#[schema(as = Site)]
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
     pub domain: String,
     pub created_at: DateTime,
+    pub deleted_at: Option<DateTime>,
+    pub team_id: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::audit_jobs::Entity")]
+    AuditJobs,
+    #[sea_orm(has_many = "super::group_sites::Entity")]
+    GroupSites,
+    #[sea_orm(has_many = "super::reports::Entity")]
+    Reports,
     #[sea_orm(has_many = "super::site_urls::Entity")]
     SiteUrls,
+    #[sea_orm(
+        belongs_to = "super::teams::Entity",
+        from = "Column::TeamId",
+        to = "super::teams::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    Teams,
+}
+
+impl Related<super::audit_jobs::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AuditJobs.def()
+    }
+}
+
+impl Related<super::group_sites::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::GroupSites.def()
+    }
+}
+
+impl Related<super::reports::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Reports.def()
+    }
 }
 
 impl Related<super::site_urls::Entity> for Entity {
@@ -24,4 +60,19 @@ impl Related<super::site_urls::Entity> for Entity {
     }
 }
 
+impl Related<super::teams::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Teams.def()
+    }
+}
+
+impl Related<super::groups::Entity> for Entity {
+    fn to() -> RelationDef {
+        super::group_sites::Relation::Groups.def()
+    }
+    fn via() -> Option<RelationDef> {
+        Some(super::group_sites::Relation::Sites.def().rev())
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
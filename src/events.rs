@@ -0,0 +1,141 @@
+use axum::response::sse::Event;
+use sea_orm::{ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QueryOrder};
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tracing::error;
+
+use crate::entities::{
+    budget_results::{self, Entity as BudgetResults},
+    regressions::{self, Entity as Regressions},
+    reports::{self, Entity as Reports},
+    runs::{self, Entity as Runs},
+    sea_orm_active_enums::RunStatus,
+};
+
+/// Progress of a stored run, fanned out to the webhook dispatcher of the
+/// process that produced it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum RunEvent {
+    Report(reports::Model),
+    Budget(budget_results::Model),
+    Regression(regressions::Model),
+    Completed(runs::Model),
+}
+
+pub type RunEvents = broadcast::Sender<RunEvent>;
+
+impl RunEvent {
+    pub fn run_id(&self) -> i32 {
+        match self {
+            RunEvent::Report(report) => report.run_id,
+            RunEvent::Budget(result) => result.run_id,
+            RunEvent::Regression(regression) => regression.run_id,
+            RunEvent::Completed(run) => run.id,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RunEvent::Report(_) => "report",
+            RunEvent::Budget(_) => "budget",
+            RunEvent::Regression(_) => "regression",
+            RunEvent::Completed(_) => "completed",
+        }
+    }
+
+    pub fn to_sse(&self) -> Event {
+        match Event::default().event(self.name()).json_data(self) {
+            Ok(event) => event,
+            Err(e) => {
+                error!(
+                    "Error creating {} event for run {}: {e}",
+                    self.name(),
+                    self.run_id()
+                );
+                Event::default().event("error")
+            }
+        }
+    }
+}
+
+// Sending only fails when nobody is listening, which is fine
+pub fn publish(events: &RunEvents, event: RunEvent) {
+    let _ = events.send(event);
+}
+
+/// How far `poll_run` has read a run's stored events.
+#[derive(Debug, Default)]
+pub struct RunCursor {
+    report: i32,
+    budget: i32,
+    regression: i32,
+    finished: bool,
+}
+
+impl RunCursor {
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+/// Events stored for the run since the cursor, ending with `Completed` once it
+/// has finished. Workers may run in other processes, so listeners read
+/// progress from the database instead of the in-process channel.
+pub async fn poll_run<C: ConnectionTrait>(
+    db: &C,
+    run_id: i32,
+    cursor: &mut RunCursor,
+) -> Result<Vec<RunEvent>, DbErr> {
+    if cursor.finished {
+        return Ok(Vec::new());
+    }
+
+    // Read before the rows, everything a finished run stores is written first
+    let run = Runs::find_by_id(run_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound(format!("run {run_id}")))?;
+
+    let mut events = Vec::new();
+
+    let reports = Reports::find()
+        .filter(reports::Column::RunId.eq(run_id))
+        .filter(reports::Column::Id.gt(cursor.report))
+        .order_by_asc(reports::Column::Id)
+        .all(db)
+        .await?;
+    if let Some(last) = reports.last() {
+        cursor.report = last.id;
+    }
+    events.extend(reports.into_iter().map(RunEvent::Report));
+
+    let budgets = BudgetResults::find()
+        .filter(budget_results::Column::RunId.eq(run_id))
+        .filter(budget_results::Column::Id.gt(cursor.budget))
+        .order_by_asc(budget_results::Column::Id)
+        .all(db)
+        .await?;
+    if let Some(last) = budgets.last() {
+        cursor.budget = last.id;
+    }
+    events.extend(budgets.into_iter().map(RunEvent::Budget));
+
+    let regressions = Regressions::find()
+        .filter(regressions::Column::RunId.eq(run_id))
+        .filter(regressions::Column::Id.gt(cursor.regression))
+        .order_by_asc(regressions::Column::Id)
+        .all(db)
+        .await?;
+    if let Some(last) = regressions.last() {
+        cursor.regression = last.id;
+    }
+    events.extend(regressions.into_iter().map(RunEvent::Regression));
+
+    if matches!(run.status, RunStatus::Completed | RunStatus::Failed) {
+        cursor.finished = true;
+        events.push(RunEvent::Completed(run));
+    }
+
+    Ok(events)
+}
@@ -0,0 +1,399 @@
+use chrono::{Days, NaiveDateTime};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, DerivePartialModel, EntityTrait, JoinType, QueryFilter,
+    QueryOrder, QuerySelect, RelationTrait,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+};
+
+use crate::{
+    analysis::trends::{build_trend, median_summary, Bucket, ReportMetrics},
+    client::psi::{top_opportunities, CategoryScores, CoreWebVitals, Opportunity},
+    entities::{
+        reports::{self, Entity as Reports},
+        runs::{self, Entity as Runs},
+        sea_orm_active_enums::Strategy,
+        sites::{self, Entity as Sites},
+    },
+    utils::escape,
+};
+
+const TOP_OPPORTUNITIES: usize = 5;
+const TREND_DAYS: u64 = 90;
+
+#[derive(Debug, Clone)]
+pub struct PageReport {
+    pub site: String,
+    pub url: String,
+    pub strategy: String,
+    pub samples: usize,
+    pub scores: CategoryScores,
+    pub vitals: CoreWebVitals,
+    pub opportunities: Vec<Opportunity>,
+    /// Daily median performance, oldest first.
+    pub trend: Vec<f64>,
+}
+
+/// A report in a url's trend history, selected without its PSI `data`.
+#[derive(Debug, Clone, PartialEq, DerivePartialModel)]
+#[sea_orm(entity = "Reports", from_query_result)]
+pub struct HistoryReport {
+    pub site_url_id: i32,
+    pub strategy: Strategy,
+    #[sea_orm(nested)]
+    pub metrics: ReportMetrics,
+}
+
+impl From<&reports::Model> for HistoryReport {
+    fn from(report: &reports::Model) -> Self {
+        HistoryReport {
+            site_url_id: report.site_url_id,
+            strategy: report.strategy,
+            metrics: report.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HtmlReport {
+    pub title: String,
+    pub generated_at: NaiveDateTime,
+    pub pages: Vec<PageReport>,
+}
+
+/// One page per url and strategy, scores are the median of the run's samples
+/// and opportunities come from the most recent sample.
+pub fn build_pages(
+    domains: &HashMap<i32, String>,
+    run_reports: &[reports::Model],
+    history: &[HistoryReport],
+) -> Vec<PageReport> {
+    let mut samples: BTreeMap<(String, String, &str), Vec<&reports::Model>> = BTreeMap::new();
+    for report in run_reports {
+        let domain = domains.get(&report.site_id).cloned().unwrap_or_default();
+        samples
+            .entry((domain, report.url.clone(), report.strategy.as_str()))
+            .or_default()
+            .push(report);
+    }
+
+    samples
+        .into_iter()
+        .map(|((site, url, strategy), samples)| {
+            let metrics: Vec<ReportMetrics> = samples.iter().copied().map(Into::into).collect();
+            let (scores, vitals) = median_summary(&metrics);
+            let latest = samples.iter().max_by_key(|report| report.id);
+            let first = samples[0];
+
+            let url_history: Vec<ReportMetrics> = history
+                .iter()
+                .filter(|report| {
+                    report.site_url_id == first.site_url_id && report.strategy == first.strategy
+                })
+                .map(|report| report.metrics.clone())
+                .collect();
+
+            PageReport {
+                site,
+                url,
+                strategy: strategy.to_string(),
+                samples: samples.len(),
+                scores,
+                vitals,
+                opportunities: latest
+                    .map(|report| top_opportunities(&report.data, TOP_OPPORTUNITIES))
+                    .unwrap_or_default(),
+                trend: build_trend(&url_history, Bucket::Day)
+                    .into_iter()
+                    .filter_map(|point| point.scores.performance)
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// Renders a report for the run, limited to one site when `site_id` is given.
+pub async fn load_run_report(
+    db: &DatabaseConnection,
+    run: &runs::Model,
+    site_id: Option<i32>,
+) -> Result<HtmlReport, DbErr> {
+    let mut query = Reports::find().filter(reports::Column::RunId.eq(run.id));
+    if let Some(site_id) = site_id {
+        query = query.filter(reports::Column::SiteId.eq(site_id));
+    }
+    let run_reports = query.order_by_asc(reports::Column::Id).all(db).await?;
+
+    let domains: HashMap<i32, String> = Sites::find()
+        .join(JoinType::InnerJoin, sites::Relation::Reports.def())
+        .filter(reports::Column::RunId.eq(run.id))
+        .distinct()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|site| (site.id, site.domain))
+        .collect();
+
+    let site_url_ids: Vec<i32> = run_reports
+        .iter()
+        .map(|report| report.site_url_id)
+        .collect();
+    let finished_at = run.finished_at.unwrap_or(run.created_at);
+    let history = Reports::find()
+        .filter(reports::Column::SiteUrlId.is_in(site_url_ids))
+        .filter(reports::Column::Strategy.eq(run.strategy))
+        .filter(reports::Column::CreatedAt.gte(finished_at - Days::new(TREND_DAYS)))
+        .filter(reports::Column::CreatedAt.lte(finished_at))
+        .order_by_asc(reports::Column::CreatedAt)
+        .into_partial_model::<HistoryReport>()
+        .all(db)
+        .await?;
+
+    let title = match site_id.and_then(|site_id| domains.get(&site_id)) {
+        Some(domain) => format!("{domain}, run #{} ({})", run.id, run.strategy.as_str()),
+        None => format!("Run #{} ({})", run.id, run.strategy.as_str()),
+    };
+
+    Ok(HtmlReport {
+        title,
+        generated_at: chrono::Utc::now().naive_utc(),
+        pages: build_pages(&domains, &run_reports, &history),
+    })
+}
+
+/// The most recent run that reported on the site.
+pub async fn latest_site_run(
+    db: &DatabaseConnection,
+    site_id: i32,
+) -> Result<Option<runs::Model>, DbErr> {
+    let Some(report) = Reports::find()
+        .filter(reports::Column::SiteId.eq(site_id))
+        .order_by_desc(reports::Column::RunId)
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    Runs::find_by_id(report.run_id).one(db).await
+}
+
+/// Score out of 100 coloured with the Lighthouse pass, average and fail ranges.
+fn badge(score: Option<f64>) -> String {
+    match score {
+        Some(score) => {
+            let class = match score {
+                s if s >= 0.9 => "pass",
+                s if s >= 0.5 => "average",
+                _ => "fail",
+            };
+            format!("<span class=\"badge {class}\">{:.0}</span>", score * 100.0)
+        }
+        None => "<span class=\"badge\">-</span>".to_string(),
+    }
+}
+
+/// Inline SVG polyline of 0-1 scores, empty for fewer than two points.
+fn sparkline(values: &[f64]) -> String {
+    const WIDTH: f64 = 120.0;
+    const HEIGHT: f64 = 24.0;
+
+    if values.len() < 2 {
+        return String::new();
+    }
+
+    let step = WIDTH / (values.len() - 1) as f64;
+    let points: Vec<String> = values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            format!(
+                "{:.1},{:.1}",
+                index as f64 * step,
+                HEIGHT - value.clamp(0.0, 1.0) * HEIGHT
+            )
+        })
+        .collect();
+
+    format!(
+        "<svg class=\"sparkline\" width=\"{WIDTH}\" height=\"{HEIGHT}\" viewBox=\"0 0 {WIDTH} {HEIGHT}\"><polyline fill=\"none\" stroke=\"#1a73e8\" stroke-width=\"1.5\" points=\"{}\"/></svg>",
+        points.join(" ")
+    )
+}
+
+fn metric(value: Option<f64>, unit: &str) -> String {
+    match value {
+        Some(value) if unit.is_empty() => format!("{value:.3}"),
+        Some(value) => format!("{value:.0} {unit}"),
+        None => "-".to_string(),
+    }
+}
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2rem;color:#202124}\
+table{border-collapse:collapse;margin-bottom:1.5rem}\
+th,td{padding:.35rem .6rem;border-bottom:1px solid #dadce0;text-align:left}\
+.badge{display:inline-block;min-width:2.2rem;padding:.15rem .3rem;border-radius:1rem;text-align:center;font-weight:600;background:#e8eaed}\
+.pass{background:#e6f4ea;color:#0d652d}.average{background:#fef7e0;color:#b06000}.fail{background:#fce8e6;color:#a50e0e}\
+section{margin-bottom:2rem}h2{font-size:1.1rem;word-break:break-all}";
+
+/// A standalone HTML document with no external assets.
+pub fn render(report: &HtmlReport) -> String {
+    let mut html = format!(
+        "<!DOCTYPE html><html lang=\"en\"><head><meta charset=\"utf-8\"><title>Tarin report: {title}</title><style>{STYLE}</style></head><body>\
+         <h1>Tarin report: {title}</h1><p>Generated {generated} UTC, {count} page(s)</p>",
+        title = escape(&report.title),
+        generated = report.generated_at.format("%Y-%m-%d %H:%M"),
+        count = report.pages.len(),
+    );
+
+    let mut sites: BTreeMap<&str, Vec<&CategoryScores>> = BTreeMap::new();
+    for page in &report.pages {
+        sites.entry(&page.site).or_default().push(&page.scores);
+    }
+
+    html.push_str("<h2>Summary</h2><table><tr><th>Site</th><th>Pages</th><th>Performance</th><th>Accessibility</th><th>Best practices</th><th>SEO</th></tr>");
+    for (site, scores) in &sites {
+        let average = CategoryScores::average(scores.iter().copied());
+        let _ = write!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape(site),
+            scores.len(),
+            badge(average.performance),
+            badge(average.accessibility),
+            badge(average.best_practices),
+            badge(average.seo)
+        );
+    }
+    html.push_str("</table>");
+
+    for page in &report.pages {
+        let _ = write!(
+            html,
+            "<section><h2><a href=\"{url}\">{url}</a></h2><p>{strategy}, {samples} sample(s) {trend}</p>\
+             <table><tr><th>Performance</th><th>Accessibility</th><th>Best practices</th><th>SEO</th></tr>\
+             <tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr></table>\
+             <table><tr><th>LCP</th><th>CLS</th><th>INP</th><th>TBT</th><th>FCP</th><th>TTFB</th></tr>\
+             <tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr></table>",
+            badge(page.scores.performance),
+            badge(page.scores.accessibility),
+            badge(page.scores.best_practices),
+            badge(page.scores.seo),
+            metric(page.vitals.lcp, "ms"),
+            metric(page.vitals.cls, ""),
+            metric(page.vitals.inp, "ms"),
+            metric(page.vitals.tbt, "ms"),
+            metric(page.vitals.fcp, "ms"),
+            metric(page.vitals.ttfb, "ms"),
+            url = escape(&page.url),
+            strategy = page.strategy,
+            samples = page.samples,
+            trend = sparkline(&page.trend),
+        );
+
+        if !page.opportunities.is_empty() {
+            html.push_str("<ul>");
+            for opportunity in &page.opportunities {
+                let _ = write!(
+                    html,
+                    "<li>{} <small>(saves ~{:.0} ms)</small></li>",
+                    escape(&opportunity.title),
+                    opportunity.savings_ms
+                );
+            }
+            html.push_str("</ul>");
+        }
+
+        html.push_str("</section>");
+    }
+
+    html.push_str("</body></html>");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    mod build_pages {
+        use super::super::*;
+        use crate::analysis::test_support::{noon, report as base};
+        use serde_json::json;
+
+        fn report(id: i32, site_url_id: i32, performance: f64, day: u32) -> reports::Model {
+            reports::Model {
+                id,
+                site_url_id,
+                url: format!("https://example.com/{site_url_id}"),
+                performance: Some(performance),
+                data: json!({
+                    "lighthouseResult": {
+                        "audits": {
+                            "uses-webp-images": {
+                                "title": format!("Sample {id}"),
+                                "details": { "type": "opportunity", "overallSavingsMs": 500 }
+                            }
+                        }
+                    }
+                }),
+                created_at: noon(&format!("2025-05-{day:02}")),
+                ..base()
+            }
+        }
+
+        #[test]
+        fn summarises_samples_per_url() {
+            let domains = HashMap::from([(1, "https://example.com/".to_string())]);
+            let run_reports = vec![
+                report(3, 1, 0.5, 20),
+                report(4, 1, 0.7, 20),
+                report(5, 1, 0.9, 20),
+                report(6, 2, 0.4, 20),
+            ];
+            let history: Vec<HistoryReport> = [report(1, 1, 0.8, 18), report(2, 1, 0.6, 19)]
+                .iter()
+                .chain(&run_reports)
+                .map(Into::into)
+                .collect();
+
+            let pages = build_pages(&domains, &run_reports, &history);
+            assert_eq!(pages.len(), 2);
+            assert_eq!(pages[0].samples, 3);
+            assert_eq!(pages[0].scores.performance, Some(0.7));
+            assert_eq!(pages[0].opportunities[0].title, "Sample 5");
+            assert_eq!(pages[0].trend, vec![0.8, 0.6, 0.7]);
+            assert_eq!(pages[1].trend, vec![0.4]);
+        }
+    }
+
+    mod render {
+        use super::super::*;
+
+        #[test]
+        fn escapes_urls_and_draws_sparkline() {
+            let report = HtmlReport {
+                title: "Run #1 (mobile)".to_string(),
+                generated_at: Default::default(),
+                pages: vec![PageReport {
+                    site: "https://example.com/".to_string(),
+                    url: "https://example.com/?q=<script>".to_string(),
+                    strategy: "mobile".to_string(),
+                    samples: 1,
+                    scores: CategoryScores {
+                        performance: Some(0.95),
+                        ..Default::default()
+                    },
+                    vitals: CoreWebVitals::default(),
+                    opportunities: Vec::new(),
+                    trend: vec![0.5, 0.95],
+                }],
+            };
+
+            let html = render(&report);
+            assert!(html.contains("https://example.com/?q=&lt;script&gt;"));
+            assert!(!html.contains("<script>"));
+            assert!(html.contains("<span class=\"badge pass\">95</span>"));
+            assert!(html.contains("<polyline"));
+        }
+    }
+}
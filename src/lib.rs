@@ -0,0 +1,239 @@
+use std::sync::Arc;
+
+use axum::{middleware, routing::get, Router};
+use sea_orm::DatabaseConnection;
+use tower_http::trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer};
+use tracing::Level;
+use utoipa::OpenApi;
+use utoipa_axum::{router::OpenApiRouter, routes};
+use utoipa_swagger_ui::SwaggerUi;
+
+pub mod accounts;
+pub mod analysis;
+pub mod audit;
+pub mod auth;
+pub mod check;
+pub mod cli;
+pub mod client;
+pub mod config;
+pub mod digest;
+pub mod entities;
+pub mod events;
+pub mod html_report;
+pub mod metrics;
+pub mod oidc;
+pub mod openapi;
+pub mod queue;
+pub mod reload;
+pub mod routes;
+pub mod run_export;
+pub mod schema;
+pub mod shutdown;
+pub mod site_import;
+pub mod utils;
+pub mod webhooks;
+pub mod worker;
+
+use auth::Scope;
+use digest::Mailer;
+use events::RunEvents;
+use reload::LiveSettings;
+use routes::{
+    auth as auth_routes, budgets, config as config_routes, digests, groups, health, reports, runs,
+    sites, status, trends,
+};
+use shutdown::Shutdown;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub db: Arc<DatabaseConnection>,
+    pub mailer: Option<Mailer>,
+    pub settings: LiveSettings,
+    pub shutdown: Shutdown,
+    /// Events of the worker running in this process, if any.
+    pub events: Option<RunEvents>,
+}
+
+/// The API router with every route and the request tracing layer. Routes other
+/// than `/status`, the health checks, `/metrics`, `/openapi.json`, `/docs` and
+/// `/auth` need an API key or a session allowed the scope of their router.
+pub fn app(app_state: Arc<AppState>) -> Router {
+    let (router, openapi) = api(&app_state).split_for_parts();
+
+    router
+        .merge(SwaggerUi::new("/docs").url("/openapi.json", openapi))
+        .route_layer(middleware::from_fn(metrics::track_requests))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
+                .on_response(DefaultOnResponse::new().level(Level::INFO)),
+        )
+        .with_state(app_state)
+}
+
+/// The routes of `app` along with the OpenAPI document of their handlers, so
+/// neither can miss a route of the other.
+pub fn api(app_state: &Arc<AppState>) -> OpenApiRouter<Arc<AppState>> {
+    let read = OpenApiRouter::new()
+        .routes(routes!(config_routes::get_config))
+        .routes(routes!(sites::get_sites))
+        .routes(routes!(sites::export_sites))
+        .routes(routes!(sites::get_site))
+        .routes(routes!(sites::get_site_audit_log))
+        .routes(routes!(sites::get_site_report))
+        .routes(routes!(trends::get_site_trends))
+        .routes(routes!(trends::get_url_trends))
+        .routes(routes!(budgets::get_site_budgets))
+        .routes(routes!(groups::get_groups))
+        .routes(routes!(groups::get_group))
+        .routes(routes!(groups::get_group_scores))
+        .routes(routes!(groups::get_group_reports))
+        .routes(routes!(digests::get_group_digest))
+        .routes(routes!(digests::get_digest_recipients))
+        .routes(routes!(runs::get_run))
+        .routes(routes!(runs::sse_run_events_handler))
+        .routes(routes!(runs::get_run_regressions))
+        .routes(routes!(runs::get_run_budgets))
+        .routes(routes!(runs::export_run))
+        .routes(routes!(runs::get_run_report))
+        .routes(routes!(routes::webhooks::get_webhooks))
+        .routes(routes!(routes::webhooks::get_webhook))
+        .routes(routes!(routes::webhooks::get_webhook_deliveries));
+
+    let write = OpenApiRouter::new()
+        .routes(routes!(config_routes::reload_config))
+        .routes(routes!(sites::create_site_handler))
+        .routes(routes!(sites::import_sites))
+        .routes(routes!(sites::update_site, sites::delete_site))
+        .routes(routes!(sites::restore_site))
+        .routes(routes!(sites::purge_site))
+        .routes(routes!(budgets::create_budget_handler))
+        .routes(routes!(budgets::update_budget, budgets::delete_budget))
+        .routes(routes!(groups::create_group_handler))
+        .routes(routes!(groups::update_group, groups::delete_group))
+        .routes(routes!(groups::add_group_site, groups::remove_group_site))
+        .routes(routes!(digests::send_group_digest))
+        .routes(routes!(digests::set_digest_recipients))
+        .routes(routes!(routes::webhooks::create_webhook_handler))
+        .routes(routes!(
+            routes::webhooks::update_webhook,
+            routes::webhooks::delete_webhook
+        ));
+
+    let run = OpenApiRouter::new()
+        .routes(routes!(reports::create_configured_run))
+        .routes(routes!(groups::create_group_run));
+
+    OpenApiRouter::with_openapi(openapi::ApiDoc::openapi())
+        .routes(routes!(status::get_status))
+        .routes(routes!(health::get_health))
+        .routes(routes!(health::get_readiness))
+        .routes(routes!(health::get_metrics))
+        .routes(routes!(auth_routes::login))
+        .routes(routes!(auth_routes::logout))
+        .routes(routes!(auth_routes::get_current_user))
+        .routes(routes!(auth_routes::oidc_login))
+        .routes(routes!(auth_routes::oidc_callback))
+        .merge(scoped(read, app_state, Scope::Read))
+        .merge(scoped(write, app_state, Scope::Write))
+        .merge(scoped(run, app_state, Scope::Run))
+}
+
+// Guards the routes with `scope` and documents that they need it
+fn scoped(
+    router: OpenApiRouter<Arc<AppState>>,
+    app_state: &Arc<AppState>,
+    scope: Scope,
+) -> OpenApiRouter<Arc<AppState>> {
+    let mut router = router.route_layer(middleware::from_fn_with_state(
+        (app_state.clone(), scope),
+        auth::require_scope,
+    ));
+    openapi::require_scope(router.get_openapi_mut(), scope);
+
+    router
+}
+
+/// Health checks and metrics alone, for `tarin worker --listen`.
+pub fn probes(app_state: Arc<AppState>) -> Router {
+    Router::new()
+        .route("/healthz", get(health::get_health))
+        .route("/readyz", get(health::get_readiness))
+        .route("/metrics", get(health::get_metrics))
+        .with_state(app_state)
+}
+
+#[cfg(test)]
+mod tests {
+    mod api {
+        use super::super::*;
+        use axum::{
+            body::Body,
+            http::{Method, Request, StatusCode},
+        };
+        use config::Settings;
+        use sea_orm::Database;
+        use tower::ServiceExt;
+
+        // Every documented operation reaches its handler, and the ones
+        // documented with a scope are turned away without credentials
+        #[tokio::test]
+        async fn routes_every_documented_operation() {
+            let db = Database::connect("sqlite::memory:").await.unwrap();
+            let app_state = Arc::new(AppState {
+                db: Arc::new(db),
+                mailer: None,
+                settings: LiveSettings::new(Settings::default(), None, Vec::new()),
+                shutdown: Shutdown::new(),
+                events: None,
+            });
+            let document = api(&app_state).into_openapi();
+            let router = app(app_state)
+                .fallback(|| async { StatusCode::IM_A_TEAPOT })
+                .method_not_allowed_fallback(|| async { StatusCode::IM_A_TEAPOT });
+
+            let mut operations = 0;
+            for (path, item) in &document.paths.paths {
+                let uri: Vec<&str> = path
+                    .split('/')
+                    .map(|segment| {
+                        if segment.starts_with('{') {
+                            "1"
+                        } else {
+                            segment
+                        }
+                    })
+                    .collect();
+
+                for (method, operation) in [
+                    (Method::GET, &item.get),
+                    (Method::PUT, &item.put),
+                    (Method::POST, &item.post),
+                    (Method::DELETE, &item.delete),
+                ] {
+                    let Some(operation) = operation else {
+                        continue;
+                    };
+                    let request = Request::builder()
+                        .method(&method)
+                        .uri(uri.join("/"))
+                        .body(Body::empty())
+                        .unwrap();
+                    let status = router.clone().oneshot(request).await.unwrap().status();
+
+                    assert_ne!(
+                        status,
+                        StatusCode::IM_A_TEAPOT,
+                        "{method} {path} isn't routed"
+                    );
+                    if operation.security.is_some() {
+                        assert_eq!(status, StatusCode::UNAUTHORIZED, "{method} {path}");
+                    }
+                    operations += 1;
+                }
+            }
+
+            assert_eq!(operations, 55);
+        }
+    }
+}
@@ -1,60 +1,200 @@
-use std::sync::Arc;
+use std::{process::ExitCode, sync::Arc, time::Duration};
 
 use anyhow::{Context, Result};
-use axum::{
-    routing::{delete, get, post, put},
-    Router,
-};
+use clap::Parser;
 use dotenv::dotenv;
-use sea_orm::{Database, DatabaseConnection};
-use tower_http::trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer};
-use tracing::Level;
+use sea_orm::DatabaseConnection;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
 use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt};
 
-mod client;
-pub mod config;
-mod entities;
-mod routes;
-mod utils;
+use tarin::{
+    app,
+    cli::{self, Cli, Command, ServeArgs, WorkerArgs},
+    client::{processor, provider::provider_from_settings},
+    config::Settings,
+    digest::{self, Mailer},
+    probes,
+    reload::LiveSettings,
+    schema,
+    shutdown::{self, Shutdown},
+    webhooks,
+    worker::Worker,
+    AppState,
+};
+
+#[tokio::main]
+async fn main() -> Result<ExitCode> {
+    dotenv().ok();
 
-use entities::{prelude::*, *};
-use routes::{reports, sites};
+    let cli = Cli::parse();
 
-#[derive(Clone)]
-pub struct AppState {
-    pub db: Arc<DatabaseConnection>,
+    // Logs go to stderr so subcommands can write their output to stdout
+    tracing_subscriber::registry()
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .init();
+
+    let env: Vec<(String, String)> = std::env::vars().collect();
+    let settings = Settings::load(cli.config.as_deref(), env.clone()).await?;
+
+    // Without a subcommand the API and the queue workers share the process
+    let default = Command::Serve(ServeArgs {
+        worker: true,
+        ..cli.serve
+    });
+
+    match cli.command.unwrap_or(default) {
+        Command::Serve(args) => {
+            let settings = LiveSettings::new(settings, cli.config, env);
+            serve(settings, args).await
+        }
+        Command::Worker(args) => {
+            let settings = LiveSettings::new(settings, cli.config, env);
+            work(settings, args).await
+        }
+        Command::Report(args) => cli::report(&settings, args).await,
+        Command::Crawl { url } => cli::crawl(&settings, &url).await,
+        Command::Run(args) => cli::run(Arc::new(settings), args).await,
+        Command::Sites(command) => cli::sites(&settings, command).await,
+        Command::Export(args) => cli::export(&settings, args).await,
+        Command::Keys(command) => cli::keys(&settings, command).await,
+        Command::Users(command) => cli::users(&settings, command).await,
+        Command::Teams(command) => cli::teams(&settings, command).await,
+        Command::Jobs(command) => cli::jobs(&settings, command).await,
+        Command::Check(args) => return cli::check(&settings, args).await,
+    }
+    .map(|()| ExitCode::SUCCESS)
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    dotenv().ok();
+async fn serve(live: LiveSettings, args: ServeArgs) -> Result<()> {
+    let settings = live.current();
+    let db = prepare(&settings, args.migrate).await?;
+
+    let mailer = Mailer::from_settings(&settings.smtp)?;
+    if let Some(mailer) = &mailer {
+        digest::spawn_scheduler(db.clone(), mailer.clone(), live.clone());
+    }
+
+    let shutdown = Shutdown::new();
+    let worker = match args.worker {
+        true => Some(start_worker(db.clone(), live.clone(), shutdown.clone()).await?),
+        false => None,
+    };
+
+    let app_state = Arc::new(AppState {
+        db: db.clone(),
+        mailer,
+        settings: live.clone(),
+        shutdown: shutdown.clone(),
+        events: worker.as_ref().map(|worker| worker.events().clone()),
+    });
+    live.spawn_watcher();
+
+    let app = app(app_state);
+
+    let listener = tokio::net::TcpListener::bind(&settings.server.url)
+        .await
+        .with_context(|| format!("Unable to bind `server.url` {}", settings.server.url))?;
+
+    spawn_signal_handler(&shutdown);
+
+    // Open SSE streams end with a `shutdown` event once triggered, so this
+    // only waits for in-flight requests
+    let stopping = shutdown.clone();
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async move { stopping.triggered().await })
+        .await
+        .context("Server error")?;
+
+    stop(&shutdown, worker.as_ref(), &settings).await
+}
+
+async fn work(live: LiveSettings, args: WorkerArgs) -> Result<()> {
+    let settings = live.current();
+    let db = prepare(&settings, args.migrate).await?;
+
+    let shutdown = Shutdown::new();
+    let worker = start_worker(db.clone(), live.clone(), shutdown.clone()).await?;
+    live.spawn_watcher();
+
+    if let Some(url) = &args.listen {
+        let listener = tokio::net::TcpListener::bind(url)
+            .await
+            .with_context(|| format!("Unable to bind `--listen` {url}"))?;
+        let app_state = Arc::new(AppState {
+            db: db.clone(),
+            mailer: None,
+            settings: live.clone(),
+            shutdown: shutdown.clone(),
+            events: Some(worker.events().clone()),
+        });
+
+        let stopping = shutdown.clone();
+        tokio::spawn(async move {
+            axum::serve(listener, probes(app_state))
+                .with_graceful_shutdown(async move { stopping.triggered().await })
+                .await
+        });
+    }
+
+    info!("Worker {} waiting for jobs", worker.id());
+    spawn_signal_handler(&shutdown);
+    shutdown.triggered().await;
+
+    stop(&shutdown, Some(&worker), &settings).await
+}
+
+async fn prepare(settings: &Settings, migrate: bool) -> Result<Arc<DatabaseConnection>> {
+    // Fail at boot rather than on the first audit, e.g. without a PSI key
+    provider_from_settings(&settings.audit)?;
+
+    let db = Arc::new(cli::connect(settings).await?);
+    let schema = schema::prepare(db.as_ref(), migrate || settings.server.migrate).await?;
+    info!(version = ?schema.version, "Database schema checked");
+
+    Ok(db)
+}
+
+// Run events of this process go to the webhook dispatcher and wake the event
+// streams it serves, which read the events from the database
+async fn start_worker(
+    db: Arc<DatabaseConnection>,
+    live: LiveSettings,
+    shutdown: Shutdown,
+) -> Result<Worker> {
+    let (events, _) = broadcast::channel(256);
+    webhooks::spawn_dispatcher(db.clone(), events.subscribe());
+
+    let queued = processor::queue_unfinished_runs(db.as_ref(), &events).await?;
+    if queued > 0 {
+        info!("Queued {queued} unfinished run(s)");
+    }
+
+    let worker = Worker::new(db, events, live, shutdown);
+    worker.spawn();
+    Ok(worker)
+}
+
+fn spawn_signal_handler(shutdown: &Shutdown) {
+    let trigger = shutdown.clone();
+    tokio::spawn(async move {
+        shutdown::signal().await;
+        trigger.trigger();
+    });
+}
 
-    let _psi_url = std::env::var("PSI_URL").context("PSI_URL not found")?;
-    let _psi_key = std::env::var("PSI_KEY").context("PSI_KEY not found")?;
-    let server_url = std::env::var("SERVER_URL").context("SERVER_URL not found")?;
-    let database_url = std::env::var("DATABASE_URL").context("DATABASE_URL not found")?;
-
-    let db: Arc<DatabaseConnection> = Arc::new(Database::connect(database_url).await?);
-    let app_state = Arc::new(AppState { db });
-
-    tracing_subscriber::registry().with(fmt::layer()).init();
-
-    let app = Router::new()
-        .route("/reports", get(reports::sse_reports_handler))
-        .route("/sites", post(sites::create_site_handler))
-        .route("/sites", get(sites::get_sites))
-        .route("/sites/{site_id}", get(sites::get_site))
-        .route("/sites/{site_id}", put(sites::update_site))
-        .route("/sites/{site_id}", delete(sites::delete_site))
-        .layer(
-            TraceLayer::new_for_http()
-                .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
-                .on_response(DefaultOnResponse::new().level(Level::INFO)),
-        )
-        .with_state(app_state);
-
-    let listener = tokio::net::TcpListener::bind(server_url).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+// Waits for the jobs in flight, handing back the ones still running at the
+// deadline
+async fn stop(shutdown: &Shutdown, worker: Option<&Worker>, settings: &Settings) -> Result<()> {
+    let deadline = Duration::from_secs(settings.server.shutdown_timeout);
+    if !shutdown.drain(deadline).await {
+        if let Some(worker) = worker {
+            let released = worker.release().await?;
+            warn!(
+                "Audits still running after {deadline:?}, released {released} job(s) to the queue"
+            );
+        }
+    }
 
     Ok(())
 }
@@ -0,0 +1,156 @@
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use prometheus::{
+    Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+use std::{sync::LazyLock, time::Instant};
+
+/// Process wide metrics, served in the Prometheus text format at `/metrics`.
+pub struct Metrics {
+    registry: Registry,
+    pub http_requests: IntCounterVec,
+    pub http_duration: HistogramVec,
+    pub audits: IntCounterVec,
+    pub audit_duration: HistogramVec,
+    pub sitemap_fetches: IntCounterVec,
+    pub sitemap_duration: Histogram,
+    pub sitemap_urls: IntCounter,
+    pub queue_depth: IntGauge,
+    pub active_runs: IntGauge,
+}
+
+static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+// Audits take tens of seconds, the default buckets stop at 10
+const AUDIT_BUCKETS: [f64; 10] = [1.0, 2.5, 5.0, 10.0, 15.0, 20.0, 30.0, 45.0, 60.0, 120.0];
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("tarin".to_string()), None)
+            .expect("static registry prefix is valid");
+
+        let metrics = Metrics {
+            http_requests: IntCounterVec::new(
+                Opts::new("http_requests_total", "HTTP requests by route and status"),
+                &["method", "path", "status"],
+            )
+            .expect("valid metric"),
+            http_duration: HistogramVec::new(
+                HistogramOpts::new(
+                    "http_request_duration_seconds",
+                    "Time to respond to HTTP requests",
+                ),
+                &["method", "path"],
+            )
+            .expect("valid metric"),
+            audits: IntCounterVec::new(
+                Opts::new("audits_total", "Audit backend calls by outcome"),
+                &["provider", "outcome"],
+            )
+            .expect("valid metric"),
+            audit_duration: HistogramVec::new(
+                HistogramOpts::new(
+                    "audit_duration_seconds",
+                    "Time taken by audit backend calls",
+                )
+                .buckets(AUDIT_BUCKETS.to_vec()),
+                &["provider"],
+            )
+            .expect("valid metric"),
+            sitemap_fetches: IntCounterVec::new(
+                Opts::new("sitemap_fetches_total", "Sitemap requests by outcome"),
+                &["outcome"],
+            )
+            .expect("valid metric"),
+            sitemap_duration: Histogram::with_opts(HistogramOpts::new(
+                "sitemap_fetch_duration_seconds",
+                "Time taken to fetch a sitemap",
+            ))
+            .expect("valid metric"),
+            sitemap_urls: IntCounter::new("sitemap_urls_total", "Urls found in sitemaps")
+                .expect("valid metric"),
+            queue_depth: IntGauge::new("queue_depth", "Audit jobs waiting to be claimed")
+                .expect("valid metric"),
+            active_runs: IntGauge::new("active_runs", "Runs in progress").expect("valid metric"),
+            registry,
+        };
+
+        let collectors: [Box<dyn prometheus::core::Collector>; 9] = [
+            Box::new(metrics.http_requests.clone()),
+            Box::new(metrics.http_duration.clone()),
+            Box::new(metrics.audits.clone()),
+            Box::new(metrics.audit_duration.clone()),
+            Box::new(metrics.sitemap_fetches.clone()),
+            Box::new(metrics.sitemap_duration.clone()),
+            Box::new(metrics.sitemap_urls.clone()),
+            Box::new(metrics.queue_depth.clone()),
+            Box::new(metrics.active_runs.clone()),
+        ];
+        for collector in collectors {
+            metrics
+                .registry
+                .register(collector)
+                .expect("metric names are unique");
+        }
+
+        metrics
+    }
+
+    pub fn render(&self) -> String {
+        TextEncoder::new()
+            .encode_to_string(&self.registry.gather())
+            .unwrap_or_default()
+    }
+}
+
+/// Counts and times requests by their route pattern, so ids in paths don't
+/// create a series each.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let path = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_default();
+    let method = request.method().to_string();
+    let start = Instant::now();
+
+    let response = next.run(request).await;
+
+    let metrics = metrics();
+    metrics
+        .http_duration
+        .with_label_values(&[&method, &path])
+        .observe(start.elapsed().as_secs_f64());
+    metrics
+        .http_requests
+        .with_label_values(&[&method, &path, response.status().as_str()])
+        .inc();
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    mod render {
+        use super::super::*;
+
+        #[test]
+        fn prefixes_metric_names() {
+            metrics().sitemap_urls.inc_by(3);
+            metrics().audits.with_label_values(&["fake", "ok"]).inc();
+
+            let text = metrics().render();
+            assert!(text.contains("# TYPE tarin_sitemap_urls_total counter"));
+            assert!(text.contains("tarin_audits_total{outcome=\"ok\",provider=\"fake\"}"));
+            assert!(text.contains("tarin_active_runs 0"));
+        }
+    }
+}
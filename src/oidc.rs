@@ -0,0 +1,220 @@
+use anyhow::{bail, Context, Result};
+use reqwest::Client;
+use serde::Deserialize;
+use url::Url;
+
+use crate::config::AuthSettings;
+
+/// The endpoints of the provider, from its discovery document.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UserInfo {
+    pub sub: String,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+/// Authorization code flow against the provider in the `auth` settings. The
+/// user is read from the userinfo endpoint so ID tokens are never decoded.
+#[derive(Clone)]
+pub struct OidcClient {
+    issuer: String,
+    client_id: String,
+    client_secret: Option<String>,
+    redirect_url: String,
+    client: Client,
+}
+
+impl OidcClient {
+    /// `None` unless `auth.oidc_issuer` is set.
+    pub fn from_settings(settings: &AuthSettings) -> Option<Self> {
+        Some(OidcClient {
+            issuer: settings.oidc_issuer.clone()?,
+            client_id: settings.oidc_client_id.clone()?,
+            client_secret: settings.oidc_client_secret.clone(),
+            redirect_url: settings.oidc_redirect_url.clone()?,
+            client: Client::new(),
+        })
+    }
+
+    pub async fn discover(&self) -> Result<ProviderMetadata> {
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            self.issuer.trim_end_matches('/')
+        );
+
+        self.client
+            .get(&url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("OIDC discovery request to {url} failed"))?
+            .json()
+            .await
+            .context("Invalid OIDC discovery document")
+    }
+
+    /// Where to send the user to log in, `state` comes back on the callback.
+    pub fn authorize_url(&self, metadata: &ProviderMetadata, state: &str) -> Result<Url> {
+        let mut url = Url::parse(&metadata.authorization_endpoint)
+            .context("Invalid OIDC authorization endpoint")?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.client_id)
+            .append_pair("redirect_uri", &self.redirect_url)
+            .append_pair("scope", "openid email profile")
+            .append_pair("state", state);
+
+        Ok(url)
+    }
+
+    /// Exchanges the callback code and fetches the user it belongs to.
+    pub async fn user_info(&self, metadata: &ProviderMetadata, code: &str) -> Result<UserInfo> {
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &self.redirect_url),
+            ("client_id", &self.client_id),
+        ];
+        if let Some(secret) = &self.client_secret {
+            form.push(("client_secret", secret));
+        }
+
+        let token: TokenResponse = self
+            .client
+            .post(&metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .context("OIDC token request failed")?
+            .json()
+            .await
+            .context("Invalid OIDC token response")?;
+
+        let info: UserInfo = self
+            .client
+            .get(&metadata.userinfo_endpoint)
+            .bearer_auth(token.access_token)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .context("OIDC userinfo request failed")?
+            .json()
+            .await
+            .context("Invalid OIDC userinfo response")?;
+
+        if info.email_verified == Some(false) {
+            bail!("OIDC user {} has an unverified email", info.sub);
+        }
+
+        Ok(info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod user_info {
+        use super::super::*;
+        use mockito::{Matcher, Server};
+        use serde_json::json;
+
+        async fn client(server: &Server) -> (OidcClient, ProviderMetadata) {
+            let settings = AuthSettings {
+                oidc_issuer: Some(server.url()),
+                oidc_client_id: Some("tarin".to_string()),
+                oidc_client_secret: Some("secret".to_string()),
+                oidc_redirect_url: Some("http://localhost/auth/oidc/callback".to_string()),
+                ..Default::default()
+            };
+            let client = OidcClient::from_settings(&settings).unwrap();
+            let metadata = client.discover().await.unwrap();
+            (client, metadata)
+        }
+
+        #[tokio::test]
+        async fn exchanges_code_for_user() {
+            let mut server = Server::new_async().await;
+            server
+                .mock("GET", "/.well-known/openid-configuration")
+                .with_body(
+                    json!({
+                        "authorization_endpoint": format!("{}/authorize", server.url()),
+                        "token_endpoint": format!("{}/token", server.url()),
+                        "userinfo_endpoint": format!("{}/userinfo", server.url()),
+                    })
+                    .to_string(),
+                )
+                .create_async()
+                .await;
+            let token = server
+                .mock("POST", "/token")
+                .match_body(Matcher::AllOf(vec![
+                    Matcher::UrlEncoded("code".into(), "abc".into()),
+                    Matcher::UrlEncoded("client_secret".into(), "secret".into()),
+                ]))
+                .with_body(json!({ "access_token": "token-1" }).to_string())
+                .create_async()
+                .await;
+            server
+                .mock("GET", "/userinfo")
+                .match_header("authorization", "Bearer token-1")
+                .with_body(json!({ "sub": "42", "email": "ana@example.com" }).to_string())
+                .create_async()
+                .await;
+
+            let (client, metadata) = client(&server).await;
+            let url = client.authorize_url(&metadata, "state-1").unwrap();
+            assert!(url.as_str().contains("state=state-1"));
+
+            let info = client.user_info(&metadata, "abc").await.unwrap();
+            token.assert_async().await;
+            assert_eq!(info.sub, "42");
+            assert_eq!(info.email.as_deref(), Some("ana@example.com"));
+        }
+
+        #[tokio::test]
+        async fn rejects_unverified_email() {
+            let mut server = Server::new_async().await;
+            server
+                .mock("GET", "/.well-known/openid-configuration")
+                .with_body(
+                    json!({
+                        "authorization_endpoint": format!("{}/authorize", server.url()),
+                        "token_endpoint": format!("{}/token", server.url()),
+                        "userinfo_endpoint": format!("{}/userinfo", server.url()),
+                    })
+                    .to_string(),
+                )
+                .create_async()
+                .await;
+            server
+                .mock("POST", "/token")
+                .with_body(json!({ "access_token": "token-1" }).to_string())
+                .create_async()
+                .await;
+            server
+                .mock("GET", "/userinfo")
+                .with_body(
+                    json!({ "sub": "42", "email": "ana@example.com", "email_verified": false })
+                        .to_string(),
+                )
+                .create_async()
+                .await;
+
+            let (client, metadata) = client(&server).await;
+            assert!(client.user_info(&metadata, "abc").await.is_err());
+        }
+    }
+}
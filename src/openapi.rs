@@ -0,0 +1,161 @@
+use utoipa::{
+    openapi::{
+        security::{
+            ApiKey, ApiKeyValue, Http, HttpAuthScheme, SecurityRequirement, SecurityScheme,
+        },
+        Info, OpenApi as Document, Response,
+    },
+    Modify, OpenApi,
+};
+
+use crate::auth::{Scope, SESSION_COOKIE};
+
+/// The info and security schemes of the document served at `/openapi.json`.
+/// Its paths and schemas come from the `#[utoipa::path]` handlers `api` routes.
+#[derive(OpenApi)]
+#[openapi(modifiers(&Tarin))]
+pub struct ApiDoc;
+
+struct Tarin;
+
+impl Modify for Tarin {
+    fn modify(&self, openapi: &mut Document) {
+        openapi.info = Info::new("tarin", env!("CARGO_PKG_VERSION"));
+
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "apiKey",
+            SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+        );
+        components.add_security_scheme(
+            "session",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(SESSION_COOKIE))),
+        );
+    }
+}
+
+/// Documents every operation of `openapi` as needing an API key or session
+/// allowed `scope`, the way `auth::require_scope` guards their router.
+pub fn require_scope(openapi: &mut Document, scope: Scope) {
+    let requirement = format!("Requires the `{}` scope.", scope.as_str());
+
+    for item in openapi.paths.paths.values_mut() {
+        let operations = [
+            &mut item.get,
+            &mut item.put,
+            &mut item.post,
+            &mut item.delete,
+            &mut item.patch,
+        ];
+
+        for operation in operations.into_iter().flatten() {
+            operation.description = Some(match operation.description.take() {
+                Some(description) => format!("{description}\n\n{requirement}"),
+                None => requirement.clone(),
+            });
+            operation.security = Some(vec![
+                SecurityRequirement::new("apiKey", Vec::<String>::new()),
+                SecurityRequirement::new("session", Vec::<String>::new()),
+            ]);
+
+            let responses = &mut operation.responses.responses;
+            for (status, description) in [
+                ("401", "Missing or invalid API key or session"),
+                ("403", "Not allowed"),
+            ] {
+                responses
+                    .entry(status.to_string())
+                    .or_insert_with(|| Response::new(description).into());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod components {
+        use chrono::NaiveDateTime;
+        use serde_json::{json, Value};
+        use utoipa::PartialSchema;
+
+        use crate::{
+            entities::{
+                runs,
+                sea_orm_active_enums::{Role, RunStatus, Strategy},
+                sites, users,
+            },
+            routes::{groups::NewRun, sites::NewSite},
+        };
+
+        fn schema<T: PartialSchema>() -> Value {
+            serde_json::to_value(T::schema()).unwrap()
+        }
+
+        fn properties<T: PartialSchema>() -> Vec<String> {
+            let mut names: Vec<String> = schema::<T>()["properties"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect();
+            names.sort();
+            names
+        }
+
+        fn serialized(value: impl serde::Serialize) -> Vec<String> {
+            let mut names: Vec<String> = serde_json::to_value(value)
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect();
+            names.sort();
+            names
+        }
+
+        #[test]
+        fn match_serialized_fields() {
+            let site = sites::Model {
+                id: 1,
+                domain: "https://example.com".to_string(),
+                created_at: NaiveDateTime::default(),
+                deleted_at: None,
+                team_id: None,
+            };
+            let run = runs::Model {
+                id: 1,
+                group_id: None,
+                status: RunStatus::Pending,
+                strategy: Strategy::Mobile,
+                created_at: NaiveDateTime::default(),
+                finished_at: None,
+                samples: 1,
+            };
+            let user = users::Model {
+                id: 1,
+                email: "ana@example.com".to_string(),
+                name: None,
+                password_hash: Some("hash".to_string()),
+                oidc_subject: None,
+                is_admin: false,
+                created_at: NaiveDateTime::default(),
+            };
+
+            assert_eq!(properties::<sites::Model>(), serialized(&site));
+            assert_eq!(properties::<runs::Model>(), serialized(&run));
+            assert_eq!(properties::<users::Model>(), serialized(&user));
+        }
+
+        #[test]
+        fn optional_and_defaulted_fields_are_not_required() {
+            assert_eq!(schema::<NewSite>()["required"], json!(["domain"]));
+            assert_eq!(schema::<NewRun>().get("required"), None);
+            assert_eq!(schema::<NewRun>()["properties"]["samples"]["default"], 1);
+            assert_eq!(
+                schema::<Role>()["enum"],
+                json!(["viewer", "editor", "admin"])
+            );
+        }
+    }
+}
@@ -0,0 +1,455 @@
+use chrono::{NaiveDateTime, Utc};
+use sea_orm::{
+    sea_query::{Expr, LockBehavior, LockType, OnConflict, Query},
+    ActiveEnum,
+    ActiveValue::Set,
+    ColumnTrait, Condition, ConnectionTrait, DbErr, EntityTrait, Order, PaginatorTrait,
+    QueryFilter, QueryOrder,
+};
+use std::{collections::HashMap, time::Duration};
+use url::Url;
+
+use crate::{
+    config::QueueSettings,
+    entities::{
+        audit_jobs::{self, Entity as AuditJobs},
+        reports::{self, Entity as Reports},
+        runs,
+        sea_orm_active_enums::{JobKind, JobStatus, RunStatus},
+        sites,
+    },
+};
+
+// Retries never wait longer than this, however many attempts came before
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
+fn now() -> NaiveDateTime {
+    Utc::now().naive_utc()
+}
+
+fn after(delay: Duration) -> NaiveDateTime {
+    now() + chrono::Duration::from_std(delay).unwrap_or(chrono::Duration::zero())
+}
+
+/// Wait before retrying a job that failed its `attempts`th attempt.
+pub fn retry_delay(settings: &QueueSettings, attempts: i32) -> Duration {
+    let doublings = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    Duration::from_secs(settings.retry_delay.saturating_mul(1 << doublings)).min(MAX_RETRY_DELAY)
+}
+
+/// Queues a crawl of each site, which queues the site's audits in turn.
+pub async fn enqueue_run<C: ConnectionTrait>(
+    db: &C,
+    run: &runs::Model,
+    sites: &[sites::Model],
+) -> Result<(), DbErr> {
+    let jobs = sites.iter().map(|site| audit_jobs::ActiveModel {
+        run_id: Set(run.id),
+        site_id: Set(site.id),
+        kind: Set(JobKind::Crawl),
+        strategy: Set(run.strategy),
+        status: Set(JobStatus::Queued),
+        available_at: Set(now()),
+        ..Default::default()
+    });
+
+    AuditJobs::insert_many(jobs)
+        .on_empty_do_nothing()
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Queues each sample of `urls` the run hasn't stored a report for yet. Audits
+/// already queued are left alone, so crawling a site twice is harmless.
+pub async fn enqueue_audits<C: ConnectionTrait>(
+    db: &C,
+    run: &runs::Model,
+    site_id: i32,
+    urls: &[Url],
+) -> Result<u64, DbErr> {
+    let stored = stored_samples(db, run.id, site_id).await?;
+
+    let jobs: Vec<audit_jobs::ActiveModel> = urls
+        .iter()
+        .flat_map(|url| {
+            let done = stored.get(url.as_str()).copied().unwrap_or(0);
+            ((done + 1)..=run.samples).map(move |sample| audit_jobs::ActiveModel {
+                run_id: Set(run.id),
+                site_id: Set(site_id),
+                kind: Set(JobKind::Audit),
+                url: Set(Some(url.to_string())),
+                strategy: Set(run.strategy),
+                sample: Set(Some(sample)),
+                status: Set(JobStatus::Queued),
+                available_at: Set(now()),
+                ..Default::default()
+            })
+        })
+        .collect();
+
+    if jobs.is_empty() {
+        return Ok(0);
+    }
+
+    AuditJobs::insert_many(jobs)
+        .on_conflict(
+            OnConflict::columns([
+                audit_jobs::Column::RunId,
+                audit_jobs::Column::Url,
+                audit_jobs::Column::Strategy,
+                audit_jobs::Column::Sample,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await
+}
+
+// Reports stored per url, from before a crash or from a previous crawl
+async fn stored_samples<C: ConnectionTrait>(
+    db: &C,
+    run_id: i32,
+    site_id: i32,
+) -> Result<HashMap<String, i32>, DbErr> {
+    let reports = Reports::find()
+        .filter(reports::Column::RunId.eq(run_id))
+        .filter(reports::Column::SiteId.eq(site_id))
+        .all(db)
+        .await?;
+
+    let mut stored = HashMap::new();
+    for report in reports {
+        *stored.entry(report.url).or_insert(0) += 1;
+    }
+
+    Ok(stored)
+}
+
+/// Claims the oldest available job for `worker`, hiding it from other workers
+/// for the visibility timeout. Jobs whose timeout passed are claimed again.
+///
+/// On Postgres the candidate row is locked with `FOR UPDATE SKIP LOCKED`, so
+/// concurrent workers never wait on or claim the same job. SQLite serializes
+/// writes instead.
+pub async fn claim<C: ConnectionTrait>(
+    db: &C,
+    worker: &str,
+    settings: &QueueSettings,
+    run_id: Option<i32>,
+) -> Result<Option<audit_jobs::Model>, DbErr> {
+    let now = now();
+    let mut candidate = Query::select();
+    candidate
+        .column(audit_jobs::Column::Id)
+        .from(AuditJobs)
+        .cond_where(
+            Condition::any()
+                .add(
+                    audit_jobs::Column::Status
+                        .eq(JobStatus::Queued)
+                        .and(audit_jobs::Column::AvailableAt.lte(now)),
+                )
+                .add(
+                    audit_jobs::Column::Status
+                        .eq(JobStatus::Running)
+                        .and(audit_jobs::Column::LockedUntil.lt(now))
+                        .and(audit_jobs::Column::Attempts.lt(settings.max_attempts)),
+                ),
+        )
+        .order_by(audit_jobs::Column::Id, Order::Asc)
+        .limit(1)
+        .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked);
+    if let Some(run_id) = run_id {
+        candidate.and_where(audit_jobs::Column::RunId.eq(run_id));
+    }
+
+    let claimed = AuditJobs::update_many()
+        .col_expr(
+            audit_jobs::Column::Status,
+            Expr::value(JobStatus::Running.to_value()),
+        )
+        .col_expr(
+            audit_jobs::Column::Attempts,
+            Expr::col(audit_jobs::Column::Attempts).add(1),
+        )
+        .col_expr(
+            audit_jobs::Column::LockedUntil,
+            Expr::value(after(Duration::from_secs(settings.visibility_timeout))),
+        )
+        .col_expr(audit_jobs::Column::LockedBy, Expr::value(worker))
+        .filter(audit_jobs::Column::Id.in_subquery(candidate))
+        .exec_with_returning(db)
+        .await?;
+
+    Ok(claimed.into_iter().next())
+}
+
+// Whether `job` is still held by the claim it was returned from. Once the
+// visibility timeout passes another claim takes over, bumping the attempts
+fn still_claimed(job: &audit_jobs::Model) -> Condition {
+    Condition::all()
+        .add(audit_jobs::Column::Id.eq(job.id))
+        .add(audit_jobs::Column::Status.eq(JobStatus::Running))
+        .add(audit_jobs::Column::LockedBy.eq(job.locked_by.clone()))
+        .add(audit_jobs::Column::Attempts.eq(job.attempts))
+}
+
+/// Marks the claimed job done. `false` when the claim was lost to another
+/// worker, which then owns the job and the stale result is dropped.
+pub async fn complete<C: ConnectionTrait>(db: &C, job: &audit_jobs::Model) -> Result<bool, DbErr> {
+    let completed = AuditJobs::update_many()
+        .col_expr(
+            audit_jobs::Column::Status,
+            Expr::value(JobStatus::Done.to_value()),
+        )
+        .col_expr(
+            audit_jobs::Column::LockedUntil,
+            Expr::value(Option::<NaiveDateTime>::None),
+        )
+        .col_expr(audit_jobs::Column::FinishedAt, Expr::value(now()))
+        .filter(still_claimed(job))
+        .exec(db)
+        .await?;
+
+    Ok(completed.rows_affected > 0)
+}
+
+/// Queues the job again after the retry delay, or dead-letters it once it is
+/// out of attempts. Returns the job's new status, or `None` when the claim was
+/// lost to another worker and the failure is dropped.
+pub async fn fail<C: ConnectionTrait>(
+    db: &C,
+    job: &audit_jobs::Model,
+    error: &str,
+    settings: &QueueSettings,
+) -> Result<Option<JobStatus>, DbErr> {
+    let status = if job.attempts >= settings.max_attempts {
+        JobStatus::Dead
+    } else {
+        JobStatus::Queued
+    };
+
+    let mut update = AuditJobs::update_many()
+        .col_expr(audit_jobs::Column::Status, Expr::value(status.to_value()))
+        .col_expr(
+            audit_jobs::Column::LockedUntil,
+            Expr::value(Option::<NaiveDateTime>::None),
+        )
+        .col_expr(audit_jobs::Column::LastError, Expr::value(error))
+        .filter(still_claimed(job));
+    update = match status {
+        JobStatus::Dead => update.col_expr(audit_jobs::Column::FinishedAt, Expr::value(now())),
+        _ => update.col_expr(
+            audit_jobs::Column::AvailableAt,
+            Expr::value(after(retry_delay(settings, job.attempts))),
+        ),
+    };
+    let failed = update.exec(db).await?;
+
+    Ok((failed.rows_affected > 0).then_some(status))
+}
+
+/// Dead-letters claimed jobs whose visibility timeout passed on their last
+/// attempt, returning their runs so they can be finished.
+pub async fn expire<C: ConnectionTrait>(
+    db: &C,
+    settings: &QueueSettings,
+) -> Result<Vec<i32>, DbErr> {
+    let expired = AuditJobs::update_many()
+        .col_expr(
+            audit_jobs::Column::Status,
+            Expr::value(JobStatus::Dead.to_value()),
+        )
+        .col_expr(
+            audit_jobs::Column::LastError,
+            Expr::value("Visibility timeout expired on the last attempt"),
+        )
+        .col_expr(audit_jobs::Column::FinishedAt, Expr::value(now()))
+        .filter(audit_jobs::Column::Status.eq(JobStatus::Running))
+        .filter(audit_jobs::Column::LockedUntil.lt(now()))
+        .filter(audit_jobs::Column::Attempts.gte(settings.max_attempts))
+        .exec_with_returning(db)
+        .await?;
+
+    let mut runs: Vec<i32> = expired.into_iter().map(|job| job.run_id).collect();
+    runs.sort_unstable();
+    runs.dedup();
+    Ok(runs)
+}
+
+/// Hands the jobs `worker` still holds back to the queue without using up an
+/// attempt, for when a shutdown deadline passes mid-audit.
+pub async fn release<C: ConnectionTrait>(db: &C, worker: &str) -> Result<u64, DbErr> {
+    let released = AuditJobs::update_many()
+        .col_expr(
+            audit_jobs::Column::Status,
+            Expr::value(JobStatus::Queued.to_value()),
+        )
+        .col_expr(
+            audit_jobs::Column::Attempts,
+            Expr::col(audit_jobs::Column::Attempts).sub(1),
+        )
+        .col_expr(
+            audit_jobs::Column::LockedUntil,
+            Expr::value(Option::<NaiveDateTime>::None),
+        )
+        .col_expr(audit_jobs::Column::AvailableAt, Expr::value(now()))
+        .filter(audit_jobs::Column::Status.eq(JobStatus::Running))
+        .filter(audit_jobs::Column::LockedBy.eq(worker))
+        .exec(db)
+        .await?;
+
+    Ok(released.rows_affected)
+}
+
+/// Puts a dead job back in the queue with fresh attempts, reopening its run so
+/// it finishes again once the job is done.
+pub async fn retry<C: ConnectionTrait>(db: &C, job_id: i32) -> Result<bool, DbErr> {
+    let retried = AuditJobs::update_many()
+        .col_expr(
+            audit_jobs::Column::Status,
+            Expr::value(JobStatus::Queued.to_value()),
+        )
+        .col_expr(audit_jobs::Column::Attempts, Expr::value(0))
+        .col_expr(audit_jobs::Column::AvailableAt, Expr::value(now()))
+        .col_expr(
+            audit_jobs::Column::FinishedAt,
+            Expr::value(Option::<NaiveDateTime>::None),
+        )
+        .filter(audit_jobs::Column::Id.eq(job_id))
+        .filter(audit_jobs::Column::Status.eq(JobStatus::Dead))
+        .exec_with_returning(db)
+        .await?;
+    let Some(job) = retried.into_iter().next() else {
+        return Ok(false);
+    };
+
+    runs::Entity::update_many()
+        .col_expr(
+            runs::Column::Status,
+            Expr::value(RunStatus::Running.to_value()),
+        )
+        .col_expr(
+            runs::Column::FinishedAt,
+            Expr::value(Option::<NaiveDateTime>::None),
+        )
+        .filter(runs::Column::Id.eq(job.run_id))
+        .exec(db)
+        .await?;
+
+    Ok(true)
+}
+
+/// Jobs of the run that may still be worked on.
+pub async fn open_jobs<C: ConnectionTrait>(db: &C, run_id: i32) -> Result<u64, DbErr> {
+    AuditJobs::find()
+        .filter(audit_jobs::Column::RunId.eq(run_id))
+        .filter(audit_jobs::Column::Status.is_in([JobStatus::Queued, JobStatus::Running]))
+        .count(db)
+        .await
+}
+
+pub async fn depth<C: ConnectionTrait>(db: &C) -> Result<u64, DbErr> {
+    AuditJobs::find()
+        .filter(audit_jobs::Column::Status.eq(JobStatus::Queued))
+        .count(db)
+        .await
+}
+
+pub async fn list<C: ConnectionTrait>(
+    db: &C,
+    status: Option<JobStatus>,
+) -> Result<Vec<audit_jobs::Model>, DbErr> {
+    let mut query = AuditJobs::find();
+    if let Some(status) = status {
+        query = query.filter(audit_jobs::Column::Status.eq(status));
+    }
+
+    query.order_by_asc(audit_jobs::Column::Id).all(db).await
+}
+
+#[cfg(test)]
+mod tests {
+    mod retry_delay {
+        use super::super::*;
+
+        #[test]
+        fn doubles_up_to_an_hour() {
+            let settings = QueueSettings {
+                retry_delay: 30,
+                ..Default::default()
+            };
+
+            assert_eq!(retry_delay(&settings, 1), Duration::from_secs(30));
+            assert_eq!(retry_delay(&settings, 2), Duration::from_secs(60));
+            assert_eq!(retry_delay(&settings, 3), Duration::from_secs(120));
+            assert_eq!(retry_delay(&settings, 40), MAX_RETRY_DELAY);
+        }
+    }
+
+    mod complete {
+        use super::super::*;
+        use crate::entities::sea_orm_active_enums::Strategy;
+        use sea_orm::{ActiveModelTrait, Database};
+
+        #[tokio::test]
+        async fn drops_results_of_a_lost_claim() {
+            let db = Database::connect("sqlite::memory:").await.unwrap();
+            crate::schema::prepare(&db, true).await.unwrap();
+
+            let site = sites::ActiveModel {
+                domain: Set("https://example.com".to_string()),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+            let run = runs::ActiveModel {
+                status: Set(RunStatus::Running),
+                strategy: Set(Strategy::Mobile),
+                samples: Set(1),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+            enqueue_run(&db, &run, &[site]).await.unwrap();
+
+            // The first claim times out straight away, so a second worker
+            // takes the job over while the first is still working on it
+            let settings = QueueSettings {
+                visibility_timeout: 0,
+                ..Default::default()
+            };
+            let stale = claim(&db, "first", &settings, None).await.unwrap().unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            let current = claim(&db, "second", &settings, None)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(stale.id, current.id);
+
+            assert!(!complete(&db, &stale).await.unwrap());
+            assert_eq!(fail(&db, &stale, "late", &settings).await.unwrap(), None);
+            let job = AuditJobs::find_by_id(current.id)
+                .one(&db)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(job.status, JobStatus::Running);
+            assert_eq!(job.locked_by.as_deref(), Some("second"));
+            assert_eq!(job.last_error, None);
+
+            assert!(complete(&db, &current).await.unwrap());
+            let job = AuditJobs::find_by_id(current.id)
+                .one(&db)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(job.status, JobStatus::Done);
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+use tokio::{fs, task::JoinHandle};
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+
+use crate::config::{Settings, DEFAULT_FILE};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Settings for a running server, swapped in place when the config file
+/// changes. An invalid file is reported and the last valid settings stay.
+#[derive(Clone)]
+pub struct LiveSettings {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    path: Option<PathBuf>,
+    env: Vec<(String, String)>,
+    current: RwLock<Arc<Settings>>,
+    status: RwLock<ReloadStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReloadStatus {
+    pub path: String,
+    pub loaded_at: DateTime<Utc>,
+    /// The last failed reload, cleared by the next successful one.
+    pub error: Option<ReloadError>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReloadError {
+    pub message: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+impl LiveSettings {
+    /// `path` and `env` are the sources `settings` was loaded from, reloads
+    /// read them again.
+    pub fn new(settings: Settings, path: Option<PathBuf>, env: Vec<(String, String)>) -> Self {
+        let status = ReloadStatus {
+            path: path
+                .as_deref()
+                .unwrap_or(Path::new(DEFAULT_FILE))
+                .display()
+                .to_string(),
+            loaded_at: Utc::now(),
+            error: None,
+        };
+
+        LiveSettings {
+            inner: Arc::new(Inner {
+                path,
+                env,
+                current: RwLock::new(Arc::new(settings)),
+                status: RwLock::new(status),
+            }),
+        }
+    }
+
+    /// The active settings, callers keep the snapshot for the whole run.
+    pub fn current(&self) -> Arc<Settings> {
+        self.inner.current.read().unwrap().clone()
+    }
+
+    pub fn status(&self) -> ReloadStatus {
+        self.inner.status.read().unwrap().clone()
+    }
+
+    fn file(&self) -> &Path {
+        self.inner
+            .path
+            .as_deref()
+            .unwrap_or(Path::new(DEFAULT_FILE))
+    }
+
+    /// Loads the config again, keeping the current settings when it is invalid.
+    pub async fn reload(&self) -> Result<()> {
+        let loaded = Settings::load(self.inner.path.as_deref(), self.inner.env.clone()).await;
+        let mut status = self.inner.status.write().unwrap();
+
+        match loaded {
+            Ok(loaded) => {
+                let (settings, ignored) = apply_reloadable(&self.current(), loaded);
+                if !ignored.is_empty() {
+                    warn!(
+                        "Config changes to {} need a restart to apply",
+                        ignored.join(", ")
+                    );
+                }
+
+                *self.inner.current.write().unwrap() = Arc::new(settings);
+                status.loaded_at = Utc::now();
+                status.error = None;
+                info!("Reloaded config from {}", status.path);
+                Ok(())
+            }
+            Err(e) => {
+                error!(
+                    "Keeping the last valid config, reloading {} failed: {e:#}",
+                    status.path
+                );
+                status.error = Some(ReloadError {
+                    message: format!("{e:#}"),
+                    failed_at: Utc::now(),
+                });
+                Err(e)
+            }
+        }
+    }
+
+    /// Polls the config file and reloads whenever it is modified, created or
+    /// removed.
+    pub fn spawn_watcher(&self) -> JoinHandle<()> {
+        let live = self.clone();
+
+        tokio::spawn(async move {
+            let mut seen = modified(live.file()).await;
+            let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+            loop {
+                interval.tick().await;
+
+                let current = modified(live.file()).await;
+                if current != seen {
+                    seen = current;
+                    // Failures are logged and recorded in the status
+                    live.reload().await.ok();
+                }
+            }
+        })
+    }
+}
+
+async fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).await.and_then(|m| m.modified()).ok()
+}
+
+/// Takes the sampling, budget, regression and digest settings from `loaded`.
+/// Connections and the listener are set up once at boot, so changes to those
+/// sections are returned by name instead.
+fn apply_reloadable(current: &Settings, loaded: Settings) -> (Settings, Vec<&'static str>) {
+    let mut ignored = Vec::new();
+    if loaded.server != current.server {
+        ignored.push("server");
+    }
+    if loaded.database != current.database {
+        ignored.push("database");
+    }
+    if loaded.audit != current.audit {
+        ignored.push("audit");
+    }
+    if loaded.smtp != current.smtp {
+        ignored.push("smtp");
+    }
+    if loaded.queue != current.queue {
+        ignored.push("queue");
+    }
+
+    let settings = Settings {
+        server: current.server.clone(),
+        database: current.database.clone(),
+        audit: current.audit.clone(),
+        smtp: current.smtp.clone(),
+        queue: current.queue.clone(),
+        ..loaded
+    };
+
+    (settings, ignored)
+}
+
+#[cfg(test)]
+mod tests {
+    mod reload {
+        use super::super::*;
+        use std::io::Write;
+        use tempfile::NamedTempFile;
+
+        async fn live(file: &NamedTempFile) -> LiveSettings {
+            let settings = Settings::load(Some(file.path()), Vec::new()).await.unwrap();
+            LiveSettings::new(settings, Some(file.path().to_path_buf()), Vec::new())
+        }
+
+        fn rewrite(file: &NamedTempFile, content: &str) {
+            let mut handle = file.reopen().unwrap();
+            handle.set_len(0).unwrap();
+            handle.write_all(content.as_bytes()).unwrap();
+        }
+
+        #[tokio::test]
+        async fn applies_valid_changes() {
+            let file = NamedTempFile::new().unwrap();
+            rewrite(&file, "patterns = [\"/blog/:slug\"]");
+            let live = live(&file).await;
+
+            rewrite(
+                &file,
+                "patterns = [\"/news/:slug\"]\nignore_paths = [\"/search\"]",
+            );
+            live.reload().await.unwrap();
+
+            assert_eq!(live.current().patterns, vec!["/news/:slug"]);
+            assert_eq!(live.current().ignore_paths, vec!["/search"]);
+            assert!(live.status().error.is_none());
+        }
+
+        #[tokio::test]
+        async fn keeps_last_valid_settings() {
+            let file = NamedTempFile::new().unwrap();
+            rewrite(&file, "patterns = [\"/blog/:slug\"]");
+            let live = live(&file).await;
+
+            rewrite(&file, "patterns = [\"/news/:slug\"\nignore_paths = 1");
+            assert!(live.reload().await.is_err());
+
+            assert_eq!(live.current().patterns, vec!["/blog/:slug"]);
+            let error = live.status().error.unwrap();
+            assert!(error.message.starts_with("Invalid config file"));
+
+            rewrite(&file, "patterns = [\"/news/:slug\"]");
+            live.reload().await.unwrap();
+            assert!(live.status().error.is_none());
+        }
+
+        #[tokio::test]
+        async fn restart_sections_are_not_applied() {
+            let file = NamedTempFile::new().unwrap();
+            rewrite(&file, "[server]\nurl = \"127.0.0.1:8080\"");
+            let live = live(&file).await;
+
+            rewrite(
+                &file,
+                "[server]\nurl = \"0.0.0.0:80\"\n\n[digest]\nhour = 6",
+            );
+            live.reload().await.unwrap();
+
+            assert_eq!(live.current().server.url, "127.0.0.1:8080");
+            assert_eq!(live.current().digest.hour, 6);
+        }
+    }
+}
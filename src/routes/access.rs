@@ -0,0 +1,152 @@
+use axum::http::StatusCode;
+use sea_orm::{
+    sea_query::Query, ColumnTrait, Condition, ConnectionTrait, EntityTrait, ModelTrait,
+    QueryFilter, QueryOrder,
+};
+
+use crate::{
+    auth::Principal,
+    entities::{
+        audit_jobs::{self, Entity as AuditJobs},
+        groups::{self, Entity as Groups},
+        reports::{self, Entity as Reports},
+        runs::{self, Entity as Runs},
+        sea_orm_active_enums::Role,
+        sites::{self, Entity as Sites},
+    },
+};
+
+// What the principal can't see is a 404 so other teams can't probe for it
+pub fn check_role(role: Option<Role>, needed: Role) -> Result<(), StatusCode> {
+    match role {
+        None => Err(StatusCode::NOT_FOUND),
+        Some(role) if role < needed => Err(StatusCode::FORBIDDEN),
+        Some(_) => Ok(()),
+    }
+}
+
+pub fn check_site_role(
+    principal: &Principal,
+    site: &sites::Model,
+    needed: Role,
+) -> Result<(), StatusCode> {
+    check_role(principal.site_role(site), needed)
+}
+
+/// The site, deleted or not, when the principal has at least `needed` on it.
+pub async fn authorize_site<C: ConnectionTrait>(
+    db: &C,
+    principal: &Principal,
+    site_id: i32,
+    needed: Role,
+) -> Result<sites::Model, StatusCode> {
+    let site = Sites::find_by_id(site_id)
+        .one(db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    check_site_role(principal, &site, needed)?;
+
+    Ok(site)
+}
+
+/// Like `authorize_site` but deleted sites are a 404.
+pub async fn authorize_active_site<C: ConnectionTrait>(
+    db: &C,
+    principal: &Principal,
+    site_id: i32,
+    needed: Role,
+) -> Result<sites::Model, StatusCode> {
+    let site = authorize_site(db, principal, site_id, needed).await?;
+    if site.deleted_at.is_some() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(site)
+}
+
+/// The group when the principal has at least `needed` on every one of its
+/// sites, deleted ones included.
+pub async fn authorize_group<C: ConnectionTrait>(
+    db: &C,
+    principal: &Principal,
+    group_id: i32,
+    needed: Role,
+) -> Result<groups::Model, StatusCode> {
+    let group = Groups::find_by_id(group_id)
+        .one(db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    if !principal.sees_every_team() {
+        let sites = group
+            .find_related(Sites)
+            .all(db)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        check_role(principal.sites_role(&sites), needed)?;
+    }
+
+    Ok(group)
+}
+
+/// The run when the principal has at least `needed` on every site it audited
+/// or queued. A run's group may have changed since, so its sites don't count.
+pub async fn authorize_run<C: ConnectionTrait>(
+    db: &C,
+    principal: &Principal,
+    run_id: i32,
+    needed: Role,
+) -> Result<runs::Model, StatusCode> {
+    let run = Runs::find_by_id(run_id)
+        .one(db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    if !principal.sees_every_team() {
+        let queued = Query::select()
+            .column(audit_jobs::Column::SiteId)
+            .from(AuditJobs)
+            .and_where(audit_jobs::Column::RunId.eq(run_id))
+            .to_owned();
+        let reported = Query::select()
+            .column(reports::Column::SiteId)
+            .from(Reports)
+            .and_where(reports::Column::RunId.eq(run_id))
+            .to_owned();
+
+        let sites = Sites::find()
+            .filter(
+                Condition::any()
+                    .add(sites::Column::Id.in_subquery(queued))
+                    .add(sites::Column::Id.in_subquery(reported)),
+            )
+            .all(db)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        check_role(principal.sites_role(&sites), needed)?;
+    }
+
+    Ok(run)
+}
+
+/// Groups the principal can see at least one role on.
+pub async fn visible_groups<C: ConnectionTrait>(
+    db: &C,
+    principal: &Principal,
+) -> Result<Vec<groups::Model>, StatusCode> {
+    let groups = Groups::find()
+        .find_with_related(Sites)
+        .order_by_asc(groups::Column::Id)
+        .all(db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(groups
+        .into_iter()
+        .filter(|(_, sites)| principal.sites_role(sites).is_some())
+        .map(|(group, _)| group)
+        .collect())
+}
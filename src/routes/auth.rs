@@ -0,0 +1,288 @@
+use axum::{
+    extract::{Query, State},
+    http::{header::SET_COOKIE, HeaderMap, StatusCode},
+    response::{AppendHeaders, IntoResponse, Redirect},
+    Json,
+};
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::error;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    accounts,
+    auth::{self, generate_key, Principal, SESSION_COOKIE},
+    config::AuthSettings,
+    entities::{
+        sea_orm_active_enums::Role,
+        teams::Entity as Teams,
+        users::{self, Entity as Users},
+    },
+    oidc::OidcClient,
+    AppState,
+};
+
+const STATE_COOKIE: &str = "tarin_oidc_state";
+
+#[derive(Deserialize, ToSchema)]
+pub struct Login {
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TeamRole {
+    pub id: i32,
+    pub name: String,
+    pub role: Role,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CurrentUser {
+    #[serde(flatten)]
+    pub user: users::Model,
+    pub teams: Vec<TeamRole>,
+}
+
+fn cookie(name: &str, value: &str, path: &str, max_age: i64, settings: &AuthSettings) -> String {
+    let secure = if settings.secure() { "; Secure" } else { "" };
+    format!("{name}={value}; Path={path}; HttpOnly; SameSite=Lax; Max-Age={max_age}{secure}")
+}
+
+async fn start_session(app_state: &AppState, user_id: i32) -> Result<String, StatusCode> {
+    let settings = &app_state.settings.current().auth;
+    let hours = settings.session_hours;
+    let token = accounts::create_session(app_state.db.as_ref(), user_id, hours)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(cookie(SESSION_COOKIE, &token, "/", hours * 3600, settings))
+}
+
+/// Log in with a password, setting a session cookie.
+#[utoipa::path(
+    post,
+    path = "/auth/login",
+    responses(
+        (status = 200, description = "OK", body = users::Model),
+        (status = 401, description = "Wrong email or password"),
+    )
+)]
+pub async fn login(
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<Login>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let user = Users::find()
+        .filter(users::Column::Email.eq(payload.email.trim().to_lowercase()))
+        .one(app_state.db.as_ref())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let stored = user.as_ref().and_then(|user| user.password_hash.clone());
+
+    // Hashing is deliberately slow, keep it off the async workers. Unknown
+    // emails are hashed too so timing doesn't tell which ones exist
+    let valid = tokio::task::spawn_blocking(move || {
+        accounts::verify_login(&payload.password, stored.as_deref())
+    })
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let Some(user) = user.filter(|_| valid) else {
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+
+    let cookie = start_session(&app_state, user.id).await?;
+    Ok(([(SET_COOKIE, cookie)], Json(user)))
+}
+
+/// End the session.
+#[utoipa::path(
+    post,
+    path = "/auth/logout",
+    responses(
+        (status = 204, description = "No content"),
+    )
+)]
+pub async fn logout(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, StatusCode> {
+    if let Some(token) = auth::session_token(&headers) {
+        accounts::end_session(app_state.db.as_ref(), token)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    let settings = &app_state.settings.current().auth;
+    Ok((
+        [(SET_COOKIE, cookie(SESSION_COOKIE, "", "/", 0, settings))],
+        StatusCode::NO_CONTENT,
+    ))
+}
+
+/// The logged in user and their team roles.
+#[utoipa::path(
+    get,
+    path = "/auth/me",
+    responses(
+        (status = 200, description = "OK", body = CurrentUser),
+        (status = 401, description = "Not logged in"),
+    )
+)]
+pub async fn get_current_user(
+    State(app_state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<CurrentUser>, StatusCode> {
+    let db = app_state.db.as_ref();
+    let Some(Principal::User { user, teams }) = auth::principal(db, &headers)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    else {
+        return Err(StatusCode::UNAUTHORIZED);
+    };
+
+    let names = Teams::find()
+        .all(db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let teams = teams
+        .into_iter()
+        .filter_map(|member| {
+            let team = names.iter().find(|team| team.id == member.team_id)?;
+            Some(TeamRole {
+                id: team.id,
+                name: team.name.clone(),
+                role: member.role,
+            })
+        })
+        .collect();
+
+    Ok(Json(CurrentUser { user, teams }))
+}
+
+fn oidc_client(app_state: &AppState) -> Result<OidcClient, StatusCode> {
+    OidcClient::from_settings(&app_state.settings.current().auth).ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Log in through the OIDC provider.
+///
+/// Redirects to the provider, 404 when OIDC isn't configured.
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/login",
+    responses(
+        (status = 303, description = "Redirect to the provider"),
+        (status = 404, description = "Not found"),
+        (status = 502, description = "The provider is unreachable"),
+    )
+)]
+pub async fn oidc_login(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let client = oidc_client(&app_state)?;
+    let metadata = client.discover().await.map_err(|e| {
+        error!("{e:#}");
+        StatusCode::BAD_GATEWAY
+    })?;
+
+    let state = generate_key();
+    let url = client.authorize_url(&metadata, &state).map_err(|e| {
+        error!("{e:#}");
+        StatusCode::BAD_GATEWAY
+    })?;
+
+    let settings = &app_state.settings.current().auth;
+    Ok((
+        [(
+            SET_COOKIE,
+            cookie(STATE_COOKIE, &state, "/auth/oidc", 600, settings),
+        )],
+        Redirect::to(url.as_str()),
+    ))
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct Callback {
+    pub code: String,
+    pub state: String,
+}
+
+/// Return from the OIDC provider.
+///
+/// Logs in the user matching the provider's subject, or their email on the
+/// first login when the provider reports it verified. Users have to exist
+/// already so team membership stays managed.
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/callback",
+    params(Callback),
+    responses(
+        (status = 303, description = "Logged in, redirect to `/`"),
+        (status = 400, description = "The state doesn't match the login's"),
+        (status = 401, description = "The provider rejected the code"),
+        (status = 403, description = "No user matches the provider's"),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn oidc_callback(
+    State(app_state): State<Arc<AppState>>,
+    Query(callback): Query<Callback>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, StatusCode> {
+    let client = oidc_client(&app_state)?;
+    if auth::cookie(&headers, STATE_COOKIE) != Some(callback.state.as_str()) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let info = async {
+        let metadata = client.discover().await?;
+        client.user_info(&metadata, &callback.code).await
+    }
+    .await
+    .map_err(|e| {
+        error!("OIDC login failed: {e:#}");
+        StatusCode::UNAUTHORIZED
+    })?;
+
+    let db = app_state.db.as_ref();
+    let by_subject = Users::find()
+        .filter(users::Column::OidcSubject.eq(&info.sub))
+        .one(db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Linking by email trusts the provider's word that the address is the
+    // caller's, so a missing `email_verified` claim is not enough
+    let user = match (by_subject, &info.email) {
+        (Some(user), _) => user,
+        (None, Some(email)) if info.email_verified == Some(true) => {
+            let user = Users::find()
+                .filter(users::Column::Email.eq(email.to_lowercase()))
+                .filter(users::Column::OidcSubject.is_null())
+                .one(db)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                .ok_or(StatusCode::FORBIDDEN)?;
+
+            let mut user: users::ActiveModel = user.into();
+            user.oidc_subject = Set(Some(info.sub.clone()));
+            user.update(db)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        }
+        (None, _) => return Err(StatusCode::FORBIDDEN),
+    };
+
+    let session = start_session(&app_state, user.id).await?;
+    let settings = &app_state.settings.current().auth;
+    Ok((
+        AppendHeaders([
+            (SET_COOKIE, session),
+            (
+                SET_COOKIE,
+                cookie(STATE_COOKIE, "", "/auth/oidc", 0, settings),
+            ),
+        ]),
+        Redirect::to("/"),
+    ))
+}
@@ -0,0 +1,222 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter, QueryOrder,
+    TransactionTrait,
+};
+use std::sync::Arc;
+
+use crate::{
+    analysis::budgets::Budget,
+    audit::{self, Actor},
+    auth::Principal,
+    entities::{
+        budgets::{self, Entity as Budgets},
+        sea_orm_active_enums::{AuditAction, Role},
+    },
+    routes::access::{authorize_active_site, authorize_site},
+    AppState,
+};
+
+/// Budgets of a site.
+#[utoipa::path(
+    get,
+    path = "/sites/{site_id}/budgets",
+    responses(
+        (status = 200, description = "OK", body = Vec<budgets::Model>),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn get_site_budgets(
+    Path(site_id): Path<i32>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+) -> Result<Json<Vec<budgets::Model>>, StatusCode> {
+    let db = app_state.db.as_ref();
+    authorize_active_site(db, &principal, site_id, Role::Viewer).await?;
+
+    let budgets = Budgets::find()
+        .filter(budgets::Column::SiteId.eq(site_id))
+        .order_by_asc(budgets::Column::Id)
+        .all(db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(budgets))
+}
+
+/// Add a budget to a site.
+#[utoipa::path(
+    post,
+    path = "/sites/{site_id}/budgets",
+    responses(
+        (status = 200, description = "OK", body = budgets::Model),
+        (status = 404, description = "Not found"),
+        (status = 422, description = "Invalid payload"),
+    )
+)]
+pub async fn create_budget_handler(
+    Path(site_id): Path<i32>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+    actor: Actor,
+    Json(payload): Json<Budget>,
+) -> Result<Json<budgets::Model>, StatusCode> {
+    if !payload.is_valid() {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let txn = app_state
+        .db
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    authorize_active_site(&txn, &principal, site_id, Role::Editor).await?;
+
+    let budget = budgets::ActiveModel {
+        site_id: Set(site_id),
+        pattern: Set(payload.pattern.clone()),
+        metric: Set(payload.metric),
+        min: Set(payload.min),
+        max: Set(payload.max),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    audit::record(
+        &txn,
+        site_id,
+        AuditAction::BudgetAdded,
+        &actor,
+        audit::field_change("budget", None::<Budget>, &payload),
+    )
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    txn.commit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(budget))
+}
+
+/// Update a budget.
+#[utoipa::path(
+    put,
+    path = "/budgets/{budget_id}",
+    responses(
+        (status = 200, description = "OK", body = budgets::Model),
+        (status = 404, description = "Not found"),
+        (status = 422, description = "Invalid payload"),
+    )
+)]
+pub async fn update_budget(
+    Path(budget_id): Path<i32>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+    actor: Actor,
+    Json(payload): Json<Budget>,
+) -> Result<Json<budgets::Model>, StatusCode> {
+    if !payload.is_valid() {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let txn = app_state
+        .db
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let budget: budgets::Model = Budgets::find_by_id(budget_id)
+        .one(&txn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    authorize_site(&txn, &principal, budget.site_id, Role::Editor).await?;
+
+    let previous = Budget::from(&budget);
+
+    let mut budget: budgets::ActiveModel = budget.into();
+    budget.pattern = Set(payload.pattern.clone());
+    budget.metric = Set(payload.metric);
+    budget.min = Set(payload.min);
+    budget.max = Set(payload.max);
+
+    let budget: budgets::Model = budget
+        .update(&txn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    audit::record(
+        &txn,
+        budget.site_id,
+        AuditAction::BudgetUpdated,
+        &actor,
+        audit::field_change("budget", &previous, &payload),
+    )
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    txn.commit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(budget))
+}
+
+/// Delete a budget.
+#[utoipa::path(
+    delete,
+    path = "/budgets/{budget_id}",
+    responses(
+        (status = 204, description = "No content"),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn delete_budget(
+    Path(budget_id): Path<i32>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+    actor: Actor,
+) -> Result<impl IntoResponse, StatusCode> {
+    let txn = app_state
+        .db
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let budget: budgets::Model = Budgets::find_by_id(budget_id)
+        .one(&txn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    authorize_site(&txn, &principal, budget.site_id, Role::Editor).await?;
+
+    Budgets::delete_by_id(budget_id)
+        .exec(&txn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    audit::record(
+        &txn,
+        budget.site_id,
+        AuditAction::BudgetRemoved,
+        &actor,
+        audit::field_change("budget", Budget::from(&budget), None::<Budget>),
+    )
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    txn.commit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,66 @@
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Serialize;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+use crate::{auth::Admin, reload::ReloadStatus, AppState};
+
+#[derive(Serialize, ToSchema)]
+pub struct ConfigStatus {
+    #[serde(flatten)]
+    pub reload: ReloadStatus,
+    pub patterns: Vec<String>,
+    pub ignore_paths: Vec<String>,
+    pub sites: Vec<String>,
+}
+
+impl ConfigStatus {
+    fn from_state(app_state: &AppState) -> Self {
+        let settings = app_state.settings.current();
+
+        ConfigStatus {
+            reload: app_state.settings.status(),
+            patterns: settings.patterns.clone(),
+            ignore_paths: settings.ignore_paths.clone(),
+            sites: settings.sites.clone(),
+        }
+    }
+}
+
+/// Active sampling settings and reload status.
+#[utoipa::path(
+    get,
+    path = "/config",
+    responses(
+        (status = 200, description = "OK", body = ConfigStatus),
+    )
+)]
+pub async fn get_config(
+    State(app_state): State<Arc<AppState>>,
+    _admin: Admin,
+) -> Json<ConfigStatus> {
+    Json(ConfigStatus::from_state(&app_state))
+}
+
+/// Reload the config file.
+///
+/// Reloads without waiting for the watcher, 422 when the file is invalid.
+#[utoipa::path(
+    post,
+    path = "/config/reload",
+    responses(
+        (status = 200, description = "OK", body = ConfigStatus),
+        (status = 422, description = "The file is invalid, the previous settings stay active", body = ConfigStatus),
+    )
+)]
+pub async fn reload_config(
+    State(app_state): State<Arc<AppState>>,
+    _admin: Admin,
+) -> (StatusCode, Json<ConfigStatus>) {
+    let status = match app_state.settings.reload().await {
+        Ok(()) => StatusCode::OK,
+        Err(_) => StatusCode::UNPROCESSABLE_ENTITY,
+    };
+
+    (status, Json(ConfigStatus::from_state(&app_state)))
+}
@@ -0,0 +1,226 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{NaiveDate, Utc};
+use lettre::Address;
+use sea_orm::{ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter, TransactionTrait};
+use serde::Deserialize;
+use std::{collections::BTreeSet, sync::Arc};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    analysis::digest::Digest,
+    auth::Principal,
+    digest::{group_recipients, load_digest, render_html, render_text, send_digest},
+    entities::{
+        digest_recipients::{self, Entity as DigestRecipients},
+        sea_orm_active_enums::Role,
+    },
+    routes::access::authorize_group,
+    AppState,
+};
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DigestFormat {
+    #[default]
+    Json,
+    Text,
+    Html,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct DigestParams {
+    /// Last day of the period, exclusive, defaults to today.
+    pub until: Option<NaiveDate>,
+    #[serde(default)]
+    pub format: DigestFormat,
+}
+
+/// Preview a group's digest.
+#[utoipa::path(
+    get,
+    path = "/groups/{group_id}/digest",
+    params(DigestParams),
+    responses(
+        (
+            status = 200,
+            description = "OK",
+            content(
+                (Digest = "application/json"),
+                (String = "text/plain"),
+                (String = "text/html")
+            )
+        ),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn get_group_digest(
+    Path(group_id): Path<i32>,
+    Query(params): Query<DigestParams>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+) -> Result<Response, StatusCode> {
+    let digest = build_group_digest(&app_state, &principal, group_id, params.until).await?;
+
+    let response = match params.format {
+        DigestFormat::Json => Json(digest).into_response(),
+        DigestFormat::Text => (
+            [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+            render_text(&digest),
+        )
+            .into_response(),
+        DigestFormat::Html => (
+            [(header::CONTENT_TYPE, "text/html; charset=utf-8")],
+            render_html(&digest),
+        )
+            .into_response(),
+    };
+
+    Ok(response)
+}
+
+/// Send a group's digest.
+///
+/// Sends the digest to the group's recipients now, outside the weekly schedule.
+#[utoipa::path(
+    post,
+    path = "/groups/{group_id}/digest",
+    params(DigestParams),
+    responses(
+        (status = 200, description = "OK", body = Digest),
+        (status = 404, description = "Not found"),
+        (status = 422, description = "The group has no recipients"),
+        (status = 502, description = "The mail server refused the digest"),
+        (status = 503, description = "Mail isn't configured"),
+    )
+)]
+pub async fn send_group_digest(
+    Path(group_id): Path<i32>,
+    Query(params): Query<DigestParams>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+) -> Result<Json<Digest>, StatusCode> {
+    authorize_group(app_state.db.as_ref(), &principal, group_id, Role::Editor).await?;
+    let mailer = app_state
+        .mailer
+        .as_ref()
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+
+    let digest = build_group_digest(&app_state, &principal, group_id, params.until).await?;
+
+    let recipients = group_recipients(app_state.db.as_ref(), group_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if recipients.is_empty() {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    send_digest(mailer, &recipients, &digest)
+        .await
+        .map_err(|_| StatusCode::BAD_GATEWAY)?;
+
+    Ok(Json(digest))
+}
+
+/// Digest recipients.
+#[utoipa::path(
+    get,
+    path = "/groups/{group_id}/digest/recipients",
+    responses(
+        (status = 200, description = "OK", body = Vec<String>),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn get_digest_recipients(
+    Path(group_id): Path<i32>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+) -> Result<Json<Vec<String>>, StatusCode> {
+    let db = app_state.db.as_ref();
+    authorize_group(db, &principal, group_id, Role::Viewer).await?;
+
+    let recipients = group_recipients(db, group_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(recipients))
+}
+
+/// Replaces the group's recipient list.
+#[utoipa::path(
+    put,
+    path = "/groups/{group_id}/digest/recipients",
+    responses(
+        (status = 200, description = "OK", body = Vec<String>),
+        (status = 404, description = "Not found"),
+        (status = 422, description = "An address is invalid"),
+    )
+)]
+pub async fn set_digest_recipients(
+    Path(group_id): Path<i32>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+    Json(payload): Json<Vec<String>>,
+) -> Result<Json<Vec<String>>, StatusCode> {
+    let db = app_state.db.as_ref();
+    authorize_group(db, &principal, group_id, Role::Editor).await?;
+
+    let mut recipients = BTreeSet::new();
+    for email in payload {
+        let address: Address = email
+            .trim()
+            .parse()
+            .map_err(|_| StatusCode::UNPROCESSABLE_ENTITY)?;
+        recipients.insert(address.to_string());
+    }
+
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    DigestRecipients::delete_many()
+        .filter(digest_recipients::Column::GroupId.eq(group_id))
+        .exec(&txn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if !recipients.is_empty() {
+        DigestRecipients::insert_many(recipients.iter().map(|email| {
+            digest_recipients::ActiveModel {
+                group_id: Set(group_id),
+                email: Set(email.clone()),
+                ..Default::default()
+            }
+        }))
+        .exec(&txn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    txn.commit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(recipients.into_iter().collect()))
+}
+
+async fn build_group_digest(
+    app_state: &AppState,
+    principal: &Principal,
+    group_id: i32,
+    until: Option<NaiveDate>,
+) -> Result<Digest, StatusCode> {
+    let db = app_state.db.as_ref();
+    let group = authorize_group(db, principal, group_id, Role::Viewer).await?;
+    let until = until.unwrap_or_else(|| Utc::now().date_naive());
+
+    load_digest(db, &group, until)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
@@ -0,0 +1,573 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use sea_orm::{
+    sea_query::OnConflict, ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr,
+    DeleteResult, EntityTrait, ModelTrait, QueryFilter, QueryOrder, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, sync::Arc};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    audit::{self, Actor},
+    auth::Principal,
+    client::psi::CategoryScores,
+    entities::{
+        group_sites::{self, Entity as GroupSites},
+        groups::{self, Entity as Groups},
+        reports::{self, Entity as Reports},
+        runs::{self, Entity as Runs},
+        sea_orm_active_enums::{AuditAction, Role, RunStatus, Strategy},
+        sites::{self, Entity as Sites},
+    },
+    queue,
+    routes::access::{authorize_active_site, authorize_group, authorize_run, visible_groups},
+    AppState,
+};
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewGroup {
+    pub name: String,
+    #[serde(default)]
+    pub site_ids: Vec<i32>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct GroupWithSites {
+    #[serde(flatten)]
+    pub group: groups::Model,
+    pub sites: Vec<sites::Model>,
+}
+
+/// Create a group.
+///
+/// Groups belong to the teams of their sites, so only admins can create one
+/// without sites.
+#[utoipa::path(
+    post,
+    path = "/groups",
+    responses(
+        (status = 200, description = "OK", body = GroupWithSites),
+        (status = 422, description = "Invalid payload"),
+    )
+)]
+pub async fn create_group_handler(
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+    actor: Actor,
+    Json(payload): Json<NewGroup>,
+) -> Result<Json<GroupWithSites>, StatusCode> {
+    let db = app_state.db.as_ref();
+    if payload.site_ids.is_empty() && !principal.sees_every_team() {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    for site_id in &payload.site_ids {
+        authorize_member(db, &principal, *site_id).await?;
+    }
+
+    let new_group = groups::ActiveModel {
+        name: Set(payload.name),
+        ..Default::default()
+    };
+
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let group = new_group
+        .insert(&txn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    for site_id in payload.site_ids {
+        insert_membership(&txn, group.id, site_id, &actor).await?;
+    }
+
+    txn.commit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let sites = group
+        .find_related(Sites)
+        .filter(sites::Column::DeletedAt.is_null())
+        .all(db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(GroupWithSites { group, sites }))
+}
+
+/// Groups visible to the caller.
+#[utoipa::path(
+    get,
+    path = "/groups",
+    responses(
+        (status = 200, description = "OK", body = Vec<groups::Model>),
+    )
+)]
+pub async fn get_groups(
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+) -> Result<Json<Vec<groups::Model>>, StatusCode> {
+    let groups = visible_groups(app_state.db.as_ref(), &principal).await?;
+
+    Ok(Json(groups))
+}
+
+/// A group with its sites.
+#[utoipa::path(
+    get,
+    path = "/groups/{group_id}",
+    responses(
+        (status = 200, description = "OK", body = GroupWithSites),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn get_group(
+    Path(group_id): Path<i32>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+) -> Result<Json<GroupWithSites>, StatusCode> {
+    let db = app_state.db.as_ref();
+    let group = authorize_group(db, &principal, group_id, Role::Viewer).await?;
+
+    let sites = group
+        .find_related(Sites)
+        .filter(sites::Column::DeletedAt.is_null())
+        .all(db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(GroupWithSites { group, sites }))
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateGroup {
+    pub name: Option<String>,
+}
+
+/// Update a group.
+#[utoipa::path(
+    put,
+    path = "/groups/{group_id}",
+    responses(
+        (status = 200, description = "OK", body = groups::Model),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn update_group(
+    Path(group_id): Path<i32>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+    Json(payload): Json<UpdateGroup>,
+) -> Result<Json<groups::Model>, StatusCode> {
+    let db = app_state.db.as_ref();
+    let mut group: groups::ActiveModel = authorize_group(db, &principal, group_id, Role::Editor)
+        .await?
+        .into();
+
+    if let Some(name) = payload.name {
+        group.name = Set(name);
+    }
+
+    let group: groups::Model = group
+        .update(db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(group))
+}
+
+/// Delete a group.
+#[utoipa::path(
+    delete,
+    path = "/groups/{group_id}",
+    responses(
+        (status = 204, description = "No content"),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn delete_group(
+    Path(group_id): Path<i32>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+) -> Result<impl IntoResponse, StatusCode> {
+    let db = app_state.db.as_ref();
+    authorize_group(db, &principal, group_id, Role::Editor).await?;
+
+    let result: DeleteResult = Groups::delete_by_id(group_id)
+        .exec(db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Add a site to a group.
+#[utoipa::path(
+    put,
+    path = "/groups/{group_id}/sites/{site_id}",
+    responses(
+        (status = 204, description = "No content"),
+        (status = 404, description = "Not found"),
+        (status = 422, description = "The site doesn't exist or isn't visible to the caller"),
+    )
+)]
+pub async fn add_group_site(
+    Path((group_id, site_id)): Path<(i32, i32)>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+    actor: Actor,
+) -> Result<impl IntoResponse, StatusCode> {
+    let db = app_state.db.as_ref();
+    authorize_group(db, &principal, group_id, Role::Editor).await?;
+    authorize_member(db, &principal, site_id).await?;
+
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    insert_membership(&txn, group_id, site_id, &actor).await?;
+    txn.commit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Remove a site from a group.
+#[utoipa::path(
+    delete,
+    path = "/groups/{group_id}/sites/{site_id}",
+    responses(
+        (status = 204, description = "No content"),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn remove_group_site(
+    Path((group_id, site_id)): Path<(i32, i32)>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+    actor: Actor,
+) -> Result<impl IntoResponse, StatusCode> {
+    let txn = app_state
+        .db
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    authorize_group(&txn, &principal, group_id, Role::Editor).await?;
+
+    let result: DeleteResult = GroupSites::delete_by_id((group_id, site_id))
+        .exec(&txn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    audit::record(
+        &txn,
+        site_id,
+        AuditAction::GroupRemoved,
+        &actor,
+        audit::field_change("group_id", group_id, None::<i32>),
+    )
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    txn.commit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub const MAX_SAMPLES: i32 = 5;
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewRun {
+    #[serde(default)]
+    #[schema(default = "mobile")]
+    pub strategy: Strategy,
+    #[serde(default = "default_samples")]
+    #[schema(default = 1)]
+    pub samples: i32,
+}
+
+impl Default for NewRun {
+    fn default() -> Self {
+        NewRun {
+            strategy: Strategy::default(),
+            samples: default_samples(),
+        }
+    }
+}
+
+fn default_samples() -> i32 {
+    1
+}
+
+/// Start auditing a group.
+#[utoipa::path(
+    post,
+    path = "/groups/{group_id}/runs",
+    request_body(content = Option<NewRun>, description = "Defaults to one mobile sample"),
+    responses(
+        (status = 202, description = "Accepted", body = runs::Model),
+        (status = 404, description = "Not found"),
+        (status = 422, description = "Invalid payload or a group without active sites"),
+    )
+)]
+pub async fn create_group_run(
+    Path(group_id): Path<i32>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+    payload: Option<Json<NewRun>>,
+) -> Result<(StatusCode, Json<runs::Model>), StatusCode> {
+    let db = app_state.db.as_ref();
+    let group = authorize_group(db, &principal, group_id, Role::Editor).await?;
+    let payload = payload.map(|Json(payload)| payload).unwrap_or_default();
+
+    if !(1..=MAX_SAMPLES).contains(&payload.samples) {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let sites = group
+        .find_related(Sites)
+        .filter(sites::Column::DeletedAt.is_null())
+        .all(db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if sites.is_empty() {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    let txn = db
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let run = runs::ActiveModel {
+        group_id: Set(Some(group.id)),
+        status: Set(RunStatus::Pending),
+        strategy: Set(payload.strategy),
+        samples: Set(payload.samples),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    queue::enqueue_run(&txn, &run, &sites)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    txn.commit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((StatusCode::ACCEPTED, Json(run)))
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct RunFilter {
+    pub run_id: Option<i32>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SiteScores {
+    pub site_id: i32,
+    pub domain: String,
+    pub report_count: usize,
+    pub scores: CategoryScores,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct GroupScores {
+    pub run: runs::Model,
+    pub report_count: usize,
+    pub scores: CategoryScores,
+    pub sites: Vec<SiteScores>,
+}
+
+/// Latest scores of a group.
+#[utoipa::path(
+    get,
+    path = "/groups/{group_id}/scores",
+    params(RunFilter),
+    responses(
+        (status = 200, description = "OK", body = GroupScores),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn get_group_scores(
+    Path(group_id): Path<i32>,
+    Query(filter): Query<RunFilter>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+) -> Result<Json<GroupScores>, StatusCode> {
+    let db = app_state.db.as_ref();
+    let run = find_group_run(db, &principal, group_id, filter.run_id).await?;
+
+    let reports = run
+        .find_related(Reports)
+        .find_also_related(Sites)
+        .all(db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut by_site: BTreeMap<i32, (String, Vec<CategoryScores>)> = BTreeMap::new();
+    for (report, site) in &reports {
+        let domain = site
+            .as_ref()
+            .map(|site| site.domain.clone())
+            .unwrap_or_default();
+        by_site
+            .entry(report.site_id)
+            .or_insert_with(|| (domain, Vec::new()))
+            .1
+            .push(CategoryScores::from(report));
+    }
+
+    let sites: Vec<SiteScores> = by_site
+        .into_iter()
+        .map(|(site_id, (domain, scores))| SiteScores {
+            site_id,
+            domain,
+            report_count: scores.len(),
+            scores: CategoryScores::average(&scores),
+        })
+        .collect();
+
+    let all_scores: Vec<CategoryScores> = reports
+        .iter()
+        .map(|(report, _)| CategoryScores::from(report))
+        .collect();
+
+    Ok(Json(GroupScores {
+        run,
+        report_count: all_scores.len(),
+        scores: CategoryScores::average(&all_scores),
+        sites,
+    }))
+}
+
+/// Reports of a group's run.
+#[utoipa::path(
+    get,
+    path = "/groups/{group_id}/reports",
+    params(RunFilter),
+    responses(
+        (status = 200, description = "OK", body = Vec<reports::Model>),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn get_group_reports(
+    Path(group_id): Path<i32>,
+    Query(filter): Query<RunFilter>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+) -> Result<Json<Vec<reports::Model>>, StatusCode> {
+    let db = app_state.db.as_ref();
+    let run = find_group_run(db, &principal, group_id, filter.run_id).await?;
+
+    let reports = run
+        .find_related(Reports)
+        .order_by_asc(reports::Column::SiteId)
+        .order_by_asc(reports::Column::Id)
+        .all(db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(reports))
+}
+
+// Uses the requested run, or the group's most recent one when none is given.
+// Sites may have left the group since, so the run is checked on its own too
+async fn find_group_run(
+    db: &sea_orm::DatabaseConnection,
+    principal: &Principal,
+    group_id: i32,
+    run_id: Option<i32>,
+) -> Result<runs::Model, StatusCode> {
+    authorize_group(db, principal, group_id, Role::Viewer).await?;
+
+    let mut query = Runs::find().filter(runs::Column::GroupId.eq(group_id));
+    if let Some(run_id) = run_id {
+        query = query.filter(runs::Column::Id.eq(run_id));
+    }
+
+    let run = query
+        .order_by_desc(runs::Column::Id)
+        .one(db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    authorize_run(db, principal, run.id, Role::Viewer).await
+}
+
+// Sites joining a group must be live and editable by the caller, unknown or
+// hidden ones are a 422 like any other invalid payload
+async fn authorize_member<C: ConnectionTrait>(
+    db: &C,
+    principal: &Principal,
+    site_id: i32,
+) -> Result<(), StatusCode> {
+    authorize_active_site(db, principal, site_id, Role::Editor)
+        .await
+        .map(|_| ())
+        .map_err(|status| match status {
+            StatusCode::NOT_FOUND => StatusCode::UNPROCESSABLE_ENTITY,
+            status => status,
+        })
+}
+
+// Inserts the membership and its audit row unless the site is already a member
+async fn insert_membership<C: ConnectionTrait>(
+    db: &C,
+    group_id: i32,
+    site_id: i32,
+    actor: &Actor,
+) -> Result<(), StatusCode> {
+    let membership = group_sites::ActiveModel {
+        group_id: Set(group_id),
+        site_id: Set(site_id),
+    };
+
+    let inserted = GroupSites::insert(membership)
+        .on_conflict(
+            OnConflict::columns([group_sites::Column::GroupId, group_sites::Column::SiteId])
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec(db)
+        .await;
+
+    match inserted {
+        Ok(_) => audit::record(
+            db,
+            site_id,
+            AuditAction::GroupAdded,
+            actor,
+            audit::field_change("group_id", None::<i32>, group_id),
+        )
+        .await
+        .map(|_| ())
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR),
+        Err(DbErr::RecordNotInserted) => Ok(()),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
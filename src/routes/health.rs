@@ -0,0 +1,115 @@
+use axum::{
+    extract::State,
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::error;
+use utoipa::ToSchema;
+
+use crate::{
+    client::provider::provider_from_settings,
+    entities::{
+        runs::{self, Entity as Runs},
+        sea_orm_active_enums::RunStatus,
+    },
+    metrics::metrics,
+    queue, schema, AppState,
+};
+
+/// The process is up, for liveness probes.
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    responses(
+        (status = 200, description = "OK", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn get_health() -> &'static str {
+    "ok"
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Readiness {
+    pub database: bool,
+    pub migrations: bool,
+    pub audit: bool,
+    pub shutting_down: bool,
+}
+
+/// Database, migrations and audit backend are ready.
+///
+/// 503 until the database is reachable, its schema is current and the audit
+/// backend is configured, and again once shutdown starts so load balancers
+/// stop sending requests while it drains.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    responses(
+        (status = 200, description = "OK", body = Readiness),
+        (status = 503, description = "Not ready or shutting down", body = Readiness),
+    )
+)]
+pub async fn get_readiness(
+    State(app_state): State<Arc<AppState>>,
+) -> (StatusCode, Json<Readiness>) {
+    let db = app_state.db.as_ref();
+    let database = db.ping().await.is_ok();
+    let migrations = database
+        && schema::status(db)
+            .await
+            .is_ok_and(|status| status.is_current());
+    let audit = provider_from_settings(&app_state.settings.current().audit).is_ok();
+    let shutting_down = app_state.shutdown.is_shutting_down();
+
+    let status = if database && migrations && audit && !shutting_down {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status,
+        Json(Readiness {
+            database,
+            migrations,
+            audit,
+            shutting_down,
+        }),
+    )
+}
+
+/// Prometheus metrics.
+///
+/// Process metrics are served even when the database is down, the gauges read
+/// from it then keep their last value.
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses(
+        (status = 200, description = "OK", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn get_metrics(State(app_state): State<Arc<AppState>>) -> impl IntoResponse {
+    let db = app_state.db.as_ref();
+    let metrics = metrics();
+
+    // Jobs and runs are shared by every instance, so these come from the database
+    match queue::depth(db).await {
+        Ok(queued) => metrics.queue_depth.set(queued as i64),
+        Err(e) => error!("Unable to read the queue depth for metrics: {e}"),
+    }
+    let running = Runs::find()
+        .filter(runs::Column::Status.eq(RunStatus::Running))
+        .count(db)
+        .await;
+    match running {
+        Ok(running) => metrics.active_runs.set(running as i64),
+        Err(e) => error!("Unable to count active runs for metrics: {e}"),
+    }
+
+    ([(CONTENT_TYPE, prometheus::TEXT_FORMAT)], metrics.render())
+}
@@ -1,2 +1,13 @@
+pub mod access;
+pub mod auth;
+pub mod budgets;
+pub mod config;
+pub mod digests;
+pub mod groups;
+pub mod health;
 pub mod reports;
+pub mod runs;
 pub mod sites;
+pub mod status;
+pub mod trends;
+pub mod webhooks;
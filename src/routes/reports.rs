@@ -1,15 +1,127 @@
-use axum::response::sse::{Event, KeepAlive, Sse};
-use std::convert::Infallible;
-use tokio::sync::mpsc;
-use tokio_stream::wrappers::ReceiverStream;
+use axum::{
+    extract::State,
+    http::{header::LOCATION, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter, TransactionTrait,
+};
+use std::{collections::HashMap, sync::Arc};
+use tracing::error;
 
-use crate::client::processor::process_websites;
+use crate::{
+    audit::{self, Actor},
+    auth::Admin,
+    entities::{
+        runs,
+        sea_orm_active_enums::{AuditAction, RunStatus, Strategy},
+        sites::{self, Entity as Sites},
+    },
+    queue,
+    site_import::validate_domain,
+    AppState,
+};
 
-pub async fn sse_reports_handler() -> Sse<impl futures::Stream<Item = Result<Event, Infallible>>> {
-    let (tx, rx) = mpsc::channel(10);
+/// Start auditing the configured sites.
+///
+/// Queues a run of the configured `sites`, whose progress streams from the
+/// `Location` of its `/runs/{run_id}/events`. Sites that aren't stored yet are
+/// added without a team, so this is for admins.
+#[utoipa::path(
+    post,
+    path = "/reports",
+    responses(
+        (
+            status = 202,
+            description = "Accepted",
+            body = runs::Model,
+            headers(("Location" = String, description = "The run's event stream"))
+        ),
+        (status = 422, description = "No sites are configured, or one is invalid"),
+    )
+)]
+pub async fn create_configured_run(
+    State(app_state): State<Arc<AppState>>,
+    _admin: Admin,
+    actor: Actor,
+) -> Result<impl IntoResponse, StatusCode> {
+    let settings = app_state.settings.current();
+    if settings.sites.is_empty() {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
 
-    tokio::spawn(process_websites(tx));
+    let txn = app_state
+        .db
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let stream = ReceiverStream::new(rx);
-    Sse::new(stream).keep_alive(KeepAlive::default())
+    let mut stored: HashMap<String, sites::Model> = Sites::find()
+        .filter(sites::Column::DeletedAt.is_null())
+        .all(&txn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .into_iter()
+        .filter_map(|site| Some((validate_domain(&site.domain).ok()?, site)))
+        .collect();
+
+    let mut sites = Vec::new();
+    for site in &settings.sites {
+        let domain = validate_domain(site).map_err(|e| {
+            error!("Invalid configured site {site}: {e}");
+            StatusCode::UNPROCESSABLE_ENTITY
+        })?;
+
+        let site = match stored.get(&domain) {
+            Some(site) => site.clone(),
+            None => {
+                let site = sites::ActiveModel {
+                    domain: Set(domain.clone()),
+                    ..Default::default()
+                }
+                .insert(&txn)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+                audit::record(
+                    &txn,
+                    site.id,
+                    AuditAction::Created,
+                    &actor,
+                    audit::field_change("domain", None::<String>, &site.domain),
+                )
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+                stored.insert(domain, site.clone());
+                site
+            }
+        };
+
+        if !sites.iter().any(|added: &sites::Model| added.id == site.id) {
+            sites.push(site);
+        }
+    }
+
+    let run = runs::ActiveModel {
+        status: Set(RunStatus::Pending),
+        strategy: Set(Strategy::default()),
+        samples: Set(1),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    queue::enqueue_run(&txn, &run, &sites)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    txn.commit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let events = format!("/runs/{}/events", run.id);
+    Ok((StatusCode::ACCEPTED, [(LOCATION, events)], Json(run)))
 }
@@ -0,0 +1,433 @@
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Response,
+    },
+    Json,
+};
+use futures::{
+    stream::{self, BoxStream, StreamExt},
+    Stream,
+};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, JoinType, QueryFilter, QueryOrder,
+    QuerySelect, RelationTrait,
+};
+use serde::Deserialize;
+use std::{
+    collections::HashMap, convert::Infallible, future::pending, pin::pin, sync::Arc, time::Duration,
+};
+use tokio::{
+    sync::{
+        broadcast::{self, error::RecvError},
+        mpsc,
+    },
+    time::{sleep, timeout},
+};
+use tokio_util::io::ReaderStream;
+use tracing::error;
+use utoipa::IntoParams;
+
+use crate::{
+    auth::Principal,
+    entities::{
+        budget_results::{self, Entity as BudgetResults},
+        regressions::{self, Entity as Regressions},
+        reports::{self, Entity as Reports},
+        runs,
+        sea_orm_active_enums::{Role, RunStatus},
+        sites::{self, Entity as Sites},
+    },
+    events::{poll_run, RunCursor, RunEvent},
+    html_report::{self, load_run_report},
+    routes::access::authorize_run,
+    run_export::{self, ExportFormat, ExportRow, XlsxBytes, XlsxExport},
+    AppState,
+};
+
+const EXPORT_PAGE_SIZE: u64 = 200;
+
+/// A run.
+#[utoipa::path(
+    get,
+    path = "/runs/{run_id}",
+    responses(
+        (status = 200, description = "OK", body = runs::Model),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn get_run(
+    Path(run_id): Path<i32>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+) -> Result<Json<runs::Model>, StatusCode> {
+    let run = authorize_run(app_state.db.as_ref(), &principal, run_id, Role::Viewer).await?;
+
+    Ok(Json(run))
+}
+
+/// Budget results of a run.
+#[utoipa::path(
+    get,
+    path = "/runs/{run_id}/budgets",
+    params(BudgetFilter),
+    responses(
+        (status = 200, description = "OK", body = Vec<budget_results::Model>),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn get_run_budgets(
+    Path(run_id): Path<i32>,
+    Query(filter): Query<BudgetFilter>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+) -> Result<Json<Vec<budget_results::Model>>, StatusCode> {
+    let db = app_state.db.as_ref();
+
+    authorize_run(db, &principal, run_id, Role::Viewer).await?;
+
+    let mut query = BudgetResults::find().filter(budget_results::Column::RunId.eq(run_id));
+    if filter.failed {
+        query = query.filter(budget_results::Column::Passed.eq(false));
+    }
+
+    let results = query
+        .order_by_asc(budget_results::Column::Id)
+        .all(db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(results))
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct BudgetFilter {
+    #[serde(default)]
+    pub failed: bool,
+}
+
+/// Regressions found by a run.
+#[utoipa::path(
+    get,
+    path = "/runs/{run_id}/regressions",
+    responses(
+        (status = 200, description = "OK", body = Vec<regressions::Model>),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn get_run_regressions(
+    Path(run_id): Path<i32>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+) -> Result<Json<Vec<regressions::Model>>, StatusCode> {
+    let db = app_state.db.as_ref();
+
+    authorize_run(db, &principal, run_id, Role::Viewer).await?;
+
+    let regressions = Regressions::find()
+        .filter(regressions::Column::RunId.eq(run_id))
+        .order_by_asc(regressions::Column::SiteUrlId)
+        .order_by_asc(regressions::Column::Id)
+        .all(db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(regressions))
+}
+
+// How often open event streams look for new rows of their run when no event
+// of this process wakes them sooner
+const EVENTS_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+type EventStream = BoxStream<'static, Result<Event, Infallible>>;
+
+/// Progress of a run.
+///
+/// Streams the run's stored `report`, `budget` and `regression` events, then
+/// new ones as workers store them, and a final `completed` event. Ends with a
+/// `shutdown` event when the server stops first.
+#[utoipa::path(
+    get,
+    path = "/runs/{run_id}/events",
+    responses(
+        (
+            status = 200,
+            description = "Server-sent `report`, `budget`, `regression`, `completed` and `shutdown` events",
+            body = String,
+            content_type = "text/event-stream"
+        ),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn sse_run_events_handler(
+    Path(run_id): Path<i32>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+) -> Result<Sse<impl futures::Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    let run = authorize_run(app_state.db.as_ref(), &principal, run_id, Role::Viewer).await?;
+
+    let stream: EventStream = if matches!(run.status, RunStatus::Completed | RunStatus::Failed) {
+        stream::once(async move { Ok(RunEvent::Completed(run).to_sse()) }).boxed()
+    } else {
+        let events = app_state.events.as_ref().map(|events| events.subscribe());
+        run_event_stream(app_state.db.clone(), events, run_id)
+    };
+
+    let stream = app_state.shutdown.close_stream(stream);
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Polls the run's events until it finishes, the worker storing them may be
+/// in another process. With a worker in this process, `events` wakes the
+/// stream as soon as it stores something for the run.
+pub fn run_event_stream(
+    db: Arc<DatabaseConnection>,
+    events: Option<broadcast::Receiver<RunEvent>>,
+    run_id: i32,
+) -> EventStream {
+    stream::unfold(
+        (db, events, RunCursor::default(), true),
+        move |(db, mut events, mut cursor, first)| async move {
+            if cursor.is_finished() {
+                return None;
+            }
+            if !first {
+                next_change(events.as_mut(), run_id).await;
+            }
+
+            let stored = match poll_run(db.as_ref(), run_id, &mut cursor).await {
+                Ok(stored) => stored,
+                Err(e) => {
+                    error!("Failed to poll events of run {run_id}: {e}");
+                    return None;
+                }
+            };
+            let sse = stored
+                .iter()
+                .map(|event| Ok(event.to_sse()))
+                .collect::<Vec<_>>();
+
+            Some((stream::iter(sse), (db, events, cursor, false)))
+        },
+    )
+    .flatten()
+    .boxed()
+}
+
+// Waits for an event of the run or the poll interval, whichever comes first
+async fn next_change(events: Option<&mut broadcast::Receiver<RunEvent>>, run_id: i32) {
+    let Some(events) = events else {
+        sleep(EVENTS_POLL_INTERVAL).await;
+        return;
+    };
+
+    let _ = timeout(EVENTS_POLL_INTERVAL, async {
+        loop {
+            match events.recv().await {
+                Ok(event) if event.run_id() != run_id => continue,
+                // The worker stopped, only the interval is left
+                Err(RecvError::Closed) => pending::<()>().await,
+                // A lagging receiver may have missed the run's events
+                Ok(_) | Err(RecvError::Lagged(_)) => return,
+            }
+        }
+    })
+    .await;
+}
+
+/// HTML report of a run.
+#[utoipa::path(
+    get,
+    path = "/runs/{run_id}/report",
+    responses(
+        (status = 200, description = "OK", body = String, content_type = "text/html"),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn get_run_report(
+    Path(run_id): Path<i32>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+) -> Result<Html<String>, StatusCode> {
+    let db = app_state.db.as_ref();
+
+    let run = authorize_run(db, &principal, run_id, Role::Viewer).await?;
+
+    let report = load_run_report(db, &run, None)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Html(html_report::render(&report)))
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ExportParams {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// Export a run as CSV, XLSX or JSON.
+///
+/// Exports one row per report, reading a page of reports at a time. CSV and
+/// JSON are streamed as each page is encoded, XLSX once every page has been
+/// written to the workbook's temporary file.
+#[utoipa::path(
+    get,
+    path = "/runs/{run_id}/export",
+    params(ExportParams),
+    responses(
+        (
+            status = 200,
+            description = "One row per report",
+            content(
+                (String = "text/csv"),
+                (Vec<ExportRow> = "application/json"),
+                (
+                    XlsxBytes =
+                        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+                )
+            )
+        ),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn export_run(
+    Path(run_id): Path<i32>,
+    Query(params): Query<ExportParams>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+) -> Result<Response, StatusCode> {
+    let db = app_state.db.clone();
+
+    authorize_run(db.as_ref(), &principal, run_id, Role::Viewer).await?;
+
+    let domains: HashMap<i32, String> = Sites::find()
+        .join(JoinType::InnerJoin, sites::Relation::Reports.def())
+        .filter(reports::Column::RunId.eq(run_id))
+        .distinct()
+        .all(db.as_ref())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .into_iter()
+        .map(|site| (site.id, site.domain))
+        .collect();
+
+    let format = params.format;
+    let pages = export_pages(db, run_id, Arc::new(domains));
+
+    let body = match format {
+        ExportFormat::Csv => Body::from_stream(pages.map(|page| {
+            let (rows, first) = page?;
+            run_export::csv_chunk(&rows, first)
+        })),
+        ExportFormat::Json => Body::from_stream(
+            stream::once(async { Ok("[".to_string()) })
+                .chain(pages.map(|page| {
+                    let (rows, first) = page?;
+                    run_export::json_chunk(&rows, first)
+                }))
+                .chain(stream::once(async { Ok("]".to_string()) })),
+        ),
+        ExportFormat::Xlsx => {
+            let workbook = xlsx_export(pages).await.map_err(|e| {
+                error!("Unable to export run {run_id} as XLSX: {e:#}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+
+            Body::from_stream(ReaderStream::new(tokio::fs::File::from_std(workbook)))
+        }
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"run-{run_id}.{}\"",
+                    format.extension()
+                ),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+// Hands each page to a blocking task filling the constant memory worksheet, so
+// no more than one page of rows is held at once
+async fn xlsx_export(
+    pages: impl Stream<Item = anyhow::Result<(Vec<ExportRow>, bool)>>,
+) -> anyhow::Result<std::fs::File> {
+    let (sender, mut receiver) = mpsc::channel::<Vec<ExportRow>>(1);
+    let writer = tokio::task::spawn_blocking(move || {
+        let mut export = XlsxExport::new()?;
+        while let Some(rows) = receiver.blocking_recv() {
+            export.write_rows(&rows)?;
+        }
+        export.finish_to_file()
+    });
+
+    let mut pages = pin!(pages);
+    while let Some(page) = pages.next().await {
+        let (rows, _) = page?;
+        // The writer only hangs up after failing, its error is returned below
+        if sender.send(rows).await.is_err() {
+            break;
+        }
+    }
+    drop(sender);
+
+    writer.await?
+}
+
+// Pages through the run's reports by id, flagging the first page
+fn export_pages(
+    db: Arc<DatabaseConnection>,
+    run_id: i32,
+    domains: Arc<HashMap<i32, String>>,
+) -> impl Stream<Item = anyhow::Result<(Vec<ExportRow>, bool)>> + Send + 'static {
+    stream::unfold(Some((0, true)), move |state| {
+        let db = db.clone();
+        let domains = domains.clone();
+
+        async move {
+            let (after, first) = state?;
+
+            let reports: Result<Vec<reports::Model>, DbErr> = Reports::find()
+                .filter(reports::Column::RunId.eq(run_id))
+                .filter(reports::Column::Id.gt(after))
+                .order_by_asc(reports::Column::Id)
+                .limit(EXPORT_PAGE_SIZE)
+                .all(db.as_ref())
+                .await;
+
+            let reports = match reports {
+                Ok(reports) => reports,
+                Err(e) => return Some((Err(e.into()), None)),
+            };
+
+            if reports.is_empty() && !first {
+                return None;
+            }
+
+            let next = match reports.last() {
+                Some(last) if reports.len() as u64 == EXPORT_PAGE_SIZE => Some((last.id, false)),
+                _ => None,
+            };
+
+            let rows = reports
+                .iter()
+                .map(|report| {
+                    let domain = domains.get(&report.site_id).map(String::as_str);
+                    ExportRow::new(domain.unwrap_or_default(), report)
+                })
+                .collect();
+
+            Some((Ok((rows, first)), next))
+        }
+    })
+}
@@ -1,44 +1,120 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{Html, IntoResponse},
     Json,
 };
-use sea_orm::{ActiveModelTrait, ActiveValue::Set, DeleteResult, EntityTrait};
-use serde::Deserialize;
-use std::sync::Arc;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, DeleteResult, EntityTrait,
+    QueryFilter, QueryOrder, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, sync::Arc};
+use tracing::error;
+use utoipa::{IntoParams, ToSchema};
 
 use crate::{
-    entities::sites::{self, Entity as Sites},
+    audit::{self, Actor},
+    auth::Principal,
+    entities::{
+        sea_orm_active_enums::{AuditAction, Role},
+        site_audit_log::{self, Entity as SiteAuditLog},
+        sites::{self, Entity as Sites},
+        teams::Entity as Teams,
+    },
+    html_report::{self, latest_site_run, load_run_report},
+    routes::access::{authorize_active_site, authorize_site, check_site_role},
+    site_import::{self, ImportRow, RowStatus, SiteFormat},
     AppState,
 };
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct NewSite {
     pub domain: String,
+    /// Owning team, only admins can create sites without one.
+    pub team_id: Option<i32>,
 }
 
+/// Create a site.
+#[utoipa::path(
+    post,
+    path = "/sites",
+    responses(
+        (status = 200, description = "OK", body = sites::Model),
+        (status = 422, description = "Invalid payload"),
+    )
+)]
 pub async fn create_site_handler(
     State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+    actor: Actor,
     Json(payload): Json<NewSite>,
 ) -> Result<Json<sites::Model>, StatusCode> {
+    check_team_role(app_state.db.as_ref(), &principal, payload.team_id).await?;
+
+    let txn = app_state
+        .db
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
     let new_site = sites::ActiveModel {
         domain: sea_orm::ActiveValue::Set(payload.domain),
+        team_id: Set(payload.team_id),
         ..Default::default()
     };
 
     let saved_site = new_site
-        .insert(app_state.db.as_ref())
+        .insert(&txn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    audit::record(
+        &txn,
+        saved_site.id,
+        AuditAction::Created,
+        &actor,
+        audit::field_change("domain", None::<String>, &saved_site.domain),
+    )
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    txn.commit()
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     Ok(Json(saved_site))
 }
 
+#[derive(Deserialize, IntoParams)]
+pub struct SiteFilter {
+    #[serde(default)]
+    pub include_deleted: bool,
+}
+
+/// Sites visible to the caller.
+#[utoipa::path(
+    get,
+    path = "/sites",
+    params(SiteFilter),
+    responses(
+        (status = 200, description = "OK", body = Vec<sites::Model>),
+    )
+)]
 pub async fn get_sites(
+    Query(filter): Query<SiteFilter>,
     State(app_state): State<Arc<AppState>>,
+    principal: Principal,
 ) -> Result<Json<Vec<sites::Model>>, StatusCode> {
-    let sites: Vec<sites::Model> = Sites::find()
+    let mut query = Sites::find();
+    if let Some(teams) = principal.visible_teams() {
+        query = query.filter(sites::Column::TeamId.is_in(teams));
+    }
+    if !filter.include_deleted {
+        query = query.filter(sites::Column::DeletedAt.is_null());
+    }
+
+    let sites: Vec<sites::Model> = query
         .all(app_state.db.as_ref())
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -46,34 +122,55 @@ pub async fn get_sites(
     Ok(Json(sites))
 }
 
+/// A site.
+#[utoipa::path(
+    get,
+    path = "/sites/{site_id}",
+    responses(
+        (status = 200, description = "OK", body = sites::Model),
+        (status = 404, description = "Not found"),
+    )
+)]
 pub async fn get_site(
     Path(site_id): Path<i32>,
     State(app_state): State<Arc<AppState>>,
+    principal: Principal,
 ) -> Result<Json<sites::Model>, StatusCode> {
-    let site: sites::Model = Sites::find_by_id(site_id)
-        .one(app_state.db.as_ref())
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::NOT_FOUND)?;
+    let site =
+        authorize_active_site(app_state.db.as_ref(), &principal, site_id, Role::Viewer).await?;
 
     Ok(Json(site))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct UpdateSite {
     pub domain: Option<String>,
 }
 
+/// Update a site.
+#[utoipa::path(
+    put,
+    path = "/sites/{site_id}",
+    responses(
+        (status = 200, description = "OK", body = sites::Model),
+        (status = 404, description = "Not found"),
+    )
+)]
 pub async fn update_site(
     Path(site_id): Path<i32>,
     State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+    actor: Actor,
     Json(payload): Json<UpdateSite>,
 ) -> Result<Json<sites::Model>, StatusCode> {
-    let site: sites::Model = Sites::find_by_id(site_id)
-        .one(app_state.db.as_ref())
+    let txn = app_state
+        .db
+        .begin()
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let site = authorize_active_site(&txn, &principal, site_id, Role::Editor).await?;
+    let previous_domain = site.domain.clone();
 
     let mut site: sites::ActiveModel = site.into();
 
@@ -82,25 +179,412 @@ pub async fn update_site(
     }
 
     let site: sites::Model = site
-        .update(app_state.db.as_ref())
+        .update(&txn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if site.domain != previous_domain {
+        audit::record(
+            &txn,
+            site.id,
+            AuditAction::Updated,
+            &actor,
+            audit::field_change("domain", &previous_domain, &site.domain),
+        )
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    txn.commit()
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     Ok(Json(site))
 }
 
+/// Soft delete a site.
+///
+/// The site and its report history are kept until purged.
+#[utoipa::path(
+    delete,
+    path = "/sites/{site_id}",
+    responses(
+        (status = 204, description = "No content"),
+        (status = 404, description = "Not found"),
+    )
+)]
 pub async fn delete_site(
     Path(site_id): Path<i32>,
     State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+    actor: Actor,
 ) -> Result<impl IntoResponse, StatusCode> {
+    authorize_site(app_state.db.as_ref(), &principal, site_id, Role::Editor).await?;
+    audit::set_deleted_at(app_state.db.as_ref(), site_id, &actor, true)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Restore a soft deleted site.
+#[utoipa::path(
+    post,
+    path = "/sites/{site_id}/restore",
+    responses(
+        (status = 200, description = "OK", body = sites::Model),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn restore_site(
+    Path(site_id): Path<i32>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+    actor: Actor,
+) -> Result<Json<sites::Model>, StatusCode> {
+    authorize_site(app_state.db.as_ref(), &principal, site_id, Role::Editor).await?;
+    let site = audit::set_deleted_at(app_state.db.as_ref(), site_id, &actor, false)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(site))
+}
+
+/// Permanently remove a soft deleted site.
+///
+/// Its urls and reports are removed with it.
+#[utoipa::path(
+    delete,
+    path = "/sites/{site_id}/purge",
+    responses(
+        (status = 204, description = "No content"),
+        (status = 404, description = "Not found"),
+        (status = 409, description = "The site isn't soft deleted"),
+    )
+)]
+pub async fn purge_site(
+    Path(site_id): Path<i32>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+    actor: Actor,
+) -> Result<impl IntoResponse, StatusCode> {
+    let txn = app_state
+        .db
+        .begin()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let site = authorize_site(&txn, &principal, site_id, Role::Admin).await?;
+
+    if site.deleted_at.is_none() {
+        return Err(StatusCode::CONFLICT);
+    }
+
     let result: DeleteResult = Sites::delete_by_id(site_id)
-        .exec(app_state.db.as_ref())
+        .exec(&txn)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    
+
     if result.rows_affected == 0 {
         return Err(StatusCode::NOT_FOUND);
     }
 
+    audit::record(
+        &txn,
+        site_id,
+        AuditAction::Purged,
+        &actor,
+        audit::field_change("domain", &site.domain, None::<String>),
+    )
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    txn.commit()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
     Ok(StatusCode::NO_CONTENT)
 }
+
+/// Changes made to a site.
+#[utoipa::path(
+    get,
+    path = "/sites/{site_id}/audit",
+    responses(
+        (status = 200, description = "OK", body = Vec<site_audit_log::Model>),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn get_site_audit_log(
+    Path(site_id): Path<i32>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+) -> Result<Json<Vec<site_audit_log::Model>>, StatusCode> {
+    // The log outlives purged sites, which only admins can still read
+    let site = Sites::find_by_id(site_id)
+        .one(app_state.db.as_ref())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    match &site {
+        Some(site) => check_site_role(&principal, site, Role::Viewer)?,
+        None if !principal.sees_every_team() => return Err(StatusCode::NOT_FOUND),
+        None => {}
+    }
+
+    let entries = SiteAuditLog::find()
+        .filter(site_audit_log::Column::SiteId.eq(site_id))
+        .order_by_asc(site_audit_log::Column::Id)
+        .all(app_state.db.as_ref())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Without a site or a log the id never existed
+    if site.is_none() && entries.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(entries))
+}
+
+/// HTML report of the site's most recent run.
+#[utoipa::path(
+    get,
+    path = "/sites/{site_id}/report",
+    responses(
+        (status = 200, description = "OK", body = String, content_type = "text/html"),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn get_site_report(
+    Path(site_id): Path<i32>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+) -> Result<Html<String>, StatusCode> {
+    let db = app_state.db.as_ref();
+    authorize_active_site(db, &principal, site_id, Role::Viewer).await?;
+
+    let run = latest_site_run(db, site_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let report = load_run_report(db, &run, Some(site_id))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Html(html_report::render(&report)))
+}
+
+// New sites need editor on their team, or an admin to leave the team unset
+async fn check_team_role(
+    db: &DatabaseConnection,
+    principal: &Principal,
+    team_id: Option<i32>,
+) -> Result<(), StatusCode> {
+    let Some(team_id) = team_id else {
+        if principal.sees_every_team() {
+            return Ok(());
+        }
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    };
+
+    match principal.team_role(team_id) {
+        Some(role) if role >= Role::Editor => {}
+        Some(_) => return Err(StatusCode::FORBIDDEN),
+        None => return Err(StatusCode::UNPROCESSABLE_ENTITY),
+    }
+
+    Teams::find_by_id(team_id)
+        .one(db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::UNPROCESSABLE_ENTITY)?;
+
+    Ok(())
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ImportParams {
+    pub format: Option<SiteFormat>,
+    #[serde(default)]
+    pub dry_run: bool,
+    pub team_id: Option<i32>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ImportSummary {
+    pub dry_run: bool,
+    pub created: usize,
+    pub skipped: usize,
+    pub invalid: usize,
+    pub rows: Vec<ImportRow>,
+}
+
+/// Import sites from CSV, JSON or TOML.
+#[utoipa::path(
+    post,
+    path = "/sites/import",
+    params(ImportParams),
+    request_body(
+        description = "Sites in the `format` parameter, or the one of the `Content-Type`",
+        content(
+            (String = "text/csv"),
+            (Vec<site_import::CsvSite> = "application/json"),
+            (String = "application/toml")
+        )
+    ),
+    responses(
+        (status = 200, description = "OK", body = ImportSummary),
+        (status = 400, description = "The body can't be read in its format"),
+        (status = 422, description = "Invalid payload"),
+    )
+)]
+pub async fn import_sites(
+    Query(params): Query<ImportParams>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+    actor: Actor,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Json<ImportSummary>, StatusCode> {
+    check_team_role(app_state.db.as_ref(), &principal, params.team_id).await?;
+
+    let format = params
+        .format
+        .or_else(|| {
+            headers
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(SiteFormat::from_content_type)
+        })
+        .unwrap_or_default();
+
+    let rows = site_import::parse_sites(format, &body).map_err(|e| {
+        error!("Unable to parse site import: {e:#}");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let db = app_state.db.as_ref();
+
+    // Only the caller's live sites count, so an import neither reveals nor is
+    // blocked by domains another team owns or that were deleted
+    let mut query = Sites::find().filter(sites::Column::DeletedAt.is_null());
+    if let Some(teams) = principal.visible_teams() {
+        query = query.filter(sites::Column::TeamId.is_in(teams));
+    }
+
+    let existing: HashSet<String> = query
+        .all(db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .iter()
+        .filter_map(|site| site_import::validate_domain(&site.domain).ok())
+        .collect();
+
+    let rows = site_import::plan_import(rows, &existing, params.dry_run);
+
+    if !params.dry_run {
+        let txn = db
+            .begin()
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        for row in rows.iter().filter(|row| row.status == RowStatus::Created) {
+            let site = sites::ActiveModel {
+                domain: Set(row.domain.clone().unwrap_or_default()),
+                team_id: Set(params.team_id),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+            audit::record(
+                &txn,
+                site.id,
+                AuditAction::Created,
+                &actor,
+                audit::field_change("domain", None::<String>, &site.domain),
+            )
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        }
+
+        txn.commit()
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    let count = |statuses: &[RowStatus]| {
+        rows.iter()
+            .filter(|row| statuses.contains(&row.status))
+            .count()
+    };
+
+    Ok(Json(ImportSummary {
+        dry_run: params.dry_run,
+        created: count(&[RowStatus::Created, RowStatus::WouldCreate]),
+        skipped: count(&[RowStatus::Exists, RowStatus::Duplicate]),
+        invalid: count(&[RowStatus::Invalid]),
+        rows,
+    }))
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ExportParams {
+    pub format: Option<SiteFormat>,
+}
+
+/// Export the active sites.
+#[utoipa::path(
+    get,
+    path = "/sites/export",
+    params(ExportParams),
+    responses(
+        (
+            status = 200,
+            description = "OK",
+            content(
+                (String = "text/csv"),
+                (Vec<site_import::CsvSite> = "application/json"),
+                (String = "application/toml")
+            )
+        ),
+    )
+)]
+pub async fn export_sites(
+    Query(params): Query<ExportParams>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+) -> Result<impl IntoResponse, StatusCode> {
+    let format = params.format.unwrap_or_default();
+
+    let mut query = Sites::find().filter(sites::Column::DeletedAt.is_null());
+    if let Some(teams) = principal.visible_teams() {
+        query = query.filter(sites::Column::TeamId.is_in(teams));
+    }
+
+    let domains: Vec<String> = query
+        .all(app_state.db.as_ref())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .into_iter()
+        .map(|site| site.domain)
+        .collect();
+
+    let body = site_import::export_sites(format, domains)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"sites.{}\"", format.extension()),
+            ),
+        ],
+        body,
+    ))
+}
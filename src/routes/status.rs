@@ -0,0 +1,36 @@
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Serialize;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+use crate::{
+    schema::{self, SchemaStatus},
+    AppState,
+};
+
+#[derive(Serialize, ToSchema)]
+pub struct Status {
+    pub version: &'static str,
+    pub schema: SchemaStatus,
+}
+
+/// Version and database schema status.
+#[utoipa::path(
+    get,
+    path = "/status",
+    responses(
+        (status = 200, description = "OK", body = Status),
+    )
+)]
+pub async fn get_status(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Status>, StatusCode> {
+    let schema = schema::status(app_state.db.as_ref())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(Status {
+        version: env!("CARGO_PKG_VERSION"),
+        schema,
+    }))
+}
@@ -0,0 +1,120 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::NaiveDate;
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Select};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    analysis::trends::{build_trend, Bucket, ReportMetrics, TrendPoint},
+    auth::Principal,
+    entities::{
+        reports::{self, Entity as Reports},
+        sea_orm_active_enums::{Role, Strategy},
+        site_urls::Entity as SiteUrls,
+    },
+    routes::access::authorize_active_site,
+    AppState,
+};
+
+#[derive(Deserialize, IntoParams)]
+pub struct TrendParams {
+    #[serde(default)]
+    pub bucket: Bucket,
+    #[serde(default)]
+    pub strategy: Strategy,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Trend {
+    pub strategy: Strategy,
+    pub points: Vec<TrendPoint>,
+}
+
+/// Score and metric trends of a site.
+#[utoipa::path(
+    get,
+    path = "/sites/{site_id}/trends",
+    params(TrendParams),
+    responses(
+        (status = 200, description = "OK", body = Trend),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn get_site_trends(
+    Path(site_id): Path<i32>,
+    Query(params): Query<TrendParams>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+) -> Result<Json<Trend>, StatusCode> {
+    let db = app_state.db.as_ref();
+    authorize_active_site(db, &principal, site_id, Role::Viewer).await?;
+
+    let query = Reports::find().filter(reports::Column::SiteId.eq(site_id));
+
+    Ok(Json(load_trend(db, query, &params).await?))
+}
+
+/// Score and metric trends of a url.
+#[utoipa::path(
+    get,
+    path = "/urls/{url_id}/trends",
+    params(TrendParams),
+    responses(
+        (status = 200, description = "OK", body = Trend),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn get_url_trends(
+    Path(url_id): Path<i32>,
+    Query(params): Query<TrendParams>,
+    State(app_state): State<Arc<AppState>>,
+    principal: Principal,
+) -> Result<Json<Trend>, StatusCode> {
+    let db = app_state.db.as_ref();
+
+    let url = SiteUrls::find_by_id(url_id)
+        .one(db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    authorize_active_site(db, &principal, url.site_id, Role::Viewer).await?;
+
+    let query = Reports::find().filter(reports::Column::SiteUrlId.eq(url_id));
+
+    Ok(Json(load_trend(db, query, &params).await?))
+}
+
+async fn load_trend(
+    db: &DatabaseConnection,
+    mut query: Select<Reports>,
+    params: &TrendParams,
+) -> Result<Trend, StatusCode> {
+    query = query.filter(reports::Column::Strategy.eq(params.strategy));
+
+    if let Some(from) = params.from {
+        query = query.filter(reports::Column::CreatedAt.gte(from.and_time(Default::default())));
+    }
+
+    if let Some(to) = params.to.and_then(|to| to.succ_opt()) {
+        query = query.filter(reports::Column::CreatedAt.lt(to.and_time(Default::default())));
+    }
+
+    let reports = query
+        .order_by_asc(reports::Column::CreatedAt)
+        .into_partial_model::<ReportMetrics>()
+        .all(db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Trend {
+        strategy: params.strategy,
+        points: build_trend(&reports, params.bucket),
+    })
+}
@@ -0,0 +1,224 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use url::Url;
+use utoipa::ToSchema;
+
+use crate::{
+    auth::Admin,
+    entities::{
+        sea_orm_active_enums::WebhookFormat,
+        webhook_deliveries::{self, Entity as WebhookDeliveries},
+        webhooks::{self, Entity as Webhooks},
+    },
+    webhooks::WebhookEvent,
+    AppState,
+};
+
+const DELIVERY_LIMIT: u64 = 100;
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewWebhook {
+    url: String,
+    #[serde(default)]
+    #[schema(default = "generic")]
+    format: WebhookFormat,
+    secret: Option<String>,
+    /// Subscribed events, empty subscribes to all of them.
+    #[serde(default)]
+    events: Vec<WebhookEvent>,
+    #[serde(default = "default_active")]
+    #[schema(default = true)]
+    active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+fn validate_url(url: &str) -> Result<(), StatusCode> {
+    match Url::parse(url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => Ok(()),
+        _ => Err(StatusCode::UNPROCESSABLE_ENTITY),
+    }
+}
+
+/// Register a webhook.
+#[utoipa::path(
+    post,
+    path = "/webhooks",
+    responses(
+        (status = 200, description = "OK", body = webhooks::Model),
+        (status = 422, description = "Invalid payload"),
+    )
+)]
+pub async fn create_webhook_handler(
+    State(app_state): State<Arc<AppState>>,
+    _admin: Admin,
+    Json(payload): Json<NewWebhook>,
+) -> Result<Json<webhooks::Model>, StatusCode> {
+    validate_url(&payload.url)?;
+
+    let webhook = webhooks::ActiveModel {
+        url: Set(payload.url),
+        format: Set(payload.format),
+        secret: Set(payload.secret.filter(|secret| !secret.is_empty())),
+        events: Set(serde_json::json!(payload.events)),
+        active: Set(payload.active),
+        ..Default::default()
+    }
+    .insert(app_state.db.as_ref())
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(webhook))
+}
+
+/// Webhooks.
+#[utoipa::path(
+    get,
+    path = "/webhooks",
+    responses(
+        (status = 200, description = "OK", body = Vec<webhooks::Model>),
+    )
+)]
+pub async fn get_webhooks(
+    State(app_state): State<Arc<AppState>>,
+    _admin: Admin,
+) -> Result<Json<Vec<webhooks::Model>>, StatusCode> {
+    let webhooks = Webhooks::find()
+        .order_by_asc(webhooks::Column::Id)
+        .all(app_state.db.as_ref())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(webhooks))
+}
+
+/// A webhook.
+#[utoipa::path(
+    get,
+    path = "/webhooks/{webhook_id}",
+    responses(
+        (status = 200, description = "OK", body = webhooks::Model),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn get_webhook(
+    Path(webhook_id): Path<i32>,
+    State(app_state): State<Arc<AppState>>,
+    _admin: Admin,
+) -> Result<Json<webhooks::Model>, StatusCode> {
+    let webhook = find_webhook(&app_state, webhook_id).await?;
+    Ok(Json(webhook))
+}
+
+/// Update a webhook.
+#[utoipa::path(
+    put,
+    path = "/webhooks/{webhook_id}",
+    responses(
+        (status = 200, description = "OK", body = webhooks::Model),
+        (status = 404, description = "Not found"),
+        (status = 422, description = "Invalid payload"),
+    )
+)]
+pub async fn update_webhook(
+    Path(webhook_id): Path<i32>,
+    State(app_state): State<Arc<AppState>>,
+    _admin: Admin,
+    Json(payload): Json<NewWebhook>,
+) -> Result<Json<webhooks::Model>, StatusCode> {
+    validate_url(&payload.url)?;
+
+    let mut webhook: webhooks::ActiveModel = find_webhook(&app_state, webhook_id).await?.into();
+    webhook.url = Set(payload.url);
+    webhook.format = Set(payload.format);
+    webhook.events = Set(serde_json::json!(payload.events));
+    webhook.active = Set(payload.active);
+
+    // An omitted secret keeps the current one, an empty string removes it
+    if let Some(secret) = payload.secret {
+        webhook.secret = Set(Some(secret).filter(|secret| !secret.is_empty()));
+    }
+
+    let webhook = webhook
+        .update(app_state.db.as_ref())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(webhook))
+}
+
+/// Delete a webhook.
+#[utoipa::path(
+    delete,
+    path = "/webhooks/{webhook_id}",
+    responses(
+        (status = 204, description = "No content"),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn delete_webhook(
+    Path(webhook_id): Path<i32>,
+    State(app_state): State<Arc<AppState>>,
+    _admin: Admin,
+) -> Result<impl IntoResponse, StatusCode> {
+    let result = Webhooks::delete_by_id(webhook_id)
+        .exec(app_state.db.as_ref())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Recent deliveries of a webhook.
+#[utoipa::path(
+    get,
+    path = "/webhooks/{webhook_id}/deliveries",
+    responses(
+        (status = 200, description = "OK", body = Vec<webhook_deliveries::Model>),
+        (status = 404, description = "Not found"),
+    )
+)]
+pub async fn get_webhook_deliveries(
+    Path(webhook_id): Path<i32>,
+    State(app_state): State<Arc<AppState>>,
+    _admin: Admin,
+) -> Result<Json<Vec<webhook_deliveries::Model>>, StatusCode> {
+    find_webhook(&app_state, webhook_id).await?;
+
+    let deliveries = WebhookDeliveries::find()
+        .filter(webhook_deliveries::Column::WebhookId.eq(webhook_id))
+        .order_by_desc(webhook_deliveries::Column::CreatedAt)
+        .limit(DELIVERY_LIMIT)
+        .all(app_state.db.as_ref())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(deliveries))
+}
+
+async fn find_webhook(
+    app_state: &AppState,
+    webhook_id: i32,
+) -> Result<webhooks::Model, StatusCode> {
+    Webhooks::find_by_id(webhook_id)
+        .one(app_state.db.as_ref())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)
+}
@@ -0,0 +1,362 @@
+use anyhow::Result;
+use rust_xlsxwriter::Workbook;
+use serde::{Deserialize, Serialize};
+use std::{fs::File, io::Seek};
+use utoipa::ToSchema;
+
+use crate::{
+    client::psi::{top_opportunities, CategoryScores, CoreWebVitals},
+    entities::reports,
+};
+
+const TOP_OPPORTUNITIES: usize = 3;
+
+const COLUMNS: [&str; 15] = [
+    "site",
+    "url",
+    "strategy",
+    "performance",
+    "accessibility",
+    "best_practices",
+    "seo",
+    "lcp",
+    "cls",
+    "inp",
+    "tbt",
+    "fcp",
+    "ttfb",
+    "total_byte_weight",
+    "opportunities",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Xlsx,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Xlsx => {
+                "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+            }
+            ExportFormat::Json => "application/json",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Xlsx => "xlsx",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+/// One report flattened into a spreadsheet row, field order matches `COLUMNS`.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct ExportRow {
+    pub site: String,
+    pub url: String,
+    pub strategy: String,
+    pub performance: Option<f64>,
+    pub accessibility: Option<f64>,
+    pub best_practices: Option<f64>,
+    pub seo: Option<f64>,
+    pub lcp: Option<f64>,
+    pub cls: Option<f64>,
+    pub inp: Option<f64>,
+    pub tbt: Option<f64>,
+    pub fcp: Option<f64>,
+    pub ttfb: Option<f64>,
+    pub total_byte_weight: Option<f64>,
+    /// Titles of the largest opportunities, separated by `; `.
+    pub opportunities: String,
+}
+
+impl ExportRow {
+    pub fn new(site: &str, report: &reports::Model) -> Self {
+        let scores = CategoryScores::from(report);
+        let vitals = CoreWebVitals::from(report);
+        let opportunities: Vec<String> = top_opportunities(&report.data, TOP_OPPORTUNITIES)
+            .into_iter()
+            .map(|opportunity| opportunity.title)
+            .collect();
+
+        ExportRow {
+            site: site.to_string(),
+            url: report.url.clone(),
+            strategy: report.strategy.as_str().to_string(),
+            performance: scores.performance,
+            accessibility: scores.accessibility,
+            best_practices: scores.best_practices,
+            seo: scores.seo,
+            lcp: vitals.lcp,
+            cls: vitals.cls,
+            inp: vitals.inp,
+            tbt: vitals.tbt,
+            fcp: vitals.fcp,
+            ttfb: vitals.ttfb,
+            total_byte_weight: report.total_byte_weight,
+            opportunities: opportunities.join("; "),
+        }
+    }
+
+    fn values(&self) -> [Option<f64>; 11] {
+        [
+            self.performance,
+            self.accessibility,
+            self.best_practices,
+            self.seo,
+            self.lcp,
+            self.cls,
+            self.inp,
+            self.tbt,
+            self.fcp,
+            self.ttfb,
+            self.total_byte_weight,
+        ]
+    }
+}
+
+/// Encodes one page of rows as CSV, the header is only written for the first page.
+pub fn csv_chunk(rows: &[ExportRow], header: bool) -> Result<String> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(header)
+        .from_writer(Vec::new());
+
+    if header && rows.is_empty() {
+        writer.write_record(COLUMNS)?;
+    }
+    for row in rows {
+        writer.serialize(row)?;
+    }
+
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+/// Encodes one page of rows as elements of a JSON array, `first` omits the
+/// leading separator.
+pub fn json_chunk(rows: &[ExportRow], first: bool) -> Result<String> {
+    let mut chunk = String::new();
+
+    for (index, row) in rows.iter().enumerate() {
+        if !(first && index == 0) {
+            chunk.push(',');
+        }
+        chunk.push_str(&serde_json::to_string(row)?);
+    }
+
+    Ok(chunk)
+}
+
+/// The bytes of an XLSX export.
+#[derive(ToSchema)]
+#[schema(value_type = String, format = Binary)]
+pub struct XlsxBytes(pub Vec<u8>);
+
+/// A workbook with a constant memory worksheet, rows are flushed to a temporary
+/// file as they are written so only the page being written is held in memory.
+pub struct XlsxExport {
+    workbook: Workbook,
+    next_line: u32,
+}
+
+impl XlsxExport {
+    pub fn new() -> Result<Self> {
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet_with_constant_memory();
+        worksheet.set_name("Results")?;
+
+        for (column, name) in COLUMNS.iter().enumerate() {
+            worksheet.write_string(0, column as u16, *name)?;
+        }
+
+        Ok(XlsxExport {
+            workbook,
+            next_line: 1,
+        })
+    }
+
+    pub fn write_rows(&mut self, rows: &[ExportRow]) -> Result<()> {
+        let worksheet = self.workbook.worksheet_from_index(0)?;
+
+        for row in rows {
+            let line = self.next_line;
+            worksheet.write_string(line, 0, &row.site)?;
+            worksheet.write_string(line, 1, &row.url)?;
+            worksheet.write_string(line, 2, &row.strategy)?;
+
+            for (offset, value) in row.values().into_iter().enumerate() {
+                if let Some(value) = value {
+                    worksheet.write_number(line, 3 + offset as u16, value)?;
+                }
+            }
+
+            worksheet.write_string(line, 14, &row.opportunities)?;
+            self.next_line += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the finished workbook to an anonymous temporary file, rewound so
+    /// it can be streamed out.
+    pub fn finish_to_file(mut self) -> Result<File> {
+        let mut file = tempfile::tempfile()?;
+        self.workbook.save_to_writer(&mut file)?;
+        file.rewind()?;
+
+        Ok(file)
+    }
+
+    pub fn finish(mut self) -> Result<Vec<u8>> {
+        Ok(self.workbook.save_to_buffer()?)
+    }
+}
+
+pub fn xlsx_workbook(rows: &[ExportRow]) -> Result<Vec<u8>> {
+    let mut export = XlsxExport::new()?;
+    export.write_rows(rows)?;
+    export.finish()
+}
+
+/// Encodes a complete export in one go, for output that is not streamed.
+pub fn export_rows(format: ExportFormat, rows: Vec<ExportRow>) -> Result<Vec<u8>> {
+    match format {
+        ExportFormat::Csv => Ok(csv_chunk(&rows, true)?.into_bytes()),
+        ExportFormat::Json => Ok(format!("[{}]", json_chunk(&rows, true)?).into_bytes()),
+        ExportFormat::Xlsx => xlsx_workbook(&rows),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod export_row {
+        use super::super::*;
+        use crate::{
+            analysis::test_support::report as base, entities::sea_orm_active_enums::Strategy,
+        };
+        use serde_json::json;
+
+        pub(super) fn report() -> reports::Model {
+            reports::Model {
+                url: "https://example.com/blog".to_string(),
+                strategy: Strategy::Desktop,
+                performance: Some(0.82),
+                accessibility: Some(0.9),
+                seo: Some(1.0),
+                lcp: Some(2400.0),
+                cls: Some(0.02),
+                tbt: Some(150.0),
+                fcp: Some(900.0),
+                ttfb: Some(200.0),
+                total_byte_weight: Some(1_048_576.0),
+                data: json!({
+                    "lighthouseResult": {
+                        "audits": {
+                            "uses-webp-images": {
+                                "title": "Serve images in next-gen formats",
+                                "details": { "type": "opportunity", "overallSavingsMs": 1200 }
+                            },
+                            "render-blocking-resources": {
+                                "title": "Eliminate render-blocking resources",
+                                "details": { "type": "opportunity", "overallSavingsMs": 300 }
+                            }
+                        }
+                    }
+                }),
+                ..base()
+            }
+        }
+
+        #[test]
+        fn flattens_report() {
+            let row = ExportRow::new("https://example.com/", &report());
+            assert_eq!(row.strategy, "desktop");
+            assert_eq!(row.performance, Some(0.82));
+            assert_eq!(row.lcp, Some(2400.0));
+            assert_eq!(
+                row.opportunities,
+                "Serve images in next-gen formats; Eliminate render-blocking resources"
+            );
+        }
+    }
+
+    mod csv_chunk {
+        use super::super::*;
+
+        #[test]
+        fn writes_header_on_first_chunk_only() {
+            let row = ExportRow::new("https://example.com/", &super::export_row::report());
+
+            let first = csv_chunk(std::slice::from_ref(&row), true).unwrap();
+            let second = csv_chunk(&[row], false).unwrap();
+            assert!(first.starts_with(&COLUMNS.join(",")));
+            assert_eq!(first.lines().count(), 2);
+            assert_eq!(second.lines().count(), 1);
+            assert!(
+                second.starts_with("https://example.com/,https://example.com/blog,desktop,0.82,")
+            );
+        }
+
+        #[test]
+        fn writes_header_for_empty_export() {
+            let chunk = csv_chunk(&[], true).unwrap();
+            assert_eq!(chunk.trim_end(), COLUMNS.join(","));
+        }
+    }
+
+    mod json_chunk {
+        use super::super::*;
+
+        #[test]
+        fn joins_chunks_into_array() {
+            let row = ExportRow::new("https://example.com/", &super::export_row::report());
+            let rows = vec![row.clone(), row];
+
+            let body = format!(
+                "[{}{}]",
+                json_chunk(&rows, true).unwrap(),
+                json_chunk(&rows[..1], false).unwrap()
+            );
+            let parsed: Vec<serde_json::Value> = serde_json::from_str(&body).unwrap();
+            assert_eq!(parsed.len(), 3);
+            assert_eq!(parsed[0]["site"], "https://example.com/");
+        }
+    }
+
+    mod xlsx_export {
+        use super::super::*;
+        use std::io::Read;
+
+        #[test]
+        fn writes_zip_container() {
+            let row = ExportRow::new("https://example.com/", &super::export_row::report());
+            let workbook = xlsx_workbook(&[row]).unwrap();
+            assert!(workbook.starts_with(b"PK"));
+        }
+
+        #[test]
+        fn writes_pages_to_file() {
+            let row = ExportRow::new("https://example.com/", &super::export_row::report());
+            let mut export = XlsxExport::new().unwrap();
+            export.write_rows(std::slice::from_ref(&row)).unwrap();
+            export.write_rows(&[row]).unwrap();
+            assert_eq!(export.next_line, 3);
+
+            let mut workbook = Vec::new();
+            export
+                .finish_to_file()
+                .unwrap()
+                .read_to_end(&mut workbook)
+                .unwrap();
+            assert!(workbook.starts_with(b"PK"));
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use anyhow::{bail, Result};
+use migration::{Migrator, MigratorTrait};
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbErr};
+use serde::Serialize;
+use std::collections::HashSet;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+/// How the database schema compares to the migrations built into this binary.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct SchemaStatus {
+    /// Latest migration applied to the database.
+    pub version: Option<String>,
+    /// Latest migration this binary knows about.
+    pub expected: Option<String>,
+    pub pending: Vec<String>,
+    /// Applied migrations missing from this binary, written by a newer release.
+    pub unknown: Vec<String>,
+}
+
+impl SchemaStatus {
+    /// Both lists are migration names in the order they were applied or defined.
+    pub fn from_versions(known: &[String], applied: &[String]) -> Self {
+        let applied_set: HashSet<&String> = applied.iter().collect();
+        let known_set: HashSet<&String> = known.iter().collect();
+
+        SchemaStatus {
+            version: applied.last().cloned(),
+            expected: known.last().cloned(),
+            pending: known
+                .iter()
+                .filter(|name| !applied_set.contains(name))
+                .cloned()
+                .collect(),
+            unknown: applied
+                .iter()
+                .filter(|name| !known_set.contains(name))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn is_newer(&self) -> bool {
+        !self.unknown.is_empty()
+    }
+
+    pub fn is_current(&self) -> bool {
+        self.pending.is_empty() && self.unknown.is_empty()
+    }
+}
+
+pub async fn status<C: ConnectionTrait>(db: &C) -> Result<SchemaStatus, DbErr> {
+    let known: Vec<String> = Migrator::migrations()
+        .iter()
+        .map(|migration| migration.name().to_string())
+        .collect();
+    let applied: Vec<String> = Migrator::get_migration_models(db)
+        .await?
+        .into_iter()
+        .map(|model| model.version)
+        .collect();
+
+    Ok(SchemaStatus::from_versions(&known, &applied))
+}
+
+/// Checks the schema before serving, applying pending migrations when
+/// `migrate` is set. A schema newer than the binary is always an error.
+pub async fn prepare(db: &DatabaseConnection, migrate: bool) -> Result<SchemaStatus> {
+    let current = status(db).await?;
+
+    if current.is_newer() {
+        bail!(
+            "Database schema is newer than this binary, unknown migrations: {}",
+            current.unknown.join(", ")
+        );
+    }
+
+    if current.pending.is_empty() {
+        return Ok(current);
+    }
+
+    if !migrate {
+        warn!(
+            "{} pending migration(s), run `sea-orm-cli migrate up` or start with --migrate",
+            current.pending.len()
+        );
+        return Ok(current);
+    }
+
+    info!("Applying {} pending migration(s)", current.pending.len());
+    Migrator::up(db, None).await?;
+
+    Ok(status(db).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    mod from_versions {
+        use super::super::*;
+
+        fn names(names: &[&str]) -> Vec<String> {
+            names.iter().map(|name| name.to_string()).collect()
+        }
+
+        #[test]
+        fn lists_pending_migrations() {
+            let status = SchemaStatus::from_versions(&names(&["m1", "m2", "m3"]), &names(&["m1"]));
+            assert_eq!(status.version.as_deref(), Some("m1"));
+            assert_eq!(status.expected.as_deref(), Some("m3"));
+            assert_eq!(status.pending, names(&["m2", "m3"]));
+            assert!(!status.is_newer());
+            assert!(!status.is_current());
+        }
+
+        #[test]
+        fn detects_newer_schema() {
+            let status =
+                SchemaStatus::from_versions(&names(&["m1", "m2"]), &names(&["m1", "m2", "m3"]));
+            assert_eq!(status.unknown, names(&["m3"]));
+            assert!(status.is_newer());
+        }
+
+        #[test]
+        fn empty_database() {
+            let status = SchemaStatus::from_versions(&names(&["m1"]), &[]);
+            assert_eq!(status.version, None);
+            assert_eq!(status.pending, names(&["m1"]));
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use axum::response::sse::Event;
+use futures::{stream, Stream, StreamExt};
+use std::{convert::Infallible, future::Future, time::Duration};
+use tokio::{signal, task::JoinHandle, time::timeout};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+use tracing::info;
+
+/// Graceful shutdown of the server. Queue workers are spawned through it so
+/// they can be waited for, and check `is_shutting_down` before claiming a job.
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    token: CancellationToken,
+    tracker: TaskTracker,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Completes once `trigger` is called.
+    pub async fn triggered(&self) {
+        self.token.cancelled().await
+    }
+
+    pub fn trigger(&self) {
+        self.token.cancel();
+        self.tracker.close();
+    }
+
+    /// Spawns a task that shutdown waits for.
+    pub fn spawn<F>(&self, task: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.tracker.spawn(task)
+    }
+
+    /// Waits for the spawned tasks, `false` if some were still running at the
+    /// deadline.
+    pub async fn drain(&self, deadline: Duration) -> bool {
+        timeout(deadline, self.tracker.wait()).await.is_ok()
+    }
+
+    /// Ends an SSE stream with a `shutdown` event once shutdown starts, as open
+    /// streams would otherwise keep the server from stopping.
+    pub fn close_stream<S>(&self, events: S) -> impl Stream<Item = Result<Event, Infallible>>
+    where
+        S: Stream<Item = Result<Event, Infallible>> + Send + 'static,
+    {
+        let token = self.token.clone();
+
+        events
+            .take_until(self.token.clone().cancelled_owned())
+            .chain(
+                stream::once(async move { token.is_cancelled() }).filter_map(
+                    |shutting_down| async move {
+                        shutting_down.then(|| {
+                            Ok(Event::default()
+                                .event("shutdown")
+                                .data("Server is shutting down"))
+                        })
+                    },
+                ),
+            )
+    }
+}
+
+/// Completes on SIGINT or SIGTERM.
+pub async fn signal() {
+    let interrupt = async {
+        signal::ctrl_c().await.expect("Failed to listen for SIGINT");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal::unix::signal(signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => info!("Received SIGINT, shutting down"),
+        _ = terminate => info!("Received SIGTERM, shutting down"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod close_stream {
+        use super::super::*;
+
+        #[tokio::test]
+        async fn ends_with_shutdown_event() {
+            let shutdown = Shutdown::new();
+            let events =
+                stream::once(async { Ok(Event::default().data("first")) }).chain(stream::pending());
+            let mut events = Box::pin(shutdown.close_stream(events));
+
+            assert!(events.next().await.is_some());
+            shutdown.trigger();
+            assert!(events.next().await.is_some());
+            assert!(events.next().await.is_none());
+        }
+
+        #[tokio::test]
+        async fn finished_streams_get_no_event() {
+            let shutdown = Shutdown::new();
+            let events = stream::once(async { Ok(Event::default().data("first")) });
+            let mut events = Box::pin(shutdown.close_stream(events));
+
+            assert!(events.next().await.is_some());
+            assert!(events.next().await.is_none());
+        }
+    }
+
+    mod drain {
+        use super::super::*;
+
+        #[tokio::test]
+        async fn waits_for_tasks_up_to_deadline() {
+            let shutdown = Shutdown::new();
+            shutdown.spawn(tokio::time::sleep(Duration::from_millis(10)));
+            shutdown.spawn(tokio::time::sleep(Duration::from_secs(60)));
+            shutdown.trigger();
+
+            assert!(!shutdown.drain(Duration::from_millis(50)).await);
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use crate::utils::site_base_url;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SiteFormat {
+    Csv,
+    #[default]
+    Json,
+    Toml,
+}
+
+impl SiteFormat {
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type.split(';').next()?.trim();
+        match mime {
+            "text/csv" => Some(SiteFormat::Csv),
+            "application/json" => Some(SiteFormat::Json),
+            "application/toml" => Some(SiteFormat::Toml),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            SiteFormat::Csv => "text/csv",
+            SiteFormat::Json => "application/json",
+            SiteFormat::Toml => "application/toml",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SiteFormat::Csv => "csv",
+            SiteFormat::Json => "json",
+            SiteFormat::Toml => "toml",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RowStatus {
+    Created,
+    WouldCreate,
+    Exists,
+    Duplicate,
+    Invalid,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImportRow {
+    pub row: usize,
+    pub domain: Option<String>,
+    pub status: RowStatus,
+    pub error: Option<String>,
+}
+
+/// A row of a CSV export or an item of a JSON one.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[schema(as = ExportedSite)]
+pub struct CsvSite {
+    pub domain: String,
+}
+
+// The `sites` key of `Settings`, other `config.toml` keys are ignored on import
+#[derive(Serialize, Deserialize)]
+struct TomlSites {
+    sites: Vec<String>,
+}
+
+/// Raw domains in input order, a row is `Err` when it could not be read at all.
+pub fn parse_sites(format: SiteFormat, body: &str) -> Result<Vec<Result<String, String>>> {
+    match format {
+        SiteFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new()
+                .trim(csv::Trim::All)
+                .from_reader(body.as_bytes());
+
+            let headers = reader.headers().context("Unable to read CSV header")?;
+            if !headers.iter().any(|header| header == "domain") {
+                return Err(anyhow!("CSV header must include a `domain` column"));
+            }
+
+            Ok(reader
+                .deserialize::<CsvSite>()
+                .map(|row| row.map(|site| site.domain).map_err(|e| e.to_string()))
+                .collect())
+        }
+        SiteFormat::Json => {
+            let values: Vec<Value> =
+                serde_json::from_str(body).context("Expected a JSON array of sites")?;
+
+            Ok(values
+                .into_iter()
+                .map(|value| match value {
+                    Value::String(domain) => Ok(domain),
+                    Value::Object(ref site) => site
+                        .get("domain")
+                        .and_then(Value::as_str)
+                        .map(str::to_string)
+                        .ok_or_else(|| "missing `domain` field".to_string()),
+                    _ => Err("expected a string or an object with a `domain`".to_string()),
+                })
+                .collect())
+        }
+        SiteFormat::Toml => {
+            let file: TomlSites = toml::from_str(body).context("Invalid TOML sites file")?;
+            Ok(file.sites.into_iter().map(Ok).collect())
+        }
+    }
+}
+
+pub fn validate_domain(domain: &str) -> Result<String, String> {
+    if domain.is_empty() {
+        return Err("domain is empty".to_string());
+    }
+
+    let url = site_base_url(domain).map_err(|e| e.to_string())?;
+
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("unsupported scheme `{}`", url.scheme()));
+    }
+
+    if url.host_str().is_none() {
+        return Err("domain has no host".to_string());
+    }
+
+    Ok(url.to_string())
+}
+
+/// Validates each row against the existing sites, `existing` holds normalised urls.
+pub fn plan_import(
+    rows: Vec<Result<String, String>>,
+    existing: &HashSet<String>,
+    dry_run: bool,
+) -> Vec<ImportRow> {
+    let mut seen = HashSet::new();
+
+    rows.into_iter()
+        .enumerate()
+        .map(|(index, row)| {
+            let row_number = index + 1;
+
+            let domain = match row {
+                Ok(domain) => domain.trim().to_string(),
+                Err(error) => {
+                    return ImportRow {
+                        row: row_number,
+                        domain: None,
+                        status: RowStatus::Invalid,
+                        error: Some(error),
+                    }
+                }
+            };
+
+            let (status, error) = match validate_domain(&domain) {
+                Err(error) => (RowStatus::Invalid, Some(error)),
+                Ok(normalised) if existing.contains(&normalised) => (RowStatus::Exists, None),
+                Ok(normalised) if !seen.insert(normalised.clone()) => (RowStatus::Duplicate, None),
+                Ok(_) if dry_run => (RowStatus::WouldCreate, None),
+                Ok(_) => (RowStatus::Created, None),
+            };
+
+            ImportRow {
+                row: row_number,
+                domain: Some(domain),
+                status,
+                error,
+            }
+        })
+        .collect()
+}
+
+/// Serialises the domains, TOML is a `config.toml` fragment setting only `sites`
+/// so it can be merged into a config file or loaded on its own.
+pub fn export_sites(format: SiteFormat, domains: Vec<String>) -> Result<String> {
+    match format {
+        SiteFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for domain in domains {
+                writer.serialize(CsvSite { domain })?;
+            }
+            Ok(String::from_utf8(writer.into_inner()?)?)
+        }
+        SiteFormat::Json => {
+            let sites: Vec<CsvSite> = domains
+                .into_iter()
+                .map(|domain| CsvSite { domain })
+                .collect();
+            Ok(serde_json::to_string_pretty(&sites)?)
+        }
+        SiteFormat::Toml => Ok(format!(
+            "# Sites exported from Tarin, a config.toml fragment\n{}",
+            toml::to_string(&TomlSites { sites: domains })?
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod parse_sites {
+        use super::super::*;
+
+        #[test]
+        fn parses_csv_rows() {
+            let body = "domain,notes\nhttps://example.com,main\n example-2.com ,\n";
+            let rows = parse_sites(SiteFormat::Csv, body).unwrap();
+            assert_eq!(
+                rows,
+                vec![
+                    Ok("https://example.com".to_string()),
+                    Ok("example-2.com".to_string())
+                ]
+            );
+        }
+
+        #[test]
+        fn rejects_csv_without_domain_column() {
+            let result = parse_sites(SiteFormat::Csv, "url\nhttps://example.com\n");
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn parses_json_strings_and_objects() {
+            let body = r#"["https://example.com", {"domain": "example-2.com"}, 42]"#;
+            let rows = parse_sites(SiteFormat::Json, body).unwrap();
+            assert_eq!(rows[0], Ok("https://example.com".to_string()));
+            assert_eq!(rows[1], Ok("example-2.com".to_string()));
+            assert!(rows[2].is_err());
+        }
+
+        #[test]
+        fn parses_config_toml() {
+            let body = "patterns = [\"/blog/:slug\"]\nignore_paths = []\nsites = [\"https://example.com\"]";
+            let rows = parse_sites(SiteFormat::Toml, body).unwrap();
+            assert_eq!(rows, vec![Ok("https://example.com".to_string())]);
+        }
+    }
+
+    mod plan_import {
+        use super::super::*;
+
+        #[test]
+        fn flags_invalid_existing_and_duplicate_rows() {
+            let existing = HashSet::from(["https://example.com/".to_string()]);
+            let rows = vec![
+                Ok("example.com".to_string()),
+                Ok("https://new.example.com".to_string()),
+                Ok("new.example.com".to_string()),
+                Ok("ftp://files.example.com".to_string()),
+                Err("bad row".to_string()),
+            ];
+
+            let plan = plan_import(rows, &existing, true);
+            let statuses: Vec<RowStatus> = plan.iter().map(|row| row.status).collect();
+            assert_eq!(
+                statuses,
+                vec![
+                    RowStatus::Exists,
+                    RowStatus::WouldCreate,
+                    RowStatus::Duplicate,
+                    RowStatus::Invalid,
+                    RowStatus::Invalid,
+                ]
+            );
+            assert_eq!(plan[4].row, 5);
+            assert_eq!(plan[4].error.as_deref(), Some("bad row"));
+        }
+    }
+
+    mod export_sites {
+        use super::super::*;
+
+        #[test]
+        fn round_trips_each_format() {
+            let domains = vec![
+                "https://example.com".to_string(),
+                "example-2.com".to_string(),
+            ];
+
+            for format in [SiteFormat::Csv, SiteFormat::Json, SiteFormat::Toml] {
+                let output = export_sites(format, domains.clone()).unwrap();
+                let rows: Vec<String> = parse_sites(format, &output)
+                    .unwrap()
+                    .into_iter()
+                    .map(Result::unwrap)
+                    .collect();
+                assert_eq!(rows, domains);
+            }
+        }
+
+        #[test]
+        fn toml_loads_as_config() {
+            let domains = vec!["https://example.com".to_string()];
+            let output = export_sites(SiteFormat::Toml, domains.clone()).unwrap();
+
+            let settings =
+                crate::config::Settings::from_sources(Some(("sites.toml", &output)), Vec::new())
+                    .unwrap();
+            assert_eq!(settings.sites, domains);
+        }
+    }
+}
@@ -1,20 +1,23 @@
 use anyhow::Result;
 use url::Url;
 
-use crate::config::load_config;
-
-// Dev helper to get website list from file
-pub async fn get_base_sites() -> Result<Vec<Url>> {
-    let mut sites = Vec::new();
-
-    if let Some(config) = load_config("config.toml").await {
-        for site in &config.sites {
-            let site_url = Url::parse(site)?;
-            sites.push(site_url)
+// Sites may be stored as a bare domain or as a full url
+pub fn site_base_url(domain: &str) -> Result<Url> {
+    match Url::parse(domain) {
+        Ok(url) => Ok(url),
+        Err(url::ParseError::RelativeUrlWithoutBase) => {
+            Ok(Url::parse(&format!("https://{domain}"))?)
         }
-    } else {
-        panic!();
+        Err(e) => Err(e.into()),
     }
+}
 
-    Ok(sites)
+/// Escapes text for HTML and XML element content and quoted attributes.
+pub fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
 }
@@ -0,0 +1,511 @@
+use hmac::{Hmac, Mac};
+use reqwest::{Client, StatusCode};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    sync::broadcast::{error::RecvError, Receiver},
+    task::JoinHandle,
+    time::sleep,
+};
+use tracing::{error, warn};
+use utoipa::ToSchema;
+
+use crate::{
+    analysis::budgets::BudgetFailure,
+    entities::{
+        sea_orm_active_enums::{RunStatus, WebhookFormat},
+        webhook_deliveries,
+        webhooks::{self, Entity as Webhooks},
+    },
+    events::RunEvent,
+};
+
+pub const SIGNATURE_HEADER: &str = "x-tarin-signature";
+pub const EVENT_HEADER: &str = "x-tarin-event";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    RunCompleted,
+    BudgetFailed,
+    Regression,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::RunCompleted => "run_completed",
+            WebhookEvent::BudgetFailed => "budget_failed",
+            WebhookEvent::Regression => "regression",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub event: WebhookEvent,
+    pub summary: String,
+    pub data: Value,
+}
+
+impl Notification {
+    pub fn from_run_event(run_event: &RunEvent) -> Option<Self> {
+        let (event, summary) = match run_event {
+            RunEvent::Report(_) => return None,
+            RunEvent::Completed(run) => {
+                let outcome = match run.status {
+                    RunStatus::Failed => "failed",
+                    _ => "completed",
+                };
+                (
+                    WebhookEvent::RunCompleted,
+                    format!(
+                        "Tarin run #{} {outcome} ({} strategy, {} sample(s) per url)",
+                        run.id,
+                        run.strategy.as_str(),
+                        run.samples
+                    ),
+                )
+            }
+            RunEvent::Budget(result) if !result.passed => {
+                let failures: Vec<BudgetFailure> =
+                    serde_json::from_value(result.failures.clone()).unwrap_or_default();
+                let details: Vec<String> = failures.iter().map(BudgetFailure::describe).collect();
+                (
+                    WebhookEvent::BudgetFailed,
+                    format!(
+                        "Budget failed for {} in run #{}: {}",
+                        result.url,
+                        result.run_id,
+                        details.join(", ")
+                    ),
+                )
+            }
+            RunEvent::Budget(_) => return None,
+            RunEvent::Regression(regression) => (
+                WebhookEvent::Regression,
+                format!(
+                    "Regression on {} ({}) in run #{}: {} {} -> {} (tolerance {})",
+                    regression.url,
+                    regression.strategy.as_str(),
+                    regression.run_id,
+                    regression.metric.as_str(),
+                    regression.previous_value,
+                    regression.current_value,
+                    regression.tolerance
+                ),
+            ),
+        };
+
+        Some(Notification {
+            event,
+            summary,
+            data: event_data(run_event),
+        })
+    }
+}
+
+// The payload body, without the full PSI response a report event would carry
+fn event_data(event: &RunEvent) -> Value {
+    match event {
+        RunEvent::Report(report) => json!({ "id": report.id, "url": report.url }),
+        RunEvent::Budget(result) => json!(result),
+        RunEvent::Regression(regression) => json!(regression),
+        RunEvent::Completed(run) => json!(run),
+    }
+}
+
+pub fn build_payload(format: WebhookFormat, notification: &Notification) -> Value {
+    match format {
+        WebhookFormat::Generic => json!({
+            "event": notification.event.as_str(),
+            "summary": notification.summary,
+            "data": notification.data,
+        }),
+        WebhookFormat::Slack => json!({ "text": notification.summary }),
+        WebhookFormat::Teams => json!({
+            "@type": "MessageCard",
+            "@context": "https://schema.org/extensions",
+            "summary": notification.summary,
+            "title": format!("Tarin: {}", notification.event.as_str().replace('_', " ")),
+            "text": notification.summary,
+        }),
+    }
+}
+
+/// Hex encoded HMAC-SHA256 of the request body, prefixed with the algorithm.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+pub fn subscribes_to(webhook: &webhooks::Model, event: WebhookEvent) -> bool {
+    match serde_json::from_value::<Vec<WebhookEvent>>(webhook.events.clone()) {
+        Ok(events) => events.is_empty() || events.contains(&event),
+        Err(_) => false,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub attempts: u32,
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeliveryOutcome {
+    pub attempts: u32,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+}
+
+impl DeliveryOutcome {
+    pub fn success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Posts the payload, retrying with exponential backoff on network errors,
+/// rate limiting and server errors.
+pub async fn deliver(
+    client: &Client,
+    url: &str,
+    secret: Option<&str>,
+    event: WebhookEvent,
+    payload: &Value,
+    retry: &RetryPolicy,
+) -> DeliveryOutcome {
+    let body = payload.to_string();
+    let mut outcome = DeliveryOutcome {
+        attempts: 0,
+        status_code: None,
+        error: None,
+    };
+
+    while outcome.attempts < retry.attempts.max(1) {
+        if outcome.attempts > 0 {
+            sleep(retry.base_delay * 2u32.pow(outcome.attempts - 1)).await;
+        }
+        outcome.attempts += 1;
+
+        let mut request = client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, event.as_str())
+            .body(body.clone());
+
+        if let Some(secret) = secret {
+            request = request.header(SIGNATURE_HEADER, sign(secret, body.as_bytes()));
+        }
+
+        match request.send().await {
+            Ok(response) if response.status().is_success() => {
+                outcome.status_code = Some(response.status().as_u16());
+                outcome.error = None;
+                return outcome;
+            }
+            Ok(response) => {
+                let status = response.status();
+                outcome.status_code = Some(status.as_u16());
+                outcome.error = Some(format!("HTTP {status}"));
+
+                if !(status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS) {
+                    return outcome;
+                }
+            }
+            Err(e) => {
+                outcome.status_code = None;
+                outcome.error = Some(e.to_string());
+            }
+        }
+    }
+
+    outcome
+}
+
+/// Forwards run events to every active webhook subscribed to them.
+pub fn spawn_dispatcher(
+    db: Arc<DatabaseConnection>,
+    mut receiver: Receiver<RunEvent>,
+) -> JoinHandle<()> {
+    let client = Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .unwrap_or_default();
+
+    tokio::spawn(async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("Webhook dispatcher skipped {skipped} run events");
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+
+            let Some(notification) = Notification::from_run_event(&event) else {
+                continue;
+            };
+
+            let webhooks = match Webhooks::find()
+                .filter(webhooks::Column::Active.eq(true))
+                .all(db.as_ref())
+                .await
+            {
+                Ok(webhooks) => webhooks,
+                Err(e) => {
+                    error!("Failed to load webhooks: {e}");
+                    continue;
+                }
+            };
+
+            for webhook in webhooks {
+                if !subscribes_to(&webhook, notification.event) {
+                    continue;
+                }
+
+                let db = db.clone();
+                let client = client.clone();
+                let notification = notification.clone();
+
+                tokio::spawn(async move {
+                    let payload = build_payload(webhook.format, &notification);
+                    let outcome = deliver(
+                        &client,
+                        &webhook.url,
+                        webhook.secret.as_deref(),
+                        notification.event,
+                        &payload,
+                        &RetryPolicy::default(),
+                    )
+                    .await;
+
+                    if let Err(e) =
+                        log_delivery(&db, webhook.id, notification.event, payload, &outcome).await
+                    {
+                        error!("Failed to log delivery for webhook {}: {e}", webhook.id);
+                    }
+                });
+            }
+        }
+    })
+}
+
+async fn log_delivery(
+    db: &DatabaseConnection,
+    webhook_id: i32,
+    event: WebhookEvent,
+    payload: Value,
+    outcome: &DeliveryOutcome,
+) -> Result<webhook_deliveries::Model, DbErr> {
+    webhook_deliveries::ActiveModel {
+        webhook_id: Set(webhook_id),
+        event: Set(event.as_str().to_string()),
+        payload: Set(payload),
+        attempts: Set(outcome.attempts as i32),
+        status_code: Set(outcome.status_code.map(i32::from)),
+        success: Set(outcome.success()),
+        error: Set(outcome.error.clone()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    mod sign {
+        use super::super::*;
+
+        #[test]
+        fn matches_known_digest() {
+            // Reference value from `echo -n 'hello' | openssl dgst -sha256 -hmac secret`
+            assert_eq!(
+                sign("secret", b"hello"),
+                "sha256=88aab3ede8d3adf94d26ab90d3bafd4a2083070c3bcce9c014ee04a443847c0b"
+            );
+        }
+    }
+
+    mod from_run_event {
+        use super::super::*;
+        use crate::entities::{
+            regressions,
+            sea_orm_active_enums::{Metric, Strategy},
+        };
+
+        #[test]
+        fn names_regressed_metric() {
+            let regression = regressions::Model {
+                id: 1,
+                run_id: 2,
+                previous_run_id: 1,
+                site_id: 1,
+                site_url_id: 1,
+                url: "https://example.com/".to_string(),
+                strategy: Strategy::Mobile,
+                metric: Metric::TotalByteWeight,
+                previous_value: 1000.0,
+                current_value: 2000.0,
+                tolerance: 100.0,
+                created_at: Default::default(),
+            };
+
+            let notification =
+                Notification::from_run_event(&RunEvent::Regression(regression)).unwrap();
+            assert_eq!(
+                notification.summary,
+                "Regression on https://example.com/ (mobile) in run #2: total_byte_weight 1000 -> 2000 (tolerance 100)"
+            );
+        }
+    }
+
+    mod build_payload {
+        use super::super::*;
+
+        fn notification() -> Notification {
+            Notification {
+                event: WebhookEvent::Regression,
+                summary: "Regression on https://example.com/".to_string(),
+                data: json!({ "id": 1 }),
+            }
+        }
+
+        #[test]
+        fn generic_payload_includes_data() {
+            let payload = build_payload(WebhookFormat::Generic, &notification());
+            assert_eq!(payload["event"], "regression");
+            assert_eq!(payload["data"]["id"], 1);
+        }
+
+        #[test]
+        fn slack_payload_uses_text() {
+            let payload = build_payload(WebhookFormat::Slack, &notification());
+            assert_eq!(
+                payload,
+                json!({ "text": "Regression on https://example.com/" })
+            );
+        }
+
+        #[test]
+        fn teams_payload_is_message_card() {
+            let payload = build_payload(WebhookFormat::Teams, &notification());
+            assert_eq!(payload["@type"], "MessageCard");
+            assert_eq!(payload["title"], "Tarin: regression");
+        }
+    }
+
+    mod deliver {
+        use super::super::*;
+        use mockito::{Matcher, Server};
+
+        fn retry() -> RetryPolicy {
+            RetryPolicy {
+                attempts: 3,
+                base_delay: Duration::from_millis(1),
+            }
+        }
+
+        #[tokio::test]
+        async fn signs_request_body() {
+            let mut server = Server::new_async().await;
+            let payload = json!({ "text": "hello" });
+            let signature = sign("secret", payload.to_string().as_bytes());
+            let mock = server
+                .mock("POST", "/hook")
+                .match_header(SIGNATURE_HEADER, signature.as_str())
+                .match_header(EVENT_HEADER, "run_completed")
+                .match_body(Matcher::Json(payload.clone()))
+                .with_status(204)
+                .create_async()
+                .await;
+
+            let url = format!("{}/hook", server.url());
+            let outcome = deliver(
+                &Client::new(),
+                &url,
+                Some("secret"),
+                WebhookEvent::RunCompleted,
+                &payload,
+                &retry(),
+            )
+            .await;
+
+            mock.assert_async().await;
+            assert!(outcome.success());
+            assert_eq!(outcome.attempts, 1);
+            assert_eq!(outcome.status_code, Some(204));
+        }
+
+        #[tokio::test]
+        async fn retries_server_errors() {
+            let mut server = Server::new_async().await;
+            let mock = server
+                .mock("POST", "/hook")
+                .with_status(503)
+                .expect(3)
+                .create_async()
+                .await;
+
+            let url = format!("{}/hook", server.url());
+            let outcome = deliver(
+                &Client::new(),
+                &url,
+                None,
+                WebhookEvent::Regression,
+                &json!({}),
+                &retry(),
+            )
+            .await;
+
+            mock.assert_async().await;
+            assert!(!outcome.success());
+            assert_eq!(outcome.attempts, 3);
+            assert_eq!(outcome.status_code, Some(503));
+        }
+
+        #[tokio::test]
+        async fn does_not_retry_client_errors() {
+            let mut server = Server::new_async().await;
+            let mock = server
+                .mock("POST", "/hook")
+                .with_status(404)
+                .expect(1)
+                .create_async()
+                .await;
+
+            let url = format!("{}/hook", server.url());
+            let outcome = deliver(
+                &Client::new(),
+                &url,
+                None,
+                WebhookEvent::BudgetFailed,
+                &json!({}),
+                &retry(),
+            )
+            .await;
+
+            mock.assert_async().await;
+            assert_eq!(outcome.attempts, 1);
+            assert_eq!(outcome.error.as_deref(), Some("HTTP 404 Not Found"));
+        }
+    }
+}
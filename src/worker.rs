@@ -0,0 +1,151 @@
+use rand::Rng;
+use sea_orm::{ActiveEnum, DatabaseConnection, DbErr};
+use std::{sync::Arc, time::Duration};
+use tokio::{task::JoinSet, time::sleep};
+use tracing::{error, warn};
+
+use crate::{
+    client::processor::{execute_job, finish_run_if_done},
+    config::QueueSettings,
+    events::RunEvents,
+    queue,
+    reload::LiveSettings,
+    shutdown::Shutdown,
+};
+
+// How long an idle worker waits before looking for jobs again
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Claims audit jobs from the queue and executes them. Any number of workers
+/// across processes can share a queue, the database hands each job to one.
+#[derive(Clone)]
+pub struct Worker {
+    id: String,
+    db: Arc<DatabaseConnection>,
+    events: RunEvents,
+    settings: LiveSettings,
+    queue: QueueSettings,
+    shutdown: Shutdown,
+}
+
+impl Worker {
+    pub fn new(
+        db: Arc<DatabaseConnection>,
+        events: RunEvents,
+        settings: LiveSettings,
+        shutdown: Shutdown,
+    ) -> Self {
+        let suffix: u32 = rand::thread_rng().gen();
+
+        Worker {
+            id: format!("{}-{suffix:08x}", std::process::id()),
+            db,
+            events,
+            queue: settings.current().queue.clone(),
+            settings,
+            shutdown,
+        }
+    }
+
+    /// Identifies the jobs this process holds in `locked_by`.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Events of the jobs this process works on.
+    pub fn events(&self) -> &RunEvents {
+        &self.events
+    }
+
+    /// Starts `queue.workers` loops that run until shutdown, which waits for
+    /// the jobs in flight.
+    pub fn spawn(&self) {
+        for _ in 0..self.queue.workers {
+            let worker = self.clone();
+            self.shutdown.spawn(async move { worker.work(None).await });
+        }
+    }
+
+    /// Works through the jobs of one run, returning once none are left.
+    pub async fn run_until_done(&self, run_id: i32) {
+        let mut loops = JoinSet::new();
+        for _ in 0..self.queue.workers {
+            let worker = self.clone();
+            loops.spawn(async move { worker.work(Some(run_id)).await });
+        }
+
+        while let Some(result) = loops.join_next().await {
+            if let Err(e) = result {
+                error!("Worker for run {run_id} failed: {e}");
+            }
+        }
+    }
+
+    /// Hands the jobs still held back to the queue, for when the shutdown
+    /// deadline passes before they finish.
+    pub async fn release(&self) -> Result<u64, DbErr> {
+        queue::release(self.db.as_ref(), &self.id).await
+    }
+
+    async fn work(&self, run_id: Option<i32>) {
+        while !self.shutdown.is_shutting_down() {
+            let idle = match self.step(run_id).await {
+                Ok(worked) => !worked,
+                Err(e) => {
+                    error!("Worker {} failed to poll the queue: {e}", self.id);
+                    true
+                }
+            };
+
+            if idle {
+                if let Some(run_id) = run_id {
+                    match queue::open_jobs(self.db.as_ref(), run_id).await {
+                        Ok(0) => return,
+                        Ok(_) => (),
+                        Err(e) => error!("Failed to count jobs of run {run_id}: {e}"),
+                    }
+                }
+
+                tokio::select! {
+                    _ = sleep(POLL_INTERVAL) => (),
+                    _ = self.shutdown.triggered() => (),
+                }
+            }
+        }
+    }
+
+    // Executes the next job, `false` when none was available
+    async fn step(&self, run_id: Option<i32>) -> Result<bool, DbErr> {
+        let db = self.db.as_ref();
+        let settings = self.settings.current();
+
+        for expired in queue::expire(db, &self.queue).await? {
+            finish_run_if_done(db, &self.events, &settings, expired).await?;
+        }
+
+        let Some(job) = queue::claim(db, &self.id, &self.queue, run_id).await? else {
+            return Ok(false);
+        };
+
+        match execute_job(db, &self.events, &settings, &job).await {
+            Ok(()) => {
+                if !queue::complete(db, &job).await? {
+                    warn!("Job {} was claimed again before it finished", job.id);
+                }
+            }
+            Err(e) => match queue::fail(db, &job, &format!("{e:#}"), &self.queue).await? {
+                Some(status) => warn!(
+                    "Job {} failed on attempt {}, now {}: {e:#}",
+                    job.id,
+                    job.attempts,
+                    status.to_value()
+                ),
+                None => warn!("Job {} failed after it was claimed again: {e:#}", job.id),
+            },
+        }
+
+        finish_run_if_done(db, &self.events, &settings, job.run_id).await?;
+
+        Ok(true)
+    }
+}
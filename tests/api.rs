@@ -0,0 +1,1080 @@
+use std::time::Duration;
+
+use reqwest::{header::LOCATION, StatusCode};
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::NotSet, ActiveValue::Set, ColumnTrait, EntityTrait,
+    PaginatorTrait, QueryFilter,
+};
+use serde_json::{json, Value};
+use tarin::{
+    auth::{self, Scope},
+    client::{
+        processor::{queue_unfinished_runs, report_from_psi},
+        sitemaps::extract_sitemap_url_list,
+    },
+    entities::{
+        audit_jobs, reports, runs,
+        sea_orm_active_enums::{JobStatus, Role, RunStatus, Strategy},
+        site_urls, sites,
+    },
+};
+use tokio::sync::broadcast;
+use url::Url;
+
+mod common;
+
+use common::{spawn_site, TestApp};
+
+#[tokio::test]
+async fn crawls_sitemaps() {
+    let site = spawn_site(&["/", "/about", "/blog/first"]).await;
+
+    let urls = extract_sitemap_url_list(&Url::parse(&site).unwrap(), &common::settings())
+        .await
+        .unwrap();
+    let paths: Vec<&str> = urls.iter().map(Url::path).collect();
+    assert_eq!(paths, vec!["/", "/about", "/blog/first"]);
+}
+
+#[tokio::test]
+async fn check_exit_status_tells_failed_budgets_from_errors() {
+    let site = spawn_site(&["/"]).await;
+    let dir = tempfile::tempdir().unwrap();
+    let check = |budget: &str| {
+        let path = dir.path().join("budgets.toml");
+        std::fs::write(&path, budget).unwrap();
+
+        tokio::process::Command::new(env!("CARGO_BIN_EXE_tarin"))
+            .current_dir(dir.path())
+            .env("TARIN_AUDIT_PROVIDER", "fake")
+            .args(["check", "--site", &site, "--budget"])
+            .arg(path)
+            .output()
+    };
+
+    // The fake backend always scores 0.87
+    let passed = check("[[budgets]]\nmetric = \"performance\"\nmin = 0.5\n")
+        .await
+        .unwrap();
+    assert_eq!(passed.status.code(), Some(0));
+
+    let failed = check("[[budgets]]\nmetric = \"performance\"\nmin = 0.95\n")
+        .await
+        .unwrap();
+    assert_eq!(failed.status.code(), Some(2));
+
+    let broken = check("[[budgets]]\nmetric = \"performance\"\n")
+        .await
+        .unwrap();
+    assert_eq!(broken.status.code(), Some(1));
+}
+
+#[tokio::test]
+async fn reports_schema_version() {
+    let app = TestApp::spawn().await;
+
+    let status: Value = app
+        .get("/status")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(status["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(status["schema"]["version"], status["schema"]["expected"]);
+    assert_eq!(status["schema"]["pending"], json!([]));
+}
+
+#[tokio::test]
+async fn reports_active_config() {
+    let app = TestApp::spawn().await;
+
+    let config: Value = app
+        .get("/config")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(config["path"], "config.toml");
+    assert_eq!(config["error"], Value::Null);
+    assert_eq!(config["patterns"], json!([]));
+}
+
+#[tokio::test]
+async fn serves_health_checks_and_metrics() {
+    let app = TestApp::spawn().await;
+    let anonymous = reqwest::Client::new();
+
+    let health = anonymous
+        .get(format!("{}/healthz", app.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(health.status(), StatusCode::OK);
+
+    let ready = anonymous
+        .get(format!("{}/readyz", app.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(ready.status(), StatusCode::OK);
+    let ready: Value = ready.json().await.unwrap();
+    assert_eq!(
+        ready,
+        json!({ "database": true, "migrations": true, "audit": true, "shutting_down": false })
+    );
+
+    let metrics = anonymous
+        .get(format!("{}/metrics", app.url))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(
+        metrics.contains(r#"tarin_http_requests_total{method="GET",path="/readyz",status="200"}"#)
+    );
+    assert!(metrics.contains("tarin_active_runs"));
+
+    // Draining instances drop out of the load balancer while still serving
+    app.shutdown.trigger();
+    let ready = anonymous
+        .get(format!("{}/readyz", app.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(ready.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let ready: Value = ready.json().await.unwrap();
+    assert_eq!(ready["database"], true);
+    assert_eq!(ready["shutting_down"], true);
+
+    // Losing the database fails readiness but not the process metrics
+    app.db.clone().close().await.unwrap();
+    let ready = anonymous
+        .get(format!("{}/readyz", app.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(ready.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    let metrics = anonymous
+        .get(format!("{}/metrics", app.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(metrics.status(), StatusCode::OK);
+    let metrics = metrics.text().await.unwrap();
+    assert!(metrics.contains("tarin_http_requests_total"));
+}
+
+#[tokio::test]
+async fn serves_openapi_document() {
+    let app = TestApp::spawn().await;
+    let anonymous = reqwest::Client::new();
+
+    let document: Value = anonymous
+        .get(format!("{}/openapi.json", app.url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(document["openapi"], "3.1.0");
+    assert_eq!(
+        document["paths"]["/sites/{site_id}"]["put"]["requestBody"]["content"]["application/json"]
+            ["schema"]["$ref"],
+        "#/components/schemas/UpdateSite"
+    );
+    assert_eq!(
+        document["paths"]["/sites/{site_id}"]["put"]["security"],
+        json!([{ "apiKey": [] }, { "session": [] }])
+    );
+    assert_eq!(
+        document["components"]["schemas"]["Site"]["required"],
+        json!(["id", "domain", "created_at"])
+    );
+    assert_eq!(
+        document["components"]["schemas"]["Webhook"]["properties"]["events"]["items"]["$ref"],
+        "#/components/schemas/WebhookEvent"
+    );
+
+    // Swagger UI is served from the binary, not a CDN
+    let docs = anonymous
+        .get(format!("{}/docs", app.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(docs.status(), StatusCode::OK);
+    assert!(!docs.text().await.unwrap().contains("https://"));
+
+    let initializer = anonymous
+        .get(format!("{}/docs/swagger-initializer.js", app.url))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(initializer.contains("/openapi.json"));
+}
+
+#[tokio::test]
+async fn enforces_api_key_scopes() {
+    let app = TestApp::spawn().await;
+    let anonymous = reqwest::Client::new();
+    let read_key = app.key(&[Scope::Read]).await;
+
+    let status = anonymous
+        .get(format!("{}/sites", app.url))
+        .send()
+        .await
+        .unwrap()
+        .status();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let status = anonymous
+        .get(format!("{}/sites", app.url))
+        .bearer_auth("tarin_not-a-key")
+        .send()
+        .await
+        .unwrap()
+        .status();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let status = anonymous
+        .get(format!("{}/sites", app.url))
+        .bearer_auth(&read_key)
+        .send()
+        .await
+        .unwrap()
+        .status();
+    assert_eq!(status, StatusCode::OK);
+
+    let status = anonymous
+        .post(format!("{}/sites", app.url))
+        .bearer_auth(&read_key)
+        .json(&json!({ "domain": "https://scopes.example.com" }))
+        .send()
+        .await
+        .unwrap()
+        .status();
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // Instance wide routes also need the admin scope
+    let write_key = app.key(&[Scope::Read, Scope::Write]).await;
+    let admin_key = app.key(&[Scope::Read, Scope::Write, Scope::Admin]).await;
+    let webhooks = |key: &str| {
+        anonymous
+            .get(format!("{}/webhooks", app.url))
+            .bearer_auth(key)
+            .send()
+    };
+    let status = webhooks(&write_key).await.unwrap().status();
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let status = webhooks(&admin_key).await.unwrap().status();
+    assert_eq!(status, StatusCode::OK);
+
+    let status = anonymous
+        .post(format!("{}/config/reload", app.url))
+        .bearer_auth(&write_key)
+        .send()
+        .await
+        .unwrap()
+        .status();
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let status = anonymous
+        .get(format!("{}/status", app.url))
+        .send()
+        .await
+        .unwrap()
+        .status();
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn scopes_sites_to_user_teams() {
+    let app = TestApp::spawn().await;
+    let suffix = &auth::generate_key()[6..14];
+    let team = app.team(&format!("team-{suffix}")).await;
+    let other_team = app.team(&format!("other-{suffix}")).await;
+    let email = format!("viewer-{suffix}@example.com");
+    app.user(&email, "hunter2", &[(team, Role::Viewer)]).await;
+
+    let mut site_ids = Vec::new();
+    for (domain, team_id) in [
+        (format!("https://{suffix}.example.com"), team),
+        (format!("https://{suffix}.example.org"), other_team),
+    ] {
+        let site: Value = app
+            .post("/sites")
+            .json(&json!({ "domain": domain, "team_id": team_id }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        site_ids.push(site["id"].as_i64().unwrap());
+    }
+
+    let anonymous = reqwest::Client::new();
+    let response = anonymous
+        .post(format!("{}/auth/login", app.url))
+        .json(&json!({ "email": email, "password": "wrong" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = anonymous
+        .post(format!("{}/auth/login", app.url))
+        .json(&json!({ "email": email.to_uppercase(), "password": "hunter2" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = response.headers()["set-cookie"]
+        .to_str()
+        .unwrap()
+        .split(';')
+        .next()
+        .unwrap()
+        .to_string();
+    let get = |path: &str| {
+        anonymous
+            .get(format!("{}{path}", app.url))
+            .header("cookie", &cookie)
+    };
+
+    let me: Value = get("/auth/me").send().await.unwrap().json().await.unwrap();
+    assert_eq!(me["email"], email);
+    assert_eq!(me["teams"][0]["role"], "viewer");
+
+    let sites: Vec<Value> = get("/sites").send().await.unwrap().json().await.unwrap();
+    let ids: Vec<i64> = sites
+        .iter()
+        .map(|site| site["id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(ids, vec![site_ids[0]]);
+
+    let status = get(&format!("/sites/{}", site_ids[1]))
+        .send()
+        .await
+        .unwrap()
+        .status();
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let status = anonymous
+        .delete(format!("{}/sites/{}", app.url, site_ids[0]))
+        .header("cookie", &cookie)
+        .send()
+        .await
+        .unwrap()
+        .status();
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let status = anonymous
+        .post(format!("{}/auth/logout", app.url))
+        .header("cookie", &cookie)
+        .send()
+        .await
+        .unwrap()
+        .status();
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let status = get("/sites").send().await.unwrap().status();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn scopes_runs_and_groups_to_user_teams() {
+    let app = TestApp::spawn().await;
+    let suffix = &auth::generate_key()[6..14];
+    let team = app.team(&format!("team-{suffix}")).await;
+    let other_team = app.team(&format!("other-{suffix}")).await;
+    let viewer = format!("viewer-{suffix}@example.com");
+    let editor = format!("editor-{suffix}@example.com");
+    app.user(&viewer, "hunter2", &[(team, Role::Viewer)]).await;
+    app.user(&editor, "hunter2", &[(team, Role::Editor)]).await;
+
+    // A site, group, budget and run for each team
+    let mut ids = Vec::new();
+    for (domain, team_id) in [
+        (format!("https://{suffix}.example.com"), team),
+        (format!("https://{suffix}.example.org"), other_team),
+    ] {
+        let site: Value = app
+            .post("/sites")
+            .json(&json!({ "domain": domain, "team_id": team_id }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let group: Value = app
+            .post("/groups")
+            .json(&json!({ "name": domain, "site_ids": [site["id"]] }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let budget: Value = app
+            .post(&format!("/sites/{}/budgets", site["id"]))
+            .json(&json!({ "metric": "performance", "min": 0.9 }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let run: Value = app
+            .post(&format!("/groups/{}/runs", group["id"]))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        ids.push((
+            site["id"].clone(),
+            group["id"].clone(),
+            budget["id"].clone(),
+            run["id"].clone(),
+        ));
+    }
+    let (site, group, _, run) = &ids[0];
+    let (other_site, other_group, other_budget, other_run) = &ids[1];
+
+    let client = app.login(&viewer, "hunter2").await;
+    let status = |path: String| {
+        let request = client.get(format!("{}{path}", app.url));
+        async move { request.send().await.unwrap().status() }
+    };
+
+    for path in [
+        format!("/runs/{run}"),
+        format!("/runs/{run}/budgets"),
+        format!("/sites/{site}/trends"),
+        format!("/sites/{site}/budgets"),
+        format!("/groups/{group}"),
+    ] {
+        assert_eq!(status(path.clone()).await, StatusCode::OK, "{path}");
+    }
+    for path in [
+        format!("/runs/{other_run}"),
+        format!("/runs/{other_run}/export"),
+        format!("/runs/{other_run}/events"),
+        format!("/runs/{other_run}/report"),
+        format!("/sites/{other_site}/trends"),
+        format!("/sites/{other_site}/budgets"),
+        format!("/groups/{other_group}"),
+        format!("/groups/{other_group}/reports"),
+        format!("/groups/{other_group}/digest/recipients"),
+    ] {
+        assert_eq!(status(path.clone()).await, StatusCode::NOT_FOUND, "{path}");
+    }
+    for path in ["/config".to_string(), "/webhooks".to_string()] {
+        assert_eq!(status(path.clone()).await, StatusCode::FORBIDDEN, "{path}");
+    }
+
+    let groups: Vec<Value> = client
+        .get(format!("{}/groups", app.url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let visible: Vec<&Value> = groups.iter().map(|group| &group["id"]).collect();
+    assert_eq!(visible, vec![group]);
+
+    // Editors change their own team's groups and budgets only
+    let client = app.login(&editor, "hunter2").await;
+    let response = client
+        .post(format!("{}/groups/{group}/runs", app.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    let response = client
+        .post(format!("{}/groups/{other_group}/runs", app.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = client
+        .put(format!("{}/budgets/{other_budget}", app.url))
+        .json(&json!({ "metric": "performance", "min": 0.1 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = client
+        .put(format!("{}/groups/{group}/sites/{other_site}", app.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = client
+        .post(format!("{}/groups", app.url))
+        .json(&json!({ "name": format!("empty-{suffix}") }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // A group is only created once every one of its sites is allowed
+    let name = format!("mixed-{suffix}");
+    let response = client
+        .post(format!("{}/groups", app.url))
+        .json(&json!({ "name": name, "site_ids": [site, other_site] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let groups: Vec<Value> = app
+        .get("/groups")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(groups.iter().all(|group| group["name"] != name.as_str()));
+
+    // Another team's domain is neither reported nor skipped as existing
+    let summary: Value = client
+        .post(format!(
+            "{}/sites/import?team_id={team}&dry_run=true",
+            app.url
+        ))
+        .header("content-type", "application/json")
+        .body(json!([format!("https://{suffix}.example.org")]).to_string())
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(summary["created"], 1);
+    assert_eq!(summary["skipped"], 0);
+
+    let response = client
+        .post(format!("{}/webhooks", app.url))
+        .json(&json!({ "url": "https://hooks.example.com/tarin" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = client
+        .post(format!("{}/config/reload", app.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn site_crud() {
+    let app = TestApp::spawn().await;
+
+    let site: Value = app
+        .post("/sites")
+        .header("x-actor", "tests")
+        .json(&json!({ "domain": "https://crud.example.com" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let id = site["id"].as_i64().unwrap();
+    assert_eq!(site["domain"], "https://crud.example.com");
+
+    let entries: Vec<Value> = app
+        .get(&format!("/sites/{id}/audit"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(entries[0]["actor"], "key:tests (on behalf of tests)");
+
+    let response = app
+        .put(&format!("/sites/{id}"))
+        .json(&json!({ "domain": "https://www.crud.example.com" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let site: Value = app
+        .get(&format!("/sites/{id}"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(site["domain"], "https://www.crud.example.com");
+
+    let response = app.delete(&format!("/sites/{id}")).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = app.get(&format!("/sites/{id}")).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = app
+        .delete(&format!("/sites/{id}/purge"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn reads_site_audit_log() {
+    let app = TestApp::spawn().await;
+
+    // Sites created before the audit log have no entries
+    let site = sites::ActiveModel {
+        domain: Set("https://unlogged.example.com".to_string()),
+        ..Default::default()
+    }
+    .insert(&app.db)
+    .await
+    .unwrap();
+
+    let response = app
+        .get(&format!("/sites/{}/audit", site.id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let entries: Vec<Value> = response.json().await.unwrap();
+    assert!(entries.is_empty());
+
+    let response = app
+        .get(&format!("/sites/{}/audit", site.id + 1000))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn completes_group_run() {
+    let app = TestApp::spawn().await;
+    let domain = spawn_site(&["/", "/pricing"]).await;
+
+    let site: Value = app
+        .post("/sites")
+        .json(&json!({ "domain": domain }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    let group: Value = app
+        .post("/groups")
+        .json(&json!({ "name": format!("Run {domain}"), "site_ids": [site["id"]] }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    let response = app
+        .post(&format!("/groups/{}/runs", group["id"]))
+        .json(&json!({ "strategy": "desktop", "samples": 2 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let run: Value = response.json().await.unwrap();
+    let run_id = run["id"].as_i64().unwrap();
+
+    let mut status = Value::Null;
+    for _ in 0..50 {
+        let run: Value = app
+            .get(&format!("/runs/{run_id}"))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        status = run["status"].clone();
+        if status == "completed" || status == "failed" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(status, "completed");
+
+    let rows: Vec<Value> = app
+        .get(&format!("/runs/{run_id}/export?format=json"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 4);
+    assert!(rows.iter().all(|row| row["strategy"] == "desktop"));
+    assert!(rows.iter().all(|row| row["performance"] == 0.87));
+    assert_eq!(rows[0]["site"], domain);
+
+    let workbook = app
+        .get(&format!("/runs/{run_id}/export?format=xlsx"))
+        .send()
+        .await
+        .unwrap()
+        .bytes()
+        .await
+        .unwrap();
+    assert!(workbook.starts_with(b"PK"));
+
+    let response = app
+        .get(&format!("/runs/{run_id}/report"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.text().await.unwrap().contains("/pricing"));
+}
+
+// Purging relies on foreign key cascades, which SQLite only enforces when asked
+#[tokio::test]
+async fn purges_site_history() {
+    let app = TestApp::spawn().await;
+    let domain = spawn_site(&["/"]).await;
+
+    let site: Value = app
+        .post("/sites")
+        .json(&json!({ "domain": domain }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let site_id = site["id"].as_i64().unwrap() as i32;
+
+    let group: Value = app
+        .post("/groups")
+        .json(&json!({ "name": format!("Purge {domain}"), "site_ids": [site_id] }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let run: Value = app
+        .post(&format!("/groups/{}/runs", group["id"]))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let run_id = run["id"].as_i64().unwrap() as i32;
+
+    let mut status = RunStatus::Pending;
+    for _ in 0..50 {
+        status = runs::Entity::find_by_id(run_id)
+            .one(&app.db)
+            .await
+            .unwrap()
+            .unwrap()
+            .status;
+        if matches!(status, RunStatus::Completed | RunStatus::Failed) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(status, RunStatus::Completed);
+
+    let response = app
+        .delete(&format!("/sites/{site_id}"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let response = app
+        .delete(&format!("/sites/{site_id}/purge"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let reports = reports::Entity::find()
+        .filter(reports::Column::SiteId.eq(site_id))
+        .all(&app.db)
+        .await
+        .unwrap();
+    assert!(reports.is_empty());
+    let urls = site_urls::Entity::find()
+        .filter(site_urls::Column::SiteId.eq(site_id))
+        .all(&app.db)
+        .await
+        .unwrap();
+    assert!(urls.is_empty());
+}
+
+#[tokio::test]
+async fn queues_interrupted_run() {
+    let app = TestApp::spawn().await;
+    let domain = spawn_site(&["/", "/pricing"]).await;
+
+    let site: Value = app
+        .post("/sites")
+        .json(&json!({ "domain": domain }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let site_id = site["id"].as_i64().unwrap() as i32;
+
+    let group: Value = app
+        .post("/groups")
+        .json(&json!({ "name": format!("Resume {domain}"), "site_ids": [site_id] }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    // A run interrupted before the job queue, after storing one of its four samples
+    let run = runs::ActiveModel {
+        group_id: Set(group["id"].as_i64().map(|id| id as i32)),
+        status: Set(RunStatus::Interrupted),
+        strategy: Set(Strategy::Mobile),
+        samples: Set(2),
+        ..Default::default()
+    }
+    .insert(&app.db)
+    .await
+    .unwrap();
+    let url = Url::parse(&format!("{domain}/")).unwrap();
+    let site_url = site_urls::ActiveModel {
+        site_id: Set(site_id),
+        url: Set(url.to_string()),
+        ..Default::default()
+    }
+    .insert(&app.db)
+    .await
+    .unwrap();
+    let mut report: reports::ActiveModel =
+        report_from_psi(&url, Strategy::Mobile, json!({})).into();
+    report.id = NotSet;
+    report.created_at = NotSet;
+    report.run_id = Set(run.id);
+    report.site_id = Set(site_id);
+    report.site_url_id = Set(site_url.id);
+    report.insert(&app.db).await.unwrap();
+
+    let (events, _) = broadcast::channel(16);
+    let queued = queue_unfinished_runs(&app.db, &events).await.unwrap();
+    assert!(queued >= 1);
+
+    let mut status = RunStatus::Interrupted;
+    for _ in 0..50 {
+        status = runs::Entity::find_by_id(run.id)
+            .one(&app.db)
+            .await
+            .unwrap()
+            .unwrap()
+            .status;
+        if matches!(status, RunStatus::Completed | RunStatus::Failed) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(status, RunStatus::Completed);
+
+    let rows: Vec<Value> = app
+        .get(&format!("/runs/{}/export?format=json", run.id))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 4);
+}
+
+#[tokio::test]
+async fn dead_letters_failing_jobs() {
+    let app = TestApp::spawn().await;
+    // Nothing listens on the port once the listener is dropped
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let domain = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+
+    let site: Value = app
+        .post("/sites")
+        .json(&json!({ "domain": domain }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    let group: Value = app
+        .post("/groups")
+        .json(&json!({ "name": format!("Dead {domain}"), "site_ids": [site["id"]] }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    let run: Value = app
+        .post(&format!("/groups/{}/runs", group["id"]))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let run_id = run["id"].as_i64().unwrap() as i32;
+
+    let mut status = RunStatus::Pending;
+    for _ in 0..50 {
+        status = runs::Entity::find_by_id(run_id)
+            .one(&app.db)
+            .await
+            .unwrap()
+            .unwrap()
+            .status;
+        if matches!(status, RunStatus::Completed | RunStatus::Failed) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(status, RunStatus::Failed);
+
+    let jobs = audit_jobs::Entity::find()
+        .filter(audit_jobs::Column::RunId.eq(run_id))
+        .all(&app.db)
+        .await
+        .unwrap();
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(jobs[0].status, JobStatus::Dead);
+    assert_eq!(jobs[0].attempts, 2);
+    assert!(jobs[0].last_error.is_some());
+}
+
+#[tokio::test]
+async fn fails_run_without_reports() {
+    let app = TestApp::spawn().await;
+    let domain = spawn_site(&[]).await;
+
+    let site: Value = app
+        .post("/sites")
+        .json(&json!({ "domain": domain }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    let group: Value = app
+        .post("/groups")
+        .json(&json!({ "name": format!("Empty {domain}"), "site_ids": [site["id"]] }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    let run: Value = app
+        .post(&format!("/groups/{}/runs", group["id"]))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let run_id = run["id"].as_i64().unwrap() as i32;
+
+    let mut status = RunStatus::Pending;
+    for _ in 0..50 {
+        status = runs::Entity::find_by_id(run_id)
+            .one(&app.db)
+            .await
+            .unwrap()
+            .unwrap()
+            .status;
+        if matches!(status, RunStatus::Completed | RunStatus::Failed) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    // The crawl itself succeeded, but no site has anything to show for the run
+    assert_eq!(status, RunStatus::Failed);
+
+    let jobs = audit_jobs::Entity::find()
+        .filter(audit_jobs::Column::RunId.eq(run_id))
+        .all(&app.db)
+        .await
+        .unwrap();
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(jobs[0].status, JobStatus::Done);
+}
+
+#[tokio::test]
+async fn streams_configured_sites_run() {
+    let domain = spawn_site(&["/", "/contact"]).await;
+    let mut settings = common::settings();
+    settings.sites = vec![domain.clone()];
+    let app = TestApp::spawn_with(settings).await;
+
+    let response = app.post("/reports").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let events = response.headers()[LOCATION].to_str().unwrap().to_string();
+    let run: Value = response.json().await.unwrap();
+    assert_eq!(events, format!("/runs/{}/events", run["id"]));
+
+    // The stream ends after the run's completed event
+    let response = app.get(&events).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = tokio::time::timeout(Duration::from_secs(10), response.text())
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(body.matches("event: report").count(), 2);
+    assert!(body.contains("event: completed"));
+    assert!(body.contains(&format!("{domain}/contact")));
+
+    // Reading doesn't start another run
+    let response = app.get("/reports").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(runs::Entity::find().count(&app.db).await.unwrap(), 1);
+}
@@ -0,0 +1,218 @@
+use std::{future::ready, sync::Arc};
+
+use axum::{routing::get, Router};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, COOKIE, SET_COOKIE};
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, Database, DatabaseConnection};
+use tarin::{
+    accounts, app,
+    auth::{self, Scope},
+    config::{AuditBackend, Settings},
+    entities::{sea_orm_active_enums::Role, team_members, teams, users},
+    reload::LiveSettings,
+    schema,
+    shutdown::Shutdown,
+    worker::Worker,
+    AppState,
+};
+use tokio::{net::TcpListener, sync::broadcast, sync::OnceCell};
+
+static MIGRATED: OnceCell<()> = OnceCell::const_new();
+
+/// A running API server and queue worker backed by the database in
+/// `TEST_DATABASE_URL`, or a fresh in-memory SQLite database when it is unset.
+/// Audits use the built-in fake backend. `client` sends a key with every scope.
+pub struct TestApp {
+    pub url: String,
+    pub client: reqwest::Client,
+    pub db: DatabaseConnection,
+    pub shutdown: Shutdown,
+}
+
+impl TestApp {
+    pub async fn spawn() -> TestApp {
+        Self::spawn_with(settings()).await
+    }
+
+    pub async fn spawn_with(settings: Settings) -> TestApp {
+        let database_url =
+            std::env::var("TEST_DATABASE_URL").unwrap_or_else(|_| "sqlite::memory:".to_string());
+
+        let db = Database::connect(&database_url)
+            .await
+            .expect("Failed to connect to the test database");
+        // Every in-memory database starts empty, a shared one is migrated once
+        if database_url.starts_with("sqlite::memory:") {
+            migrate(&db).await;
+        } else {
+            MIGRATED.get_or_init(|| migrate(&db)).await;
+        }
+
+        let (events, _) = broadcast::channel(256);
+        let app_state = Arc::new(AppState {
+            db: Arc::new(db.clone()),
+            mailer: None,
+            settings: LiveSettings::new(settings, None, Vec::new()),
+            shutdown: Shutdown::new(),
+            events: Some(events.clone()),
+        });
+        Worker::new(
+            app_state.db.clone(),
+            events,
+            app_state.settings.clone(),
+            app_state.shutdown.clone(),
+        )
+        .spawn();
+
+        let url = serve(app(app_state.clone())).await;
+        let key = create_key(&db, &[Scope::Read, Scope::Write, Scope::Run, Scope::Admin]).await;
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {key}")).unwrap(),
+        );
+
+        TestApp {
+            url,
+            client: reqwest::Client::builder()
+                .default_headers(headers)
+                .build()
+                .unwrap(),
+            db,
+            shutdown: app_state.shutdown.clone(),
+        }
+    }
+
+    /// A new key with only `scopes`.
+    pub async fn key(&self, scopes: &[Scope]) -> String {
+        create_key(&self.db, scopes).await
+    }
+
+    pub async fn team(&self, name: &str) -> i32 {
+        teams::ActiveModel {
+            name: Set(name.to_string()),
+            ..Default::default()
+        }
+        .insert(&self.db)
+        .await
+        .expect("Failed to create a team")
+        .id
+    }
+
+    /// A user with a cheap password hash and the given team roles.
+    pub async fn user(&self, email: &str, password: &str, teams: &[(i32, Role)]) -> i32 {
+        let user = users::ActiveModel {
+            email: Set(email.to_string()),
+            password_hash: Set(Some(accounts::hash_password_with(password, 10))),
+            ..Default::default()
+        }
+        .insert(&self.db)
+        .await
+        .expect("Failed to create a user");
+
+        for (team_id, role) in teams {
+            team_members::ActiveModel {
+                team_id: Set(*team_id),
+                user_id: Set(user.id),
+                role: Set(*role),
+            }
+            .insert(&self.db)
+            .await
+            .expect("Failed to add a team member");
+        }
+
+        user.id
+    }
+
+    pub fn get(&self, path: &str) -> reqwest::RequestBuilder {
+        self.client.get(format!("{}{path}", self.url))
+    }
+
+    pub fn post(&self, path: &str) -> reqwest::RequestBuilder {
+        self.client.post(format!("{}{path}", self.url))
+    }
+
+    pub fn put(&self, path: &str) -> reqwest::RequestBuilder {
+        self.client.put(format!("{}{path}", self.url))
+    }
+
+    pub fn delete(&self, path: &str) -> reqwest::RequestBuilder {
+        self.client.delete(format!("{}{path}", self.url))
+    }
+
+    /// A client sending the session cookie of a login as `email`.
+    pub async fn login(&self, email: &str, password: &str) -> reqwest::Client {
+        let response = reqwest::Client::new()
+            .post(format!("{}/auth/login", self.url))
+            .json(&serde_json::json!({ "email": email, "password": password }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let cookie = response.headers()[SET_COOKIE]
+            .to_str()
+            .unwrap()
+            .split(';')
+            .next()
+            .unwrap()
+            .to_string();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(COOKIE, HeaderValue::from_str(&cookie).unwrap());
+        reqwest::Client::builder()
+            .default_headers(headers)
+            .build()
+            .unwrap()
+    }
+}
+
+async fn migrate(db: &DatabaseConnection) {
+    schema::prepare(db, true)
+        .await
+        .expect("Failed to run migrations");
+}
+
+async fn create_key(db: &DatabaseConnection, scopes: &[Scope]) -> String {
+    let (_, key) = auth::create_key(db, "tests", scopes)
+        .await
+        .expect("Failed to create an API key");
+    key
+}
+
+/// Defaults with the fake audit backend, failed jobs are retried straight away.
+pub fn settings() -> Settings {
+    let mut settings = Settings::default();
+    settings.audit.provider = AuditBackend::Fake;
+    settings.queue.retry_delay = 0;
+    settings.queue.max_attempts = 2;
+    settings
+}
+
+/// Serves a site whose sitemap index links to one sitemap of `paths`.
+pub async fn spawn_site(paths: &[&str]) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+
+    let index = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?><sitemapindex><sitemap><loc>{base}/sitemaps/pages.xml</loc></sitemap></sitemapindex>"#
+    );
+    let urls: String = paths
+        .iter()
+        .map(|path| format!("<url><loc>{base}{path}</loc></url>"))
+        .collect();
+    let pages = format!(r#"<?xml version="1.0" encoding="UTF-8"?><urlset>{urls}</urlset>"#);
+
+    let router = Router::new()
+        .route("/sitemaps.xml", get(move || ready(index.clone())))
+        .route("/sitemaps/pages.xml", get(move || ready(pages.clone())));
+
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    base
+}
+
+async fn serve(router: Router) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    url
+}